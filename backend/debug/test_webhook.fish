@@ -0,0 +1,29 @@
+
+set ADDR 'http://localhost:8000/api'
+
+# Local HTTP stub receiving the deliveries, run in another shell:
+# while true; printf 'HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n' | nc -l 9000; end
+
+set json '{
+    "url": "http://localhost:9000/hook",
+    "events": ["EntryCreated", "EntryUpdated", "EntryDeleted", "FieldChanged"]
+}'
+
+curl -b cookies.txt -X POST $ADDR/tables/1/webhooks \
+    -H "Content-Type: application/json" \
+    -d "$json"
+
+curl -b cookies.txt -X GET $ADDR/tables/1/webhooks
+
+set json '{
+    "entries": [
+        {"1": "Hello"}
+    ]
+}'
+
+curl -b cookies.txt -X POST $ADDR/tables/1/entries \
+    -H "Content-Type: application/json" \
+    -d "$json"
+
+# Delivery log
+curl -b cookies.txt -X GET $ADDR/tables/1/webhooks/1/deliveries
@@ -0,0 +1,208 @@
+/*
+Publish a change event on the chronicle_event channel.
+The payload is a JSON object tagged by its "type" key.
+*/
+CREATE OR REPLACE FUNCTION publish_event(event JSON)
+RETURNS VOID AS
+$$
+BEGIN
+    PERFORM pg_notify('chronicle_event', event::TEXT);
+END;
+$$ LANGUAGE plpgsql;
+
+/*
+Suffix of the event type for a trigger operation.
+*/
+CREATE OR REPLACE FUNCTION event_suffix(operation TEXT)
+RETURNS TEXT AS
+$$
+BEGIN
+    RETURN CASE operation
+        WHEN 'INSERT' THEN 'Created'
+        WHEN 'UPDATE' THEN 'Updated'
+        ELSE 'Deleted'
+    END;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+/*
+Publish entry events of a dynamic table.
+table_id: ID of the table in meta_table
+*/
+CREATE OR REPLACE FUNCTION notify_entry()
+RETURNS TRIGGER AS $$
+DECLARE
+    entry_id INT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        entry_id := OLD.entry_id;
+    ELSE
+        entry_id := NEW.entry_id;
+    END IF;
+
+    PERFORM publish_event(json_build_object(
+        'type', 'Entry' || event_suffix(TG_OP),
+        'table_id', TG_ARGV[0]::INT,
+        'entry_id', entry_id
+    ));
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+/*
+Create the notify_entry trigger.
+table_name: Name of the dynamic table for the trigger
+table_id: ID of the table in meta_table
+*/
+CREATE OR REPLACE FUNCTION trigger_notify_entry(table_name TEXT, table_id INT)
+RETURNS VOID AS
+$$
+BEGIN
+    EXECUTE format('
+        CREATE TRIGGER notify_entry
+        AFTER INSERT OR UPDATE OR DELETE
+        ON %1$s
+        FOR EACH ROW
+        EXECUTE FUNCTION notify_entry(%2$s);
+    ', table_name, table_id);
+end;
+$$ language plpgsql;
+
+DO $$
+DECLARE
+    id INT;
+BEGIN
+    FOR id IN SELECT table_id FROM meta_table LOOP
+        PERFORM trigger_notify_entry(format('"data_table"."t%s"', id), id);
+    END LOOP;
+END;
+$$;
+
+/*
+Publish table events, entries of the table are covered by notify_entry.
+*/
+CREATE OR REPLACE FUNCTION notify_table()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM publish_event(json_build_object(
+            'type', 'TableDeleted',
+            'table_id', OLD.table_id
+        ));
+    ELSE
+        PERFORM publish_event(json_build_object(
+            'type', 'TableUpdated',
+            'table_id', NEW.table_id
+        ));
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_table
+AFTER UPDATE OR DELETE ON meta_table
+FOR EACH ROW
+EXECUTE FUNCTION notify_table();
+
+/*
+Publish field events.
+*/
+CREATE OR REPLACE FUNCTION notify_field()
+RETURNS TRIGGER AS $$
+DECLARE
+    field meta_field;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        field := OLD;
+    ELSE
+        field := NEW;
+    END IF;
+
+    PERFORM publish_event(json_build_object(
+        'type', 'Field' || event_suffix(TG_OP),
+        'table_id', field.table_id,
+        'field_id', field.field_id
+    ));
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_field
+AFTER INSERT OR UPDATE OR DELETE ON meta_field
+FOR EACH ROW
+EXECUTE FUNCTION notify_field();
+
+/*
+Publish chart events.
+*/
+CREATE OR REPLACE FUNCTION notify_chart()
+RETURNS TRIGGER AS $$
+DECLARE
+    chart chart;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        chart := OLD;
+    ELSE
+        chart := NEW;
+    END IF;
+
+    PERFORM publish_event(json_build_object(
+        'type', 'Chart' || event_suffix(TG_OP),
+        'dashboard_id', chart.dashboard_id,
+        'chart_id', chart.chart_id,
+        'table_id', chart.table_id
+    ));
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_chart
+AFTER INSERT OR UPDATE OR DELETE ON chart
+FOR EACH ROW
+EXECUTE FUNCTION notify_chart();
+
+/*
+Publish a chart update when its axes change.
+Identical notifications in a transaction are sent only once,
+so replacing all the axes of a chart results in a single event.
+*/
+CREATE OR REPLACE FUNCTION notify_axis()
+RETURNS TRIGGER AS $$
+DECLARE
+    axis axis;
+    chart chart;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        axis := OLD;
+    ELSE
+        axis := NEW;
+    END IF;
+
+    SELECT * INTO chart
+    FROM chart AS c
+    WHERE c.chart_id = axis.chart_id;
+
+    -- The chart is being deleted
+    IF NOT FOUND THEN
+        RETURN NULL;
+    END IF;
+
+    PERFORM publish_event(json_build_object(
+        'type', 'ChartUpdated',
+        'dashboard_id', chart.dashboard_id,
+        'chart_id', chart.chart_id,
+        'table_id', chart.table_id
+    ));
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_axis
+AFTER INSERT OR UPDATE OR DELETE ON axis
+FOR EACH ROW
+EXECUTE FUNCTION notify_axis();
@@ -0,0 +1,61 @@
+/*
+Table events a webhook can subscribe to.
+*/
+CREATE TYPE webhook_event AS ENUM (
+    'EntryCreated',
+    'EntryUpdated',
+    'EntryDeleted',
+    'FieldChanged'
+);
+
+/*
+A URL receiving the events of a user table.
+The secret is used to sign the payloads sent to the URL.
+*/
+CREATE TABLE webhook (
+    webhook_id SERIAL PRIMARY KEY,
+    table_id INT NOT NULL REFERENCES meta_table(table_id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    events webhook_event[] NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ
+);
+
+SELECT trigger_updated_at('webhook');
+
+/*
+Status of a webhook delivery.
+*/
+CREATE TYPE delivery_status AS ENUM (
+    'Pending',
+    'Delivered',
+    'Failed'
+);
+
+/*
+Outbox of webhook deliveries, also kept as the delivery log.
+Pending deliveries are sent when next_attempt_at is reached
+and retried with backoff until they succeed or fail too many times.
+*/
+CREATE TABLE webhook_delivery (
+    delivery_id SERIAL PRIMARY KEY,
+    webhook_id INT NOT NULL REFERENCES webhook(webhook_id) ON DELETE CASCADE,
+    event webhook_event NOT NULL,
+    payload JSONB NOT NULL,
+    status delivery_status NOT NULL DEFAULT 'Pending',
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    response_status INT,
+    last_error TEXT,
+    delivered_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ
+);
+
+SELECT trigger_updated_at('webhook_delivery');
+
+CREATE INDEX webhook_delivery_pending
+ON webhook_delivery (next_attempt_at)
+WHERE status = 'Pending';
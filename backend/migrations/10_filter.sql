@@ -0,0 +1,17 @@
+/*
+A filter on the rows of the charts of a dashboard.
+A dashboard filter (chart_id is NULL) applies to every chart on the table of the field,
+otherwise the filter only applies to its chart.
+The condition is stored as JSON because it depends on the kind of filter.
+*/
+CREATE TABLE filter (
+    filter_id SERIAL PRIMARY KEY,
+    dashboard_id INT NOT NULL REFERENCES dashboard(dashboard_id) ON DELETE CASCADE,
+    chart_id INT REFERENCES chart(chart_id) ON DELETE CASCADE,
+    field_id INT NOT NULL REFERENCES meta_field(field_id) ON DELETE CASCADE,
+    filter_kind JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ
+);
+
+SELECT trigger_updated_at('filter');
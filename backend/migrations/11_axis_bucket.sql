@@ -0,0 +1,21 @@
+/*
+Unit of time used to truncate date times.
+*/
+CREATE TYPE time_unit AS ENUM (
+    'Hour',
+    'Day',
+    'Week',
+    'Month',
+    'Quarter',
+    'Year'
+);
+
+/*
+Time bucketing of a DateTime axis.
+The date times are truncated to the time_bucket in the time_zone (UTC by default).
+Empty buckets are added to the chart when fill_gaps is set.
+*/
+ALTER TABLE axis
+ADD COLUMN time_bucket time_unit,
+ADD COLUMN time_zone TEXT,
+ADD COLUMN fill_gaps BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,8 @@
+/*
+Aggregates can have options, such as the fraction of a percentile,
+so they are stored as JSON instead of an enum.
+*/
+ALTER TABLE axis
+ALTER COLUMN aggregate TYPE JSONB USING to_jsonb(aggregate::TEXT);
+
+DROP TYPE aggregate;
@@ -0,0 +1,15 @@
+/*
+New kinds of charts. The axes allowed for each kind are checked by the server.
+*/
+ALTER TYPE chart_kind ADD VALUE 'Area';
+ALTER TYPE chart_kind ADD VALUE 'StackedBar';
+ALTER TYPE chart_kind ADD VALUE 'Pie';
+ALTER TYPE chart_kind ADD VALUE 'Scatter';
+ALTER TYPE chart_kind ADD VALUE 'Heatmap';
+ALTER TYPE chart_kind ADD VALUE 'Histogram';
+ALTER TYPE chart_kind ADD VALUE 'Kpi';
+
+/*
+Detail axes were missing from the axis kinds.
+*/
+ALTER TYPE axis_kind ADD VALUE 'Detail';
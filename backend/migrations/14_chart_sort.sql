@@ -0,0 +1,21 @@
+/*
+Direction of the sort of an axis.
+*/
+CREATE TYPE sort_direction AS ENUM (
+    'Ascending',
+    'Descending'
+);
+
+/*
+The chart data is sorted by the sorted axes, in the order of the axes.
+*/
+ALTER TABLE axis
+ADD COLUMN sort sort_direction;
+
+/*
+The chart data is limited to row_limit rows,
+or to the top_n rows with the remaining rows grouped in an "Other" row.
+*/
+ALTER TABLE chart
+ADD COLUMN row_limit INT,
+ADD COLUMN top_n INT;
@@ -0,0 +1,8 @@
+/*
+Pivot tables group the value axes by row and column axes, with subtotals and grand totals.
+*/
+ALTER TYPE chart_kind ADD VALUE 'Pivot';
+
+ALTER TYPE axis_kind ADD VALUE 'Row';
+ALTER TYPE axis_kind ADD VALUE 'Column';
+ALTER TYPE axis_kind ADD VALUE 'Value';
@@ -0,0 +1,20 @@
+/*
+Where the data of a chart comes from when it is requested.
+Live: the view of the chart is queried on every request.
+Memory: the chart data is cached in the server process until its tables change.
+Materialized: the view of the chart is materialized and refreshed on a schedule.
+*/
+CREATE TYPE cache_mode AS ENUM (
+    'Live',
+    'Memory',
+    'Materialized'
+);
+
+/*
+The cached chart data is recomputed every refresh_interval seconds.
+refreshed_at is the last refresh of the materialized view.
+*/
+ALTER TABLE chart
+ADD COLUMN cache_mode cache_mode NOT NULL DEFAULT 'Live',
+ADD COLUMN refresh_interval INT,
+ADD COLUMN refreshed_at TIMESTAMPTZ;
@@ -0,0 +1,76 @@
+/*
+How often a dashboard report is sent.
+*/
+CREATE TYPE report_frequency AS ENUM (
+    'Daily',
+    'Weekly',
+    'Monthly'
+);
+
+/*
+Interval between two runs of a report frequency.
+*/
+CREATE OR REPLACE FUNCTION frequency_interval(frequency report_frequency)
+RETURNS INTERVAL AS
+$$
+    SELECT CASE frequency
+        WHEN 'Daily' THEN INTERVAL '1 day'
+        WHEN 'Weekly' THEN INTERVAL '1 week'
+        WHEN 'Monthly' THEN INTERVAL '1 month'
+    END;
+$$ LANGUAGE SQL IMMUTABLE;
+
+/*
+A dashboard sent by email to the recipients on a schedule.
+Runs are repeated every frequency after next_run_at,
+in the time zone of the report so they keep their local time.
+*/
+CREATE TABLE report (
+    report_id SERIAL PRIMARY KEY,
+    dashboard_id INT NOT NULL REFERENCES dashboard(dashboard_id) ON DELETE CASCADE,
+    recipients TEXT[] NOT NULL,
+    frequency report_frequency NOT NULL,
+    time_zone TEXT NOT NULL DEFAULT 'UTC',
+    next_run_at TIMESTAMPTZ NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ
+);
+
+SELECT trigger_updated_at('report');
+
+CREATE INDEX report_due
+ON report (next_run_at)
+WHERE is_active;
+
+/*
+Status of a report run.
+*/
+CREATE TYPE report_run_status AS ENUM (
+    'Pending',
+    'Sent',
+    'Failed'
+);
+
+/*
+Outbox of report emails, also kept as the run log.
+Pending runs are sent when next_attempt_at is reached
+and retried with backoff until they succeed or fail too many times.
+*/
+CREATE TABLE report_run (
+    run_id SERIAL PRIMARY KEY,
+    report_id INT NOT NULL REFERENCES report(report_id) ON DELETE CASCADE,
+    status report_run_status NOT NULL DEFAULT 'Pending',
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_error TEXT,
+    sent_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ
+);
+
+SELECT trigger_updated_at('report_run');
+
+CREATE INDEX report_run_pending
+ON report_run (next_attempt_at)
+WHERE status = 'Pending';
@@ -0,0 +1,18 @@
+/*
+An item placed on the grid of a dashboard, which is either a chart or a block.
+A block is a section header or a text, stored as JSON because it depends on its kind.
+The positions of the item are stored as a JSON object keyed by breakpoint,
+the item is hidden at the breakpoints without a position.
+*/
+CREATE TABLE layout_item (
+    item_id SERIAL PRIMARY KEY,
+    dashboard_id INT NOT NULL REFERENCES dashboard(dashboard_id) ON DELETE CASCADE,
+    chart_id INT UNIQUE REFERENCES chart(chart_id) ON DELETE CASCADE,
+    block JSONB,
+    positions JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ,
+    CHECK ((chart_id IS NULL) <> (block IS NULL))
+);
+
+SELECT trigger_updated_at('layout_item');
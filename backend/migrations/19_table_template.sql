@@ -0,0 +1,20 @@
+/*
+A reusable structure of a table with its fields, children and optionally its charts.
+The structure is stored as JSON since it is only read and written as a whole.
+Published templates are available to every user.
+*/
+CREATE TABLE table_template (
+    template_id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES app_user(user_id),
+    name TEXT COLLATE case_insensitive NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    content JSONB NOT NULL,
+    is_published BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ,
+    UNIQUE (user_id, name)
+);
+
+SELECT trigger_updated_at('table_template');
+
+SELECT trigger_rename_duplicate('table_template', 'template_id', 'user_id');
@@ -0,0 +1,55 @@
+/*
+Build the search document of a user table from its Text, WebLink and Enumeration fields.
+The document is a generated column indexed as a tsvector, so it is kept up to date by
+PostgreSQL when entries change. It must be rebuilt when the searchable fields change.
+Enumeration values are replaced by their labels.
+table_id_: ID of the user table
+*/
+CREATE OR REPLACE FUNCTION set_search_document(table_id_ INT)
+RETURNS VOID AS
+$$
+DECLARE
+    table_ident TEXT := format('data_table.%I', 't' || table_id_);
+    document TEXT;
+BEGIN
+    SELECT string_agg(
+        CASE f.field_kind->>'type'
+            WHEN 'Enumeration' THEN format(
+                'coalesce(CASE %I %s END, %L)',
+                'f' || f.field_id,
+                (
+                    SELECT coalesce(
+                        string_agg(format('WHEN %s THEN %L', v.key, v.value), ' '),
+                        'WHEN NULL THEN NULL'
+                    )
+                    FROM jsonb_each_text(f.field_kind->'values') AS v
+                ),
+                ''
+            )
+            ELSE format('coalesce(%I COLLATE "default", %L)', 'f' || f.field_id, '')
+        END,
+        $s$ || ' ' || $s$
+        ORDER BY f.ordering
+    )
+    INTO document
+    FROM meta_field AS f
+    WHERE f.table_id = table_id_
+        AND f.field_kind->>'type' IN ('Text', 'WebLink', 'Enumeration');
+
+    -- The index is dropped with the column
+    EXECUTE format('ALTER TABLE %s DROP COLUMN IF EXISTS search_document', table_ident);
+
+    IF document IS NOT NULL THEN
+        EXECUTE format(
+            'ALTER TABLE %s ADD COLUMN search_document TEXT GENERATED ALWAYS AS (%s) STORED',
+            table_ident, document
+        );
+        EXECUTE format(
+            'CREATE INDEX ON %s USING GIN (to_tsvector(%L, search_document))',
+            table_ident, 'simple'
+        );
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+SELECT set_search_document(table_id) FROM meta_table;
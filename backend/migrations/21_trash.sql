@@ -0,0 +1,73 @@
+/*
+Deleted tables, fields, dashboards and charts are moved to the trash of their user
+by setting their deletion date. They are kept with their data until they are purged
+after the retention period, or restored.
+A table is deleted with its descendants and restored with the ones deleted at the same time.
+*/
+ALTER TABLE meta_table ADD COLUMN deleted_at TIMESTAMPTZ;
+
+ALTER TABLE meta_field ADD COLUMN deleted_at TIMESTAMPTZ;
+
+ALTER TABLE dashboard ADD COLUMN deleted_at TIMESTAMPTZ;
+
+ALTER TABLE chart ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX meta_table_deleted ON meta_table (deleted_at) WHERE deleted_at IS NOT NULL;
+
+CREATE INDEX meta_field_deleted ON meta_field (deleted_at) WHERE deleted_at IS NOT NULL;
+
+CREATE INDEX dashboard_deleted ON dashboard (deleted_at) WHERE deleted_at IS NOT NULL;
+
+CREATE INDEX chart_deleted ON chart (deleted_at) WHERE deleted_at IS NOT NULL;
+
+/*
+Exclude the deleted fields from the search document of a table.
+table_id_: ID of the user table
+*/
+CREATE OR REPLACE FUNCTION set_search_document(table_id_ INT)
+RETURNS VOID AS
+$$
+DECLARE
+    table_ident TEXT := format('data_table.%I', 't' || table_id_);
+    document TEXT;
+BEGIN
+    SELECT string_agg(
+        CASE f.field_kind->>'type'
+            WHEN 'Enumeration' THEN format(
+                'coalesce(CASE %I %s END, %L)',
+                'f' || f.field_id,
+                (
+                    SELECT coalesce(
+                        string_agg(format('WHEN %s THEN %L', v.key, v.value), ' '),
+                        'WHEN NULL THEN NULL'
+                    )
+                    FROM jsonb_each_text(f.field_kind->'values') AS v
+                ),
+                ''
+            )
+            ELSE format('coalesce(%I COLLATE "default", %L)', 'f' || f.field_id, '')
+        END,
+        $s$ || ' ' || $s$
+        ORDER BY f.ordering
+    )
+    INTO document
+    FROM meta_field AS f
+    WHERE f.table_id = table_id_
+        AND f.deleted_at IS NULL
+        AND f.field_kind->>'type' IN ('Text', 'WebLink', 'Enumeration');
+
+    -- The index is dropped with the column
+    EXECUTE format('ALTER TABLE %s DROP COLUMN IF EXISTS search_document', table_ident);
+
+    IF document IS NOT NULL THEN
+        EXECUTE format(
+            'ALTER TABLE %s ADD COLUMN search_document TEXT GENERATED ALWAYS AS (%s) STORED',
+            table_ident, document
+        );
+        EXECUTE format(
+            'CREATE INDEX ON %s USING GIN (to_tsvector(%L, search_document))',
+            table_ident, 'simple'
+        );
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
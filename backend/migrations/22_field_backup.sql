@@ -0,0 +1,6 @@
+/*
+A backup field keeps the original cells of a field whose kind was converted.
+converted_to is the field with the converted cells.
+*/
+ALTER TABLE meta_field
+ADD COLUMN converted_to INT REFERENCES meta_field (field_id) ON DELETE SET NULL;
@@ -0,0 +1,14 @@
+/*
+Log of the structural changes of a table: fields created, renamed, converted,
+reordered or deleted, and the table renamed.
+The change is the JSON of the change kind with its details.
+*/
+CREATE TABLE schema_change (
+    schema_change_id SERIAL PRIMARY KEY,
+    table_id INT NOT NULL REFERENCES meta_table(table_id) ON DELETE CASCADE,
+    user_id INT REFERENCES app_user(user_id) ON DELETE SET NULL,
+    change JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX ON schema_change (table_id, schema_change_id);
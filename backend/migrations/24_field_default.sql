@@ -0,0 +1,6 @@
+/*
+Value of a field in the created entries when it is not given,
+as the JSON of the cell. It is also the default of the column.
+*/
+ALTER TABLE meta_field
+ADD COLUMN default_value JSONB;
@@ -0,0 +1,39 @@
+/*
+Comment on an entry of a user table.
+Comments are deleted with their entry, or with their table.
+*/
+CREATE TABLE entry_comment (
+    comment_id SERIAL PRIMARY KEY,
+    table_id INT NOT NULL REFERENCES meta_table(table_id) ON DELETE CASCADE,
+    entry_id INT NOT NULL,
+    user_id INT REFERENCES app_user(user_id) ON DELETE SET NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ
+);
+
+SELECT trigger_updated_at('entry_comment');
+
+CREATE INDEX ON entry_comment (table_id, entry_id, comment_id);
+
+/*
+User mentioned in a comment with an @username.
+*/
+CREATE TABLE comment_mention (
+    comment_id INT NOT NULL REFERENCES entry_comment(comment_id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES app_user(user_id) ON DELETE CASCADE,
+    PRIMARY KEY (comment_id, user_id)
+);
+
+/*
+In-app notification of a user.
+The notification is the JSON of the notification kind with its details.
+*/
+CREATE TABLE notification (
+    notification_id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES app_user(user_id) ON DELETE CASCADE,
+    notification JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX ON notification (user_id, notification_id);
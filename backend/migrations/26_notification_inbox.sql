@@ -0,0 +1,30 @@
+/*
+Notifications are unread until their read date is set.
+*/
+ALTER TABLE notification ADD COLUMN read_at TIMESTAMPTZ;
+
+CREATE INDEX notification_unread
+ON notification (user_id, notification_id)
+WHERE read_at IS NULL;
+
+/*
+Publish the created notifications, with their details for the open clients of the user.
+*/
+CREATE OR REPLACE FUNCTION notify_notification()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM publish_event(json_build_object(
+        'type', 'NotificationCreated',
+        'user_id', NEW.user_id,
+        'notification_id', NEW.notification_id,
+        'notification', NEW.notification
+    ));
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_notification
+AFTER INSERT ON notification
+FOR EACH ROW
+EXECUTE FUNCTION notify_notification();
@@ -0,0 +1,43 @@
+/*
+Status of a background job.
+*/
+CREATE TYPE job_status AS ENUM (
+    'Pending',
+    'Running',
+    'Completed',
+    'Failed',
+    'Cancelled'
+);
+
+/*
+Queue of the long-running work of a user, also kept as the job log.
+The job is the JSON of the job kind with its details, the input is the uploaded file.
+Pending jobs are claimed by the workers of any server instance. A running job
+whose heartbeat is too old was interrupted by a restart and is claimed again.
+The result is the file produced by the job, and the output describes it.
+*/
+CREATE TABLE job (
+    job_id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES app_user(user_id) ON DELETE CASCADE,
+    job JSONB NOT NULL,
+    status job_status NOT NULL DEFAULT 'Pending',
+    progress DOUBLE PRECISION NOT NULL DEFAULT 0,
+    attempts INT NOT NULL DEFAULT 0,
+    input BYTEA,
+    output JSONB,
+    result BYTEA,
+    error TEXT,
+    heartbeat_at TIMESTAMPTZ,
+    started_at TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ
+);
+
+SELECT trigger_updated_at('job');
+
+CREATE INDEX ON job (user_id, job_id);
+
+CREATE INDEX job_queued
+ON job (job_id)
+WHERE status IN ('Pending', 'Running');
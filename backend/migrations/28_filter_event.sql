@@ -0,0 +1,28 @@
+/*
+Publish filter events, so every server instance sees the changes of the chart data.
+*/
+CREATE OR REPLACE FUNCTION notify_filter()
+RETURNS TRIGGER AS $$
+DECLARE
+    filter filter;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        filter := OLD;
+    ELSE
+        filter := NEW;
+    END IF;
+
+    PERFORM publish_event(json_build_object(
+        'type', 'Filter' || event_suffix(TG_OP),
+        'dashboard_id', filter.dashboard_id,
+        'filter_id', filter.filter_id
+    ));
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_filter
+AFTER INSERT OR UPDATE OR DELETE ON filter
+FOR EACH ROW
+EXECUTE FUNCTION notify_filter();
@@ -0,0 +1,70 @@
+/*
+Rebuild the search document of a table only when its expression changes,
+which is when the searchable fields or the enumeration labels change.
+The expression is kept as the comment of the column to compare it.
+The fields are in the order of their creation, so reordering them keeps the document.
+table_id_: ID of the user table
+*/
+CREATE OR REPLACE FUNCTION set_search_document(table_id_ INT)
+RETURNS VOID AS
+$$
+DECLARE
+    table_ident TEXT := format('data_table.%I', 't' || table_id_);
+    document TEXT;
+BEGIN
+    SELECT string_agg(
+        CASE f.field_kind->>'type'
+            WHEN 'Enumeration' THEN format(
+                'coalesce(CASE %I %s END, %L)',
+                'f' || f.field_id,
+                (
+                    SELECT coalesce(
+                        string_agg(format('WHEN %s THEN %L', v.key, v.value), ' ' ORDER BY v.key),
+                        'WHEN NULL THEN NULL'
+                    )
+                    FROM jsonb_each_text(f.field_kind->'values') AS v
+                ),
+                ''
+            )
+            ELSE format('coalesce(%I COLLATE "default", %L)', 'f' || f.field_id, '')
+        END,
+        $s$ || ' ' || $s$
+        ORDER BY f.field_id
+    )
+    INTO document
+    FROM meta_field AS f
+    WHERE f.table_id = table_id_
+        AND f.deleted_at IS NULL
+        AND f.field_kind->>'type' IN ('Text', 'WebLink', 'Enumeration');
+
+    IF document IS NOT DISTINCT FROM (
+        SELECT col_description(a.attrelid, a.attnum)
+        FROM pg_attribute AS a
+        WHERE a.attrelid = table_ident::regclass
+            AND a.attname = 'search_document'
+            AND NOT a.attisdropped
+    ) THEN
+        RETURN;
+    END IF;
+
+    -- The index is dropped with the column
+    EXECUTE format('ALTER TABLE %s DROP COLUMN IF EXISTS search_document', table_ident);
+
+    IF document IS NOT NULL THEN
+        EXECUTE format(
+            'ALTER TABLE %s ADD COLUMN search_document TEXT GENERATED ALWAYS AS (%s) STORED',
+            table_ident, document
+        );
+        EXECUTE format(
+            'CREATE INDEX ON %s USING GIN (to_tsvector(%L, search_document))',
+            table_ident, 'simple'
+        );
+        EXECUTE format(
+            'COMMENT ON COLUMN %s.search_document IS %L',
+            table_ident, document
+        );
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+SELECT set_search_document(table_id) FROM meta_table;
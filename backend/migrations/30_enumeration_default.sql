@@ -0,0 +1,79 @@
+/*
+The default of an enumeration field is its default value, which is also the default of its column,
+instead of the default in the field kind. The default in the field kind is kept as the default value
+of the fields and template fields without one, if it is still one of the values.
+*/
+DO $$
+DECLARE
+    field RECORD;
+BEGIN
+    FOR field IN
+        SELECT field_id, table_id, field_kind->>'default_value' AS default_key
+        FROM meta_field
+        WHERE field_kind->>'type' = 'Enumeration'
+            AND default_value IS NULL
+            AND field_kind->'values' ? (field_kind->>'default_value')
+    LOOP
+        EXECUTE format(
+            'ALTER TABLE %s ALTER COLUMN %I SET DEFAULT %s',
+            format('data_table.%I', 't' || field.table_id),
+            'f' || field.field_id,
+            field.default_key::BIGINT
+        );
+    END LOOP;
+END;
+$$;
+
+UPDATE meta_field
+SET
+    default_value = coalesce(
+        default_value,
+        CASE
+            WHEN field_kind->'values' ? (field_kind->>'default_value')
+            THEN field_kind->'default_value'
+        END
+    ),
+    field_kind = field_kind - 'default_value'
+WHERE field_kind->>'type' = 'Enumeration';
+
+CREATE FUNCTION pg_temp.migrate_enumeration_default(template_table JSONB)
+RETURNS JSONB AS
+$$
+BEGIN
+    RETURN template_table || jsonb_build_object(
+        'fields', coalesce((
+            SELECT jsonb_agg(
+                CASE
+                    WHEN f.field->'field_kind'->>'type' = 'Enumeration'
+                    THEN f.field || jsonb_build_object(
+                        'field_kind', (f.field->'field_kind') - 'default_value',
+                        'default_value', coalesce(
+                            nullif(f.field->'default_value', 'null'),
+                            CASE
+                                WHEN f.field->'field_kind'->'values'
+                                    ? (f.field->'field_kind'->>'default_value')
+                                THEN f.field->'field_kind'->'default_value'
+                            END
+                        )
+                    )
+                    ELSE f.field
+                END
+                ORDER BY f.position
+            )
+            FROM jsonb_array_elements(template_table->'fields')
+                WITH ORDINALITY AS f (field, position)
+        ), '[]'),
+        'children', coalesce((
+            SELECT jsonb_agg(
+                pg_temp.migrate_enumeration_default(c.child)
+                ORDER BY c.position
+            )
+            FROM jsonb_array_elements(template_table->'children')
+                WITH ORDINALITY AS c (child, position)
+        ), '[]')
+    );
+END;
+$$ LANGUAGE plpgsql;
+
+UPDATE table_template
+SET content = pg_temp.migrate_enumeration_default(content);
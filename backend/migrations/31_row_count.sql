@@ -0,0 +1,72 @@
+/*
+Number of entries of a user table, maintained by the triggers of the table
+so the row limit is checked without counting the entries.
+*/
+CREATE TABLE table_row_count (
+    table_id INT PRIMARY KEY REFERENCES meta_table(table_id) ON DELETE CASCADE,
+    row_count BIGINT NOT NULL
+);
+
+/*
+Add the inserted entries to the row count of the table, or subtract the deleted entries.
+The argument is the ID of the table in meta_table.
+*/
+CREATE OR REPLACE FUNCTION count_rows()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        UPDATE table_row_count
+        SET row_count = row_count + (SELECT count(*) FROM inserted_rows)
+        WHERE table_id = TG_ARGV[0]::INT;
+    ELSE
+        UPDATE table_row_count
+        SET row_count = row_count - (SELECT count(*) FROM deleted_rows)
+        WHERE table_id = TG_ARGV[0]::INT;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+/*
+Count the entries of a dynamic table and maintain the count.
+table_name: Name of the dynamic table for the trigger
+table_id_: ID of the table in meta_table
+*/
+CREATE OR REPLACE FUNCTION trigger_count_rows(table_name TEXT, table_id_ INT)
+RETURNS VOID AS
+$$
+BEGIN
+    EXECUTE format('
+        INSERT INTO table_row_count (table_id, row_count)
+        SELECT %2$s, count(*)
+        FROM %1$s
+    ', table_name, table_id_);
+    EXECUTE format('
+        CREATE TRIGGER count_inserted_rows
+        AFTER INSERT
+        ON %1$s
+        REFERENCING NEW TABLE AS inserted_rows
+        FOR EACH STATEMENT
+        EXECUTE FUNCTION count_rows(%2$s);
+    ', table_name, table_id_);
+    EXECUTE format('
+        CREATE TRIGGER count_deleted_rows
+        AFTER DELETE
+        ON %1$s
+        REFERENCING OLD TABLE AS deleted_rows
+        FOR EACH STATEMENT
+        EXECUTE FUNCTION count_rows(%2$s);
+    ', table_name, table_id_);
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+DECLARE
+    id INT;
+BEGIN
+    FOR id IN SELECT table_id FROM meta_table LOOP
+        PERFORM trigger_count_rows(format('"data_table"."t%s"', id), id);
+    END LOOP;
+END;
+$$;
@@ -0,0 +1,113 @@
+/*
+Publish entry events once per statement, so bulk imports and deletions
+do not send a notification for every entry.
+A statement changing a single entry publishes the event of the entry,
+a statement changing more entries publishes an EntriesChanged event of the table.
+table_id: ID of the table in meta_table
+*/
+CREATE OR REPLACE FUNCTION notify_entries()
+RETURNS TRIGGER AS $$
+DECLARE
+    entry_count BIGINT;
+    entry_id INT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        SELECT count(*), min(e.entry_id) INTO entry_count, entry_id
+        FROM old_entries AS e;
+    ELSE
+        SELECT count(*), min(e.entry_id) INTO entry_count, entry_id
+        FROM new_entries AS e;
+    END IF;
+
+    IF entry_count = 1 THEN
+        PERFORM publish_event(json_build_object(
+            'type', 'Entry' || event_suffix(TG_OP),
+            'table_id', TG_ARGV[0]::INT,
+            'entry_id', entry_id
+        ));
+    ELSIF entry_count > 1 THEN
+        PERFORM publish_event(json_build_object(
+            'type', 'EntriesChanged',
+            'table_id', TG_ARGV[0]::INT
+        ));
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+/*
+Create the notify_entries triggers.
+table_name: Name of the dynamic table for the triggers
+table_id: ID of the table in meta_table
+*/
+CREATE OR REPLACE FUNCTION trigger_notify_entry(table_name TEXT, table_id INT)
+RETURNS VOID AS
+$$
+BEGIN
+    EXECUTE format('
+        CREATE TRIGGER notify_inserted_entries
+        AFTER INSERT
+        ON %1$s
+        REFERENCING NEW TABLE AS new_entries
+        FOR EACH STATEMENT
+        EXECUTE FUNCTION notify_entries(%2$s);
+    ', table_name, table_id);
+    EXECUTE format('
+        CREATE TRIGGER notify_updated_entries
+        AFTER UPDATE
+        ON %1$s
+        REFERENCING NEW TABLE AS new_entries
+        FOR EACH STATEMENT
+        EXECUTE FUNCTION notify_entries(%2$s);
+    ', table_name, table_id);
+    EXECUTE format('
+        CREATE TRIGGER notify_deleted_entries
+        AFTER DELETE
+        ON %1$s
+        REFERENCING OLD TABLE AS old_entries
+        FOR EACH STATEMENT
+        EXECUTE FUNCTION notify_entries(%2$s);
+    ', table_name, table_id);
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+DECLARE
+    id INT;
+BEGIN
+    FOR id IN SELECT table_id FROM meta_table LOOP
+        EXECUTE format('DROP TRIGGER notify_entry ON "data_table"."t%s"', id);
+        PERFORM trigger_notify_entry(format('"data_table"."t%s"', id), id);
+    END LOOP;
+END;
+$$;
+
+DROP FUNCTION notify_entry;
+
+/*
+Publish dashboard events, so the event streams of a dashboard end when it is deleted.
+*/
+CREATE OR REPLACE FUNCTION notify_dashboard()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM publish_event(json_build_object(
+            'type', 'DashboardDeleted',
+            'dashboard_id', OLD.dashboard_id
+        ));
+    ELSE
+        PERFORM publish_event(json_build_object(
+            'type', 'DashboardUpdated',
+            'dashboard_id', NEW.dashboard_id
+        ));
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_dashboard
+AFTER UPDATE OR DELETE ON dashboard
+FOR EACH ROW
+EXECUTE FUNCTION notify_dashboard();
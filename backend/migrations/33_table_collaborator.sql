@@ -0,0 +1,33 @@
+/*
+User with whom the owner of a table shared it.
+Collaborators can read the table and comment on its entries.
+*/
+CREATE TABLE table_collaborator (
+    table_id INT NOT NULL REFERENCES meta_table(table_id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES app_user(user_id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (table_id, user_id)
+);
+
+CREATE INDEX ON table_collaborator (user_id);
+
+/*
+Publish a table update when a collaborator is removed,
+so the event streams of the collaborator end.
+*/
+CREATE OR REPLACE FUNCTION notify_collaborator()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM publish_event(json_build_object(
+        'type', 'TableUpdated',
+        'table_id', OLD.table_id
+    ));
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_collaborator
+AFTER DELETE ON table_collaborator
+FOR EACH ROW
+EXECUTE FUNCTION notify_collaborator();
@@ -0,0 +1,6 @@
+/*
+Number of tables reserved by a running import, counted in the table limit of the user
+until the import is committed, so the limit is only locked while the tables are reserved.
+*/
+ALTER TABLE job
+ADD COLUMN reserved_tables INT NOT NULL DEFAULT 0;
@@ -0,0 +1,286 @@
+//! This module contains the in-process cache of the chart data,
+//! used by the charts with the [CacheMode::Memory] cache mode.
+//!
+//! Each server instance has its own cache, which is invalidated by the
+//! change events of the database so every instance sees the changes.
+//!
+//! [CacheMode::Memory]: crate::model::viz::CacheMode::Memory
+
+use crate::{
+    model::{events::Event, viz::ChartData},
+    Id,
+};
+use chrono::{TimeDelta, Utc};
+use hashlink::LruCache;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Maximum number of chart data cached, for all the charts and filter values.
+/// The least recently used chart data is evicted first.
+const MAX_CACHED_DATA: usize = 1000;
+
+/// Cache of the chart data, shared by the handlers and background tasks.
+#[derive(Clone)]
+pub struct ChartCache {
+    state: Arc<Mutex<CacheState>>,
+}
+
+struct CacheState {
+    /// Chart data keyed by the chart and the filter values of the request.
+    data: LruCache<(Id, String), ChartData>,
+    /// Number of invalidations so far.
+    generation: u64,
+    /// Generation of the last invalidation of each scope.
+    invalidated: HashMap<Scope, u64>,
+}
+
+/// Part of the cache affected by an invalidation.
+#[derive(PartialEq, Eq, Hash)]
+enum Scope {
+    All,
+    Chart(Id),
+    Dashboard(Id),
+    /// Charts reading from the table.
+    Table(Id),
+}
+
+impl Default for ChartCache {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(CacheState {
+                data: LruCache::new(MAX_CACHED_DATA),
+                generation: 0,
+                invalidated: HashMap::new(),
+            })),
+        }
+    }
+}
+
+impl CacheState {
+    fn invalidate(&mut self, scope: Scope, is_stale: impl Fn(&ChartData) -> bool) {
+        self.generation += 1;
+        self.invalidated.insert(scope, self.generation);
+
+        let stale_keys: Vec<_> = self
+            .data
+            .iter()
+            .filter(|(_, chart_data)| is_stale(chart_data))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in stale_keys {
+            self.data.remove(&key);
+        }
+    }
+
+    /// Check if the chart data was invalidated after the generation.
+    fn is_invalidated(&self, chart_data: &ChartData, generation: u64) -> bool {
+        let chart = &chart_data.chart;
+        [
+            Scope::All,
+            Scope::Chart(chart.chart_id),
+            Scope::Dashboard(chart.dashboard_id),
+        ]
+        .into_iter()
+        .chain(
+            chart_data
+                .axes
+                .iter()
+                .map(|axis_field| axis_field.table_id)
+                .chain([chart.table_id])
+                .map(Scope::Table),
+        )
+        .any(|scope| {
+            self.invalidated
+                .get(&scope)
+                .is_some_and(|invalidated| *invalidated > generation)
+        })
+    }
+}
+
+/// Check if the chart data reads from the table.
+fn reads_table(chart_data: &ChartData, table_id: Id) -> bool {
+    chart_data.chart.table_id == table_id
+        || chart_data
+            .axes
+            .iter()
+            .any(|axis_field| axis_field.table_id == table_id)
+}
+
+impl ChartCache {
+    /// Get the cached chart data for these filter values,
+    /// unless the refresh interval of the chart is elapsed.
+    pub fn get(&self, chart_id: Id, key: &str) -> Option<ChartData> {
+        let mut state = self.state.lock().unwrap();
+        let chart_data = state.data.get(&(chart_id, key.to_string()))?;
+        match chart_data.chart.refresh_interval {
+            Some(refresh_interval)
+                if chart_data.computed_at + TimeDelta::seconds(refresh_interval.into())
+                    <= Utc::now() =>
+            {
+                None
+            }
+            _ => Some(chart_data.clone()),
+        }
+    }
+
+    /// Get the current generation of the cache, to insert the chart data computed after this call.
+    pub fn generation(&self) -> u64 {
+        self.state.lock().unwrap().generation
+    }
+
+    /// Cache the chart data for these filter values, unless it was invalidated
+    /// after the `generation` while it was computed.
+    pub fn insert(&self, key: String, chart_data: ChartData, generation: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.is_invalidated(&chart_data, generation) {
+            return;
+        }
+        state
+            .data
+            .insert((chart_data.chart.chart_id, key), chart_data);
+    }
+
+    pub fn invalidate_chart(&self, chart_id: Id) {
+        self.state
+            .lock()
+            .unwrap()
+            .invalidate(Scope::Chart(chart_id), |chart_data| {
+                chart_data.chart.chart_id == chart_id
+            });
+    }
+
+    /// Invalidate the charts of a dashboard, for the changes of its filters.
+    pub fn invalidate_dashboard(&self, dashboard_id: Id) {
+        self.state
+            .lock()
+            .unwrap()
+            .invalidate(Scope::Dashboard(dashboard_id), |chart_data| {
+                chart_data.chart.dashboard_id == dashboard_id
+            });
+    }
+
+    /// Invalidate the charts reading from the table.
+    pub fn invalidate_table(&self, table_id: Id) {
+        self.state
+            .lock()
+            .unwrap()
+            .invalidate(Scope::Table(table_id), |chart_data| {
+                reads_table(chart_data, table_id)
+            });
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        // Every scope is older than this invalidation
+        state.invalidated.clear();
+        state.invalidate(Scope::All, |_| true);
+    }
+
+    /// Invalidate the charts affected by a change event.
+    pub fn invalidate(&self, event: &Event) {
+        match event {
+            // The missed events could have invalidated any chart
+            Event::Resync => self.clear(),
+            Event::ChartCreated { .. } => {}
+            Event::ChartUpdated { chart_id, .. } | Event::ChartDeleted { chart_id, .. } => {
+                self.invalidate_chart(*chart_id)
+            }
+            Event::FilterCreated { dashboard_id, .. }
+            | Event::FilterUpdated { dashboard_id, .. }
+            | Event::FilterDeleted { dashboard_id, .. } => self.invalidate_dashboard(*dashboard_id),
+            _ => {
+                if let Some(table_id) = event.table_id() {
+                    self.invalidate_table(table_id)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::viz::{CacheMode, Chart, ChartKind};
+
+    fn chart_data(chart_id: Id, dashboard_id: Id, table_id: Id) -> ChartData {
+        ChartData {
+            chart: Chart {
+                chart_id,
+                dashboard_id,
+                table_id,
+                name: String::new(),
+                chart_kind: ChartKind::Bar,
+                row_limit: None,
+                top_n: None,
+                cache_mode: CacheMode::Memory,
+                refresh_interval: None,
+                refreshed_at: None,
+                created_at: Utc::now(),
+                updated_at: None,
+            },
+            axes: Vec::new(),
+            filters: Vec::new(),
+            cells: Vec::new(),
+            other: None,
+            totals: None,
+            computed_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn invalidated_while_computed() {
+        let cache = ChartCache::default();
+
+        let generation = cache.generation();
+        cache.invalidate_table(3);
+        cache.insert(String::new(), chart_data(1, 2, 3), generation);
+        assert!(cache.get(1, "").is_none());
+
+        let generation = cache.generation();
+        cache.invalidate_table(4);
+        cache.invalidate_dashboard(5);
+        cache.insert(String::new(), chart_data(1, 2, 3), generation);
+        assert!(cache.get(1, "").is_some());
+
+        cache.invalidate_dashboard(2);
+        assert!(cache.get(1, "").is_none());
+    }
+
+    #[test]
+    fn cleared_while_computed() {
+        let cache = ChartCache::default();
+
+        let generation = cache.generation();
+        cache.clear();
+        cache.insert(String::new(), chart_data(1, 2, 3), generation);
+        assert!(cache.get(1, "").is_none());
+    }
+
+    #[test]
+    fn cleared_on_resync() {
+        let cache = ChartCache::default();
+        cache.insert(String::new(), chart_data(1, 2, 3), cache.generation());
+        cache.insert(String::new(), chart_data(4, 5, 6), cache.generation());
+
+        cache.invalidate(&Event::Resync);
+        assert!(cache.get(1, "").is_none());
+        assert!(cache.get(4, "").is_none());
+    }
+
+    #[test]
+    fn least_recently_used_evicted() {
+        let cache = ChartCache::default();
+        let generation = cache.generation();
+        for chart_id in 0..MAX_CACHED_DATA as Id {
+            cache.insert(String::new(), chart_data(chart_id, 1, 1), generation);
+        }
+        assert!(cache.get(0, "").is_some());
+
+        cache.insert(String::new(), chart_data(-1, 1, 1), generation);
+        assert!(cache.get(0, "").is_some());
+        assert!(cache.get(1, "").is_none());
+        assert!(cache.get(-1, "").is_some());
+    }
+}
@@ -1,5 +1,108 @@
+use anyhow::Context;
+use shuttle_runtime::SecretStore;
+use std::str::FromStr;
+
 #[derive(clap::Parser, Clone)]
 pub struct Config {
     #[clap(long, env)]
     pub database_url: String,
 }
+
+/// Resource limits of every user, set with the secrets.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub max_tables_per_user: i64,
+    pub max_fields_per_table: i64,
+    pub max_rows_per_table: i64,
+    /// Maximum size in bytes of a file uploaded to the import routes.
+    pub max_import_bytes: usize,
+    /// Maximum size in bytes of the body of the other requests.
+    pub max_body_bytes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_tables_per_user: 200,
+            max_fields_per_table: 250,
+            max_rows_per_table: 1_000_000,
+            max_import_bytes: 50 * 1024 * 1024,
+            max_body_bytes: 2 * 1024 * 1024,
+        }
+    }
+}
+
+impl Limits {
+    /// Read the limits from these optional keys, the default limit is used for missing keys:
+    /// ```toml
+    /// MAX_TABLES_PER_USER=<tables>
+    /// MAX_FIELDS_PER_TABLE=<fields>
+    /// MAX_ROWS_PER_TABLE=<rows>
+    /// MAX_IMPORT_BYTES=<bytes>
+    /// MAX_BODY_BYTES=<bytes>
+    /// ```
+    pub fn from_secrets(secrets: &SecretStore) -> anyhow::Result<Self> {
+        let default = Self::default();
+        Ok(Self {
+            max_tables_per_user: parse_secret(secrets, "MAX_TABLES_PER_USER")?
+                .unwrap_or(default.max_tables_per_user),
+            max_fields_per_table: parse_secret(secrets, "MAX_FIELDS_PER_TABLE")?
+                .unwrap_or(default.max_fields_per_table),
+            max_rows_per_table: parse_secret(secrets, "MAX_ROWS_PER_TABLE")?
+                .unwrap_or(default.max_rows_per_table),
+            max_import_bytes: parse_secret(secrets, "MAX_IMPORT_BYTES")?
+                .unwrap_or(default.max_import_bytes),
+            max_body_bytes: parse_secret(secrets, "MAX_BODY_BYTES")?
+                .unwrap_or(default.max_body_bytes),
+        })
+    }
+}
+
+fn parse_secret<T>(secrets: &SecretStore, key: &str) -> anyhow::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    secrets
+        .get(key)
+        .map(|value| {
+            value
+                .parse()
+                .with_context(|| format!("Invalid {key} secret"))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn secrets(secrets: serde_json::Value) -> SecretStore {
+        serde_json::from_value(secrets).unwrap()
+    }
+
+    #[test]
+    fn missing_limits_are_default() {
+        let limits = Limits::from_secrets(&secrets(json!({
+            "MAX_ROWS_PER_TABLE": "500",
+            "MAX_BODY_BYTES": "1024",
+        })))
+        .unwrap();
+        let default = Limits::default();
+
+        assert_eq!(limits.max_tables_per_user, default.max_tables_per_user);
+        assert_eq!(limits.max_fields_per_table, default.max_fields_per_table);
+        assert_eq!(limits.max_rows_per_table, 500);
+        assert_eq!(limits.max_import_bytes, default.max_import_bytes);
+        assert_eq!(limits.max_body_bytes, 1024);
+    }
+
+    #[test]
+    fn invalid_limit() {
+        let error =
+            Limits::from_secrets(&secrets(json!({ "MAX_TABLES_PER_USER": "many" }))).unwrap_err();
+
+        assert_eq!(error.to_string(), "Invalid MAX_TABLES_PER_USER secret");
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{model::data::Collaborator, Id};
+use sqlx::{Acquire, PgExecutor, Postgres};
+
+/// Share a table with a user. Sharing it again with the same user has no effect.
+pub async fn create_collaborator(
+    conn: impl Acquire<'_, Database = Postgres>,
+    table_id: Id,
+    user_id: Id,
+) -> sqlx::Result<Collaborator> {
+    let mut tx = conn.begin().await?;
+
+    let collaborator = sqlx::query_as(
+        r#"
+            WITH c AS (
+                INSERT INTO table_collaborator (table_id, user_id)
+                VALUES ($1, $2)
+                ON CONFLICT (table_id, user_id) DO UPDATE
+                SET table_id = EXCLUDED.table_id
+                RETURNING *
+            )
+            SELECT
+                c.table_id,
+                c.user_id,
+                u.username,
+                c.created_at
+            FROM c
+            JOIN app_user AS u
+            ON c.user_id = u.user_id
+        "#,
+    )
+    .bind(table_id)
+    .bind(user_id)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(collaborator)
+}
+
+pub async fn delete_collaborator(
+    conn: impl Acquire<'_, Database = Postgres>,
+    table_id: Id,
+    user_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            DELETE FROM table_collaborator
+            WHERE table_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(table_id)
+    .bind(user_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Get the collaborators of a table, by username.
+pub async fn get_collaborators(
+    executor: impl PgExecutor<'_>,
+    table_id: Id,
+) -> sqlx::Result<Vec<Collaborator>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                c.table_id,
+                c.user_id,
+                u.username,
+                c.created_at
+            FROM table_collaborator AS c
+            JOIN app_user AS u
+            ON c.user_id = u.user_id
+            WHERE c.table_id = $1
+            ORDER BY u.username
+        "#,
+    )
+    .bind(table_id)
+    .fetch_all(executor)
+    .await
+}
+
+/// Get the ID of the user with the username.
+pub async fn get_user_id(
+    executor: impl PgExecutor<'_>,
+    username: &str,
+) -> sqlx::Result<Option<Id>> {
+    sqlx::query_scalar(
+        r#"
+            SELECT user_id
+            FROM app_user
+            WHERE username = $1
+        "#,
+    )
+    .bind(username)
+    .fetch_optional(executor)
+    .await
+}
@@ -0,0 +1,350 @@
+use crate::{
+    db::{create_notifications, Relation},
+    model::{
+        data::{Comment, CreateComment, UpdateComment},
+        notifications::NotificationKind,
+    },
+    Id,
+};
+use sqlx::{Acquire, PgExecutor, Postgres};
+
+/// Create a comment on an entry and notify the users it mentions.
+pub async fn create_comment(
+    conn: impl Acquire<'_, Database = Postgres>,
+    table_id: Id,
+    entry_id: Id,
+    user_id: Id,
+    CreateComment { body }: CreateComment,
+    mentions: &[String],
+) -> sqlx::Result<Comment> {
+    let mut tx = conn.begin().await?;
+
+    let comment: Comment = sqlx::query_as(
+        r#"
+            WITH c AS (
+                INSERT INTO entry_comment (table_id, entry_id, user_id, body)
+                VALUES ($1, $2, $3, $4)
+                RETURNING *
+            )
+            SELECT
+                c.comment_id,
+                c.table_id,
+                c.entry_id,
+                c.user_id,
+                u.username,
+                c.body,
+                c.created_at,
+                c.updated_at
+            FROM c
+            LEFT JOIN app_user AS u
+            ON c.user_id = u.user_id
+        "#,
+    )
+    .bind(table_id)
+    .bind(entry_id)
+    .bind(user_id)
+    .bind(body)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    set_mentions(tx.as_mut(), &comment, user_id, mentions).await?;
+
+    tx.commit().await?;
+
+    Ok(comment)
+}
+
+/// Edit the body of a comment.
+/// Only the users newly mentioned by the edit are notified.
+pub async fn update_comment(
+    conn: impl Acquire<'_, Database = Postgres>,
+    comment_id: Id,
+    user_id: Id,
+    UpdateComment { body }: UpdateComment,
+    mentions: &[String],
+) -> sqlx::Result<Comment> {
+    let mut tx = conn.begin().await?;
+
+    let comment: Comment = sqlx::query_as(
+        r#"
+            WITH c AS (
+                UPDATE entry_comment
+                SET body = $1
+                WHERE comment_id = $2
+                RETURNING *
+            )
+            SELECT
+                c.comment_id,
+                c.table_id,
+                c.entry_id,
+                c.user_id,
+                u.username,
+                c.body,
+                c.created_at,
+                c.updated_at
+            FROM c
+            LEFT JOIN app_user AS u
+            ON c.user_id = u.user_id
+        "#,
+    )
+    .bind(body)
+    .bind(comment_id)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    set_mentions(tx.as_mut(), &comment, user_id, mentions).await?;
+
+    tx.commit().await?;
+
+    Ok(comment)
+}
+
+pub async fn delete_comment(
+    conn: impl Acquire<'_, Database = Postgres>,
+    comment_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            DELETE FROM entry_comment
+            WHERE comment_id = $1
+        "#,
+    )
+    .bind(comment_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Get the comments of an entry, oldest first.
+pub async fn get_comments(
+    executor: impl PgExecutor<'_>,
+    table_id: Id,
+    entry_id: Id,
+) -> sqlx::Result<Vec<Comment>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                c.comment_id,
+                c.table_id,
+                c.entry_id,
+                c.user_id,
+                u.username,
+                c.body,
+                c.created_at,
+                c.updated_at
+            FROM entry_comment AS c
+            LEFT JOIN app_user AS u
+            ON c.user_id = u.user_id
+            WHERE c.table_id = $1 AND c.entry_id = $2
+            ORDER BY c.comment_id
+        "#,
+    )
+    .bind(table_id)
+    .bind(entry_id)
+    .fetch_all(executor)
+    .await
+}
+
+/// Delete the comments of an entry.
+pub async fn delete_entry_comments(
+    executor: impl PgExecutor<'_>,
+    table_id: Id,
+    entry_id: Id,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"
+            DELETE FROM entry_comment
+            WHERE table_id = $1 AND entry_id = $2
+        "#,
+    )
+    .bind(table_id)
+    .bind(entry_id)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Check that a comment is on the entry and was written by the user.
+pub async fn check_comment_relation(
+    executor: impl PgExecutor<'_>,
+    user_id: Id,
+    table_id: Id,
+    entry_id: Id,
+    comment_id: Id,
+) -> sqlx::Result<Relation> {
+    sqlx::query_as::<_, (Id, Id, Option<Id>)>(
+        r#"
+            SELECT table_id, entry_id, user_id
+            FROM entry_comment
+            WHERE comment_id = $1
+        "#,
+    )
+    .bind(comment_id)
+    .fetch_optional(executor)
+    .await
+    .map(|comment| match comment {
+        Some((t_id, e_id, author_id)) if t_id == table_id && e_id == entry_id => {
+            if author_id == Some(user_id) {
+                Relation::Owned
+            } else {
+                Relation::NotOwned
+            }
+        }
+        _ => Relation::Absent,
+    })
+}
+
+/// Replace the mentions of a comment by the existing users with the given usernames
+/// who can access the table, and notify the users who were not mentioned before.
+/// The owner of the table and its collaborators can access it.
+/// The author is not notified of their own mentions.
+async fn set_mentions(
+    conn: impl Acquire<'_, Database = Postgres>,
+    comment: &Comment,
+    author_id: Id,
+    mentions: &[String],
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            DELETE FROM comment_mention AS m
+            USING app_user AS u
+            WHERE m.comment_id = $1
+                AND m.user_id = u.user_id
+                AND u.username <> ALL($2)
+        "#,
+    )
+    .bind(comment.comment_id)
+    .bind(mentions)
+    .execute(tx.as_mut())
+    .await?;
+
+    let mentioned_ids: Vec<Id> = sqlx::query_scalar(
+        r#"
+            INSERT INTO comment_mention (comment_id, user_id)
+            SELECT $1, u.user_id
+            FROM app_user AS u
+            WHERE u.username = ANY($2)
+                AND u.user_id <> $3
+                AND (
+                    u.user_id = (SELECT user_id FROM meta_table WHERE table_id = $4)
+                    OR EXISTS (
+                        SELECT 1
+                        FROM table_collaborator AS c
+                        WHERE c.table_id = $4 AND c.user_id = u.user_id
+                    )
+                )
+            ON CONFLICT DO NOTHING
+            RETURNING user_id
+        "#,
+    )
+    .bind(comment.comment_id)
+    .bind(mentions)
+    .bind(author_id)
+    .bind(comment.table_id)
+    .fetch_all(tx.as_mut())
+    .await?;
+
+    if !mentioned_ids.is_empty() {
+        create_notifications(
+            tx.as_mut(),
+            &mentioned_ids,
+            &NotificationKind::Mentioned {
+                table_id: comment.table_id,
+                entry_id: comment.entry_id,
+                comment_id: comment.comment_id,
+                author_id,
+            },
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{create_collaborator, create_table},
+        model::data::{CreateTable, TableIdentifier},
+    };
+    use sqlx::PgPool;
+
+    async fn create_user(pool: &PgPool, username: &str) -> sqlx::Result<Id> {
+        sqlx::query_scalar(
+            r#"
+                INSERT INTO app_user (username, password_hash)
+                VALUES ($1, '')
+                RETURNING user_id
+            "#,
+        )
+        .bind(username)
+        .fetch_one(pool)
+        .await
+    }
+
+    #[sqlx::test]
+    async fn collaborators_notified_of_mentions(pool: PgPool) -> sqlx::Result<()> {
+        let owner_id = create_user(&pool, "owner").await?;
+        let collaborator_id = create_user(&pool, "collaborator").await?;
+        create_user(&pool, "outsider").await?;
+        let table = create_table(
+            &pool,
+            owner_id,
+            CreateTable {
+                parent_id: None,
+                name: "Sales".to_string(),
+                description: String::new(),
+            },
+        )
+        .await?;
+        create_collaborator(&pool, table.table_id, collaborator_id).await?;
+        let entry_id: Id = sqlx::query_scalar(&format!(
+            r#"
+                INSERT INTO {} DEFAULT VALUES
+                RETURNING entry_id
+            "#,
+            TableIdentifier::new(table.table_id, "data_table")
+        ))
+        .fetch_one(&pool)
+        .await?;
+
+        create_comment(
+            &pool,
+            table.table_id,
+            entry_id,
+            owner_id,
+            CreateComment {
+                body: "@collaborator @outsider @owner".to_string(),
+            },
+            &[
+                "collaborator".to_string(),
+                "outsider".to_string(),
+                "owner".to_string(),
+            ],
+        )
+        .await?;
+
+        let notified_ids: Vec<Id> = sqlx::query_scalar(
+            r#"
+                SELECT user_id
+                FROM notification
+            "#,
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(notified_ids, [collaborator_id]);
+
+        Ok(())
+    }
+}
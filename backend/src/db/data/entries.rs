@@ -1,8 +1,14 @@
-use super::{entry_from_row, select_columns, set_columns};
+use super::{
+    delete_entry_comments, enqueue_webhook_deliveries, entry_from_row, select_columns,
+    set_columns,
+};
 use crate::{
     db::{data::insert_columns, Relation},
     model::{
-        data::{Entry, FieldIdentifier, FieldMetadata, TableIdentifier},
+        data::{
+            Entry, FieldIdentifier, FieldKind, FieldMetadata, TableIdentifier, WebhookEvent,
+            WebhookPayload,
+        },
         Cell,
     },
     Id,
@@ -25,12 +31,25 @@ pub async fn create_entry(
         .map(|field| FieldIdentifier::new(field.field_id))
         .collect_vec();
 
+    // Generated columns are computed by the database
+    let entry = entry
+        .into_iter()
+        .zip(&fields)
+        .filter(|(_, field)| !field.field_kind.is_generated())
+        .map(|(cell, _)| cell)
+        .collect_vec();
+    let insert_idents = fields
+        .iter()
+        .filter(|field| !field.field_kind.is_generated())
+        .map(|field| FieldIdentifier::new(field.field_id))
+        .collect_vec();
+
     let parameters = (1..=entry.len())
         .map(|i| format!("${i}"))
         .chain(parent_id.map(|_| format!("${}", entry.len() + 1)))
         .join(", ");
 
-    let insert_columns = insert_columns(parent_id.is_some(), &field_idents);
+    let insert_columns = insert_columns(parent_id.is_some(), &insert_idents);
 
     let return_columns = select_columns(parent_id.is_some(), &field_idents);
 
@@ -57,17 +76,27 @@ pub async fn create_entry(
     let row = insert_query.fetch_one(tx.as_mut()).await?;
     let entry = entry_from_row(row, &fields).unwrap();
 
+    enqueue_webhook_deliveries(
+        tx.as_mut(),
+        table_id,
+        WebhookEvent::EntryCreated,
+        &[WebhookPayload::entry(WebhookEvent::EntryCreated, table_id, &entry)],
+    )
+    .await?;
+
     tx.commit().await?;
 
     Ok(entry)
 }
 
+/// Create entries in a table, with the cells in the order of the fields.
+/// The cells which are not set take the default of their column.
 pub async fn create_entries(
     conn: impl Acquire<'_, Database = Postgres>,
     table_id: Id,
     parent_id: Option<Id>,
     fields: Vec<FieldMetadata>,
-    entries: Vec<Vec<Cell>>,
+    entries: Vec<Vec<Option<Cell>>>,
 ) -> sqlx::Result<Vec<Entry>> {
     assert!(entries
         .iter()
@@ -82,13 +111,28 @@ pub async fn create_entries(
         .map(|field| FieldIdentifier::new(field.field_id))
         .collect_vec();
 
-    let insert_columns = insert_columns(parent_id.is_some(), &field_idents);
+    // Generated columns are computed by the database
+    let insert_idents = fields
+        .iter()
+        .filter(|field| !field.field_kind.is_generated())
+        .map(|field| FieldIdentifier::new(field.field_id))
+        .collect_vec();
+
+    let insert_columns = insert_columns(parent_id.is_some(), &insert_idents);
     let return_columns = select_columns(parent_id.is_some(), &field_idents);
 
     let rows = QueryBuilder::new(format!(r#"INSERT INTO {table_ident} ({insert_columns})"#))
         .push_values(entries, |mut builder, entry| {
-            for cell in entry {
-                cell.push_bind(&mut builder);
+            for (cell, field) in entry.into_iter().zip(&fields) {
+                if field.field_kind.is_generated() {
+                    continue;
+                }
+                match cell {
+                    Some(cell) => cell.push_bind(&mut builder),
+                    None => {
+                        builder.push("DEFAULT");
+                    }
+                }
             }
             if let Some(parent_id) = parent_id {
                 builder.push_bind(parent_id);
@@ -108,6 +152,17 @@ pub async fn create_entries(
         .map(|row| entry_from_row(row, &fields).unwrap())
         .collect_vec();
 
+    enqueue_webhook_deliveries(
+        tx.as_mut(),
+        table_id,
+        WebhookEvent::EntryCreated,
+        &entries
+            .iter()
+            .map(|entry| WebhookPayload::entry(WebhookEvent::EntryCreated, table_id, entry))
+            .collect_vec(),
+    )
+    .await?;
+
     tx.commit().await?;
 
     Ok(entries)
@@ -128,7 +183,17 @@ pub async fn update_entry(
         .map(|field| FieldIdentifier::new(field.field_id))
         .collect_vec();
 
-    let set_columns = set_columns(parent_id.is_some(), &field_idents, 2);
+    // Generated columns are computed by the database and the creator of the entry is kept
+    let is_updated = |field: &FieldMetadata| {
+        !field.field_kind.is_generated() && field.field_kind.0 != FieldKind::CreatedBy
+    };
+    let set_idents = fields
+        .iter()
+        .filter(|field| is_updated(field))
+        .map(|field| FieldIdentifier::new(field.field_id))
+        .collect_vec();
+
+    let set_columns = set_columns(parent_id.is_some(), &set_idents, 2);
 
     let return_columns = select_columns(parent_id.is_some(), &field_idents);
 
@@ -144,8 +209,10 @@ pub async fn update_entry(
     );
     let mut update_query = sqlx::query(&update_query).bind(entry_id);
 
-    for cell in cells {
-        update_query = cell.bind(update_query);
+    for (cell, field) in cells.into_iter().zip(&fields) {
+        if is_updated(field) {
+            update_query = cell.bind(update_query);
+        }
     }
     if let Some(parent_id) = parent_id {
         update_query = update_query.bind(parent_id);
@@ -153,6 +220,14 @@ pub async fn update_entry(
 
     let entry = entry_from_row(update_query.fetch_one(tx.as_mut()).await?, &fields)?;
 
+    enqueue_webhook_deliveries(
+        tx.as_mut(),
+        table_id,
+        WebhookEvent::EntryUpdated,
+        &[WebhookPayload::entry(WebhookEvent::EntryUpdated, table_id, &entry)],
+    )
+    .await?;
+
     tx.commit().await?;
 
     Ok(entry)
@@ -162,19 +237,32 @@ pub async fn delete_entry(
     conn: impl Acquire<'_, Database = Postgres>,
     table_id: Id,
     entry_id: Id,
+    fields: &[FieldMetadata],
 ) -> sqlx::Result<()> {
     let mut tx = conn.begin().await?;
 
     let table_ident = TableIdentifier::new(table_id, "data_table");
 
-    sqlx::query(&format!(
+    let row = sqlx::query(&format!(
         r#"
             DELETE FROM {table_ident}
             WHERE entry_id = $1
+            RETURNING *
         "#
     ))
     .bind(entry_id)
-    .execute(tx.as_mut())
+    .fetch_one(tx.as_mut())
+    .await?;
+    let entry = entry_from_row(row, fields)?;
+
+    delete_entry_comments(tx.as_mut(), table_id, entry_id).await?;
+
+    enqueue_webhook_deliveries(
+        tx.as_mut(),
+        table_id,
+        WebhookEvent::EntryDeleted,
+        &[WebhookPayload::entry(WebhookEvent::EntryDeleted, table_id, &entry)],
+    )
     .await?;
 
     tx.commit().await?;
@@ -182,6 +270,44 @@ pub async fn delete_entry(
     Ok(())
 }
 
+pub async fn get_entry(
+    executor: impl PgExecutor<'_>,
+    table_id: Id,
+    entry_id: Id,
+    fields: &[FieldMetadata],
+) -> sqlx::Result<Entry> {
+    let table_ident = TableIdentifier::new(table_id, "data_table");
+
+    let row = sqlx::query(&format!(
+        r#"
+            SELECT *
+            FROM {table_ident}
+            WHERE entry_id = $1
+        "#
+    ))
+    .bind(entry_id)
+    .fetch_one(executor)
+    .await?;
+
+    entry_from_row(row, fields)
+}
+
+/// Prevent the changes of the entries of a table until the end of the transaction,
+/// so the entries read in the transaction are not outdated.
+pub async fn lock_entries(executor: impl PgExecutor<'_>, table_id: Id) -> sqlx::Result<()> {
+    let table_ident = TableIdentifier::new(table_id, "data_table");
+
+    sqlx::query(&format!(
+        r#"
+            LOCK TABLE {table_ident} IN SHARE ROW EXCLUSIVE MODE
+        "#
+    ))
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
 pub async fn check_entry_relation(
     executor: impl PgExecutor<'_> + Copy,
     table_id: Id,
@@ -1,15 +1,21 @@
+use super::{create_schema_changes, enqueue_webhook_deliveries, set_search_document};
 use crate::{
-    db::Relation,
+    db::{
+        quote_literal, rebuild_field_chart_views, remove_field_axes, repoint_field_axes,
+        repoint_field_filters, Relation,
+    },
     model::{
         data::{
-            CreateField, Field, FieldIdentifier, FieldKind, FieldMetadata, TableIdentifier,
-            UpdateField,
+            ConversionFailure, CreateField, DependentAxesMode, Field, FieldConversionReport, FieldIdentifier,
+            FieldKind, FieldMetadata, SchemaChange, TableIdentifier, UpdateField, WebhookEvent,
+            WebhookPayload,
         },
         Cell,
     },
     Id,
 };
 use itertools::Itertools;
+use serde_json::Value;
 use sqlx::{types::Json, Acquire, PgExecutor, Postgres, QueryBuilder, Row};
 use std::{collections::HashMap, mem::discriminant};
 use tracing::debug;
@@ -17,20 +23,25 @@ use tracing::debug;
 pub async fn create_field(
     conn: impl Acquire<'_, Database = Postgres>,
     table_id: Id,
-    CreateField { name, field_kind }: CreateField,
+    CreateField {
+        name,
+        field_kind,
+        default_value,
+    }: CreateField,
 ) -> sqlx::Result<Field> {
     let mut tx = conn.begin().await?;
 
     let field: Field = sqlx::query_as(
         r#"
-            INSERT INTO meta_field (table_id, name, field_kind)
-            VALUES ($1, $2, $3)
+            INSERT INTO meta_field (table_id, name, field_kind, default_value)
+            VALUES ($1, $2, $3, $4)
             RETURNING
                 field_id,
                 table_id,
                 name,
                 ordering,
                 field_kind,
+                default_value,
                 created_at,
                 updated_at
         "#,
@@ -38,10 +49,12 @@ pub async fn create_field(
     .bind(table_id)
     .bind(name)
     .bind(sqlx::types::Json(field_kind.clone()))
+    .bind(default_value.as_ref().map(Json))
     .fetch_one(tx.as_mut())
     .await?;
 
-    let column_type = field_kind.get_sql_type();
+    let column_type =
+        field_kind.get_sql_column_definition(default_literal(default_value.as_ref()).as_deref());
     let table_ident = TableIdentifier::new(table_id, "data_table");
     let field_ident = FieldIdentifier::new(field.field_id);
 
@@ -57,25 +70,40 @@ pub async fn create_field(
     .execute(tx.as_mut())
     .await?;
 
+    if field_kind.is_searchable() {
+        set_search_document(tx.as_mut(), table_id).await?;
+    }
+
+    enqueue_webhook_deliveries(
+        tx.as_mut(),
+        table_id,
+        WebhookEvent::FieldChanged,
+        &[WebhookPayload::field(&field)],
+    )
+    .await?;
+
     tx.commit().await?;
 
     return Ok(field);
 }
 
+/// Create the fields of a table, logged as schema changes of the user.
 pub async fn create_fields(
     conn: impl Acquire<'_, Database = Postgres>,
     table_id: Id,
+    user_id: Id,
     fields: Vec<CreateField>,
 ) -> sqlx::Result<Vec<Field>> {
     let mut tx = conn.begin().await?;
 
     let fields: Vec<Field> =
-        QueryBuilder::new(r#"INSERT INTO meta_field (table_id, name, field_kind)"#)
+        QueryBuilder::new(r#"INSERT INTO meta_field (table_id, name, field_kind, default_value)"#)
             .push_values(fields, |mut builder, field| {
                 builder
                     .push_bind(table_id)
                     .push_bind(field.name)
-                    .push_bind(Json(field.field_kind));
+                    .push_bind(Json(field.field_kind))
+                    .push_bind(field.default_value.map(Json));
             })
             .push(
                 r#"
@@ -85,6 +113,7 @@ pub async fn create_fields(
                         name,
                         ordering,
                         field_kind,
+                        default_value,
                         created_at,
                         updated_at
                 "#,
@@ -96,7 +125,11 @@ pub async fn create_fields(
     let add_column_statement = fields
         .iter()
         .map(|field| {
-            let column_type = field.field_kind.0.get_sql_type();
+            let default = default_literal(field.default_value.as_ref().map(|value| &value.0));
+            let column_type = field
+                .field_kind
+                .0
+                .get_sql_column_definition(default.as_deref());
             let field_ident = FieldIdentifier::new(field.field_id);
             format!(r#"ADD COLUMN {field_ident} {column_type}"#)
         })
@@ -113,31 +146,75 @@ pub async fn create_fields(
     .execute(tx.as_mut())
     .await?;
 
+    if fields.iter().any(|field| field.field_kind.is_searchable()) {
+        set_search_document(tx.as_mut(), table_id).await?;
+    }
+
+    enqueue_webhook_deliveries(
+        tx.as_mut(),
+        table_id,
+        WebhookEvent::FieldChanged,
+        &fields.iter().map(WebhookPayload::field).collect_vec(),
+    )
+    .await?;
+
+    create_schema_changes(
+        tx.as_mut(),
+        table_id,
+        Some(user_id),
+        &fields.iter().map(SchemaChange::field_created).collect_vec(),
+    )
+    .await?;
+
     tx.commit().await?;
 
     return Ok(fields);
 }
 
+/// Quote the default value of a field as an SQL literal, for the column default.
+fn default_literal(default_value: Option<&Value>) -> Option<String> {
+    let value = match default_value? {
+        Value::String(value) => value.clone(),
+        Value::Number(value) => value.to_string(),
+        Value::Bool(value) => value.to_string(),
+        _ => return None,
+    };
+    Some(quote_literal(&value))
+}
+
+/// Maximum number of failed cells in a conversion report.
+const CONVERSION_FAILURE_SAMPLES: usize = 10;
+/// Number of converted cells updated by a statement, with two bind parameters per cell.
+const CONVERSION_CHUNK_SIZE: usize = 10_000;
+
 pub async fn update_field(
     conn: impl Acquire<'_, Database = Postgres>,
     field_id: Id,
-    UpdateField { name, field_kind }: UpdateField,
+    UpdateField {
+        name,
+        field_kind,
+        default_value,
+        drop_backup,
+        axes,
+        ..
+    }: UpdateField,
 ) -> sqlx::Result<Field> {
     let mut tx = conn.begin().await?;
 
-    let Json(old_field_kind): Json<FieldKind> = sqlx::query_scalar(
-        r"SELECT field_kind
-        FROM meta_field
-        WHERE field_id = $1",
-    )
-    .bind(field_id)
-    .fetch_one(tx.as_mut())
-    .await?;
+    let (Json(old_field_kind), old_default_value): (Json<FieldKind>, Option<Json<Value>>) =
+        sqlx::query_as(
+            r"SELECT field_kind, default_value
+            FROM meta_field
+            WHERE field_id = $1",
+        )
+        .bind(field_id)
+        .fetch_one(tx.as_mut())
+        .await?;
 
     let mut field: Field = sqlx::query_as(
         r#"
             UPDATE meta_field
-            SET name = $1, field_kind = $2
+            SET name = $1, field_kind = $2, default_value = $4
             WHERE field_id = $3
             RETURNING
                 field_id,
@@ -145,6 +222,7 @@ pub async fn update_field(
                 name,
                 ordering,
                 field_kind,
+                default_value,
                 created_at,
                 updated_at
         "#,
@@ -152,11 +230,66 @@ pub async fn update_field(
     .bind(name)
     .bind(Json(field_kind.clone()))
     .bind(field_id)
+    .bind(default_value.as_ref().map(Json))
     .fetch_one(tx.as_mut())
     .await?;
 
+    // The converted field is created with the search document, column default
+    // and webhook deliveries
     if discriminant(&field_kind) != discriminant(&old_field_kind) {
-        field = convert_field_kind(tx.as_mut(), field, old_field_kind).await?;
+        field = convert_field_kind(tx.as_mut(), field, old_field_kind, old_default_value).await?;
+        match axes {
+            DependentAxesMode::Keep => (),
+            DependentAxesMode::Repoint => {
+                repoint_field_axes(tx.as_mut(), field_id, field.field_id).await?;
+                repoint_field_filters(tx.as_mut(), field_id, field.field_id).await?;
+            }
+            DependentAxesMode::Remove => remove_field_axes(tx.as_mut(), field_id).await?,
+        }
+        if drop_backup {
+            // The older backups are kept as backups of the converted field
+            sqlx::query(
+                r#"
+                    UPDATE meta_field
+                    SET converted_to = $1
+                    WHERE converted_to = $2
+                "#,
+            )
+            .bind(field.field_id)
+            .bind(field_id)
+            .execute(tx.as_mut())
+            .await?;
+
+            purge_field(tx.as_mut(), field_id).await?;
+        }
+    } else {
+        if !field_kind.is_auto() {
+            let table_ident = TableIdentifier::new(field.table_id, "data_table");
+            let field_ident = FieldIdentifier::new(field_id);
+            let set_default = field_kind
+                .get_sql_default(default_literal(default_value.as_ref()).as_deref())
+                .map_or_else(|| "DROP DEFAULT".to_string(), |default| format!("SET DEFAULT {default}"));
+
+            sqlx::query(&format!(
+                r#"
+                    ALTER TABLE {table_ident}
+                    ALTER COLUMN {field_ident} {set_default}
+                "#,
+            ))
+            .execute(tx.as_mut())
+            .await?;
+        }
+        if field_kind.is_searchable() {
+            set_search_document(tx.as_mut(), field.table_id).await?;
+        }
+
+        enqueue_webhook_deliveries(
+            tx.as_mut(),
+            field.table_id,
+            WebhookEvent::FieldChanged,
+            &[WebhookPayload::field(&field)],
+        )
+        .await?;
     }
 
     tx.commit().await?;
@@ -164,103 +297,415 @@ pub async fn update_field(
     Ok(field)
 }
 
-async fn convert_field_kind(
-    conn: impl Acquire<'_, Database = Postgres>,
-    field: Field,
-    old_field_kind: FieldKind,
-) -> sqlx::Result<Field> {
-    let mut tx = conn.begin().await?;
-
-    let field_ident = FieldIdentifier::new(field.field_id);
-    let table_ident = TableIdentifier::new(field.table_id, "data_table");
+/// Convert the cells of a field to another field kind, without changing the field.
+/// The cells which cannot be converted are null.
+async fn get_converted_cells(
+    executor: impl PgExecutor<'_>,
+    table_id: Id,
+    field_id: Id,
+    old_field_kind: &FieldKind,
+    field_kind: &FieldKind,
+) -> sqlx::Result<(Vec<(Id, Cell)>, FieldConversionReport)> {
+    let field_ident = FieldIdentifier::new(field_id);
+    let table_ident = TableIdentifier::new(table_id, "data_table");
     let rows = sqlx::query(&format!(
         r#"
             SELECT entry_id, {field_ident}
             FROM {table_ident}
+            ORDER BY entry_id
         "#
     ))
-    .fetch_all(tx.as_mut())
+    .fetch_all(executor)
     .await?;
 
-    let cells: Vec<(Id, Cell)> = rows
+    let mut report = FieldConversionReport {
+        total: rows.len(),
+        converted: 0,
+        empty: 0,
+        failed: 0,
+        failures: Vec::new(),
+    };
+
+    // The cells of an automatic field are populated by the database or left empty
+    if field_kind.is_auto() {
+        if field_kind.is_generated() {
+            report.converted = report.total;
+        } else {
+            report.empty = report.total;
+        }
+        return Ok((Vec::new(), report));
+    }
+
+    let cells = rows
         .into_iter()
         .map(|row| {
-            let cell = Cell::from_field_row(&row, &field_ident.unquote(), &old_field_kind)?;
-            Ok((
-                row.get("entry_id"),
-                cell.convert_field_kind(&field.field_kind.0)
-                    .unwrap_or(Cell::Null),
-            ))
+            let entry_id = row.get("entry_id");
+            let cell = Cell::from_field_row(&row, &field_ident.unquote(), old_field_kind)?;
+            let converted_cell = match cell {
+                Cell::Null => {
+                    report.empty += 1;
+                    Cell::Null
+                }
+                cell => match cell.clone().convert_field_kind(field_kind) {
+                    Some(Cell::Null) | None => {
+                        report.failed += 1;
+                        if report.failures.len() < CONVERSION_FAILURE_SAMPLES {
+                            report.failures.push(ConversionFailure { entry_id, cell });
+                        }
+                        Cell::Null
+                    }
+                    Some(converted_cell) => {
+                        report.converted += 1;
+                        converted_cell
+                    }
+                },
+            };
+            Ok((entry_id, converted_cell))
         })
         .collect::<sqlx::Result<_>>()?;
 
+    Ok((cells, report))
+}
+
+/// Report the outcome of converting the cells of a field to another field kind, without converting them.
+/// The cells are only converted if the type of the field kind changes.
+pub async fn get_field_conversion_report(
+    executor: impl PgExecutor<'_>,
+    field: &Field,
+    field_kind: &FieldKind,
+) -> sqlx::Result<FieldConversionReport> {
+    let field_kind = if discriminant(field_kind) == discriminant(&field.field_kind.0) {
+        &field.field_kind.0
+    } else {
+        field_kind
+    };
+
+    get_converted_cells(
+        executor,
+        field.table_id,
+        field.field_id,
+        &field.field_kind.0,
+        field_kind,
+    )
+    .await
+    .map(|(_, report)| report)
+}
+
+async fn convert_field_kind(
+    conn: impl Acquire<'_, Database = Postgres>,
+    field: Field,
+    old_field_kind: FieldKind,
+    old_default_value: Option<Json<Value>>,
+) -> sqlx::Result<Field> {
+    let mut tx = conn.begin().await?;
+
+    let (cells, _) = get_converted_cells(
+        tx.as_mut(),
+        field.table_id,
+        field.field_id,
+        &old_field_kind,
+        &field.field_kind.0,
+    )
+    .await?;
+
     sqlx::query(
         r#"
             UPDATE meta_field
-            SET name = name || ' (BACKUP)', field_kind = $1
+            SET name = name || ' (BACKUP)', field_kind = $1, default_value = $3
             WHERE field_id = $2
         "#,
     )
     .bind(Json(old_field_kind))
     .bind(field.field_id)
+    .bind(old_default_value)
     .execute(tx.as_mut())
     .await?;
 
+    let backup_field_id = field.field_id;
+    let table_ident = TableIdentifier::new(field.table_id, "data_table");
+
     let field = create_field(
         tx.as_mut(),
         field.table_id,
         CreateField {
             name: field.name,
             field_kind: field.field_kind.0,
+            default_value: field.default_value.map(|value| value.0),
         },
     )
     .await?;
 
-    let field_ident = FieldIdentifier::new(field.field_id);
-
-    QueryBuilder::<Postgres>::new(format!(
-        r#"
-            UPDATE {table_ident}
-            SET {field_ident} = data.cell
-            FROM (
-        "#
-    ))
-    .push_values(cells, |mut builder, (id, cell)| {
-        builder.push_bind(id);
-        cell.push_bind(&mut builder);
-    })
-    .push(format!(
+    sqlx::query(
         r#"
-            ) AS data (entry_id, cell)
-            WHERE {table_ident}.entry_id = data.entry_id
-        "#
-    ))
-    .build()
+            UPDATE meta_field
+            SET converted_to = $1
+            WHERE field_id = $2
+        "#,
+    )
+    .bind(field.field_id)
+    .bind(backup_field_id)
     .execute(tx.as_mut())
     .await?;
 
+    let field_ident = FieldIdentifier::new(field.field_id);
+    let column_type = field.field_kind.0.get_sql_type();
+
+    // The cells are updated in chunks to stay under the limit of bind parameters
+    let mut cells = cells.into_iter().peekable();
+    while cells.peek().is_some() {
+        // The type of the cells is unknown when they are all null
+        QueryBuilder::<Postgres>::new(format!(
+            r#"
+                UPDATE {table_ident}
+                SET {field_ident} = data.cell::{column_type}
+                FROM (
+            "#
+        ))
+        .push_values(
+            cells.by_ref().take(CONVERSION_CHUNK_SIZE),
+            |mut builder, (id, cell)| {
+                builder.push_bind(id);
+                cell.push_bind(&mut builder);
+            },
+        )
+        .push(format!(
+            r#"
+                ) AS data (entry_id, cell)
+                WHERE {table_ident}.entry_id = data.entry_id
+            "#
+        ))
+        .build()
+        .execute(tx.as_mut())
+        .await?;
+    }
+
     tx.commit().await?;
 
     Ok(field)
 }
 
+/// Permanently delete the backup fields of a field, with the backups of these backups.
+pub async fn drop_field_backups(
+    conn: impl Acquire<'_, Database = Postgres>,
+    field_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    for backup_field_id in get_field_backup_ids(tx.as_mut(), field_id).await? {
+        purge_field(tx.as_mut(), backup_field_id).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Get the backup fields of a field, with the backups of these backups.
+pub async fn get_field_backup_ids(
+    executor: impl PgExecutor<'_>,
+    field_id: Id,
+) -> sqlx::Result<Vec<Id>> {
+    sqlx::query_scalar(
+        r#"
+            WITH RECURSIVE backup AS (
+                SELECT field_id
+                FROM meta_field
+                WHERE converted_to = $1
+                UNION ALL
+                SELECT f.field_id
+                FROM meta_field AS f
+                JOIN backup AS b
+                ON f.converted_to = b.field_id
+            )
+            SELECT field_id FROM backup
+        "#,
+    )
+    .bind(field_id)
+    .fetch_all(executor)
+    .await
+}
+
+/// Check if charts which are not in the trash have an axis on the fields.
+pub async fn is_field_in_charts(
+    executor: impl PgExecutor<'_>,
+    field_ids: &[Id],
+) -> sqlx::Result<bool> {
+    sqlx::query_scalar(
+        r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM axis AS a
+                JOIN chart AS c
+                ON a.chart_id = c.chart_id
+                JOIN dashboard AS d
+                ON c.dashboard_id = d.dashboard_id
+                WHERE c.deleted_at IS NULL
+                    AND d.deleted_at IS NULL
+                    AND (
+                        a.field_id = ANY($1)
+                        OR (a.aggregate->'First'->>'field_id')::INT = ANY($1)
+                        OR (a.aggregate->'Last'->>'field_id')::INT = ANY($1)
+                    )
+            )
+        "#,
+    )
+    .bind(field_ids)
+    .fetch_one(executor)
+    .await
+}
+
+/// Get the charts having an axis on the field, including the sort field of an aggregate.
+pub async fn get_field_chart_ids(
+    executor: impl PgExecutor<'_>,
+    field_ids: &[Id],
+) -> sqlx::Result<Vec<Id>> {
+    sqlx::query_scalar(
+        r#"
+            SELECT DISTINCT chart_id
+            FROM axis
+            WHERE field_id = ANY($1)
+                OR (aggregate->'First'->>'field_id')::INT = ANY($1)
+                OR (aggregate->'Last'->>'field_id')::INT = ANY($1)
+        "#,
+    )
+    .bind(field_ids)
+    .fetch_all(executor)
+    .await
+}
+
+/// Move a field to the trash. The column is kept until the field is purged.
+/// The charts with axes on the field are rebuilt without them.
 pub async fn delete_field(
     conn: impl Acquire<'_, Database = Postgres>,
     field_id: Id,
 ) -> sqlx::Result<()> {
     let mut tx = conn.begin().await?;
 
-    let table_id = sqlx::query_scalar(
+    let field: Field = sqlx::query_as(
+        r#"
+            UPDATE meta_field
+            SET deleted_at = now()
+            WHERE field_id = $1
+            RETURNING
+                field_id,
+                table_id,
+                name,
+                ordering,
+                field_kind,
+                default_value,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(field_id)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    if field.field_kind.is_searchable() {
+        set_search_document(tx.as_mut(), field.table_id).await?;
+    }
+
+    rebuild_field_chart_views(tx.as_mut(), &[field_id]).await?;
+
+    enqueue_webhook_deliveries(
+        tx.as_mut(),
+        field.table_id,
+        WebhookEvent::FieldChanged,
+        &[WebhookPayload::field(&field)],
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Restore a field from the trash, with the axes of the charts on the field.
+/// The restore is logged as a schema change of the user.
+pub async fn restore_field(
+    conn: impl Acquire<'_, Database = Postgres>,
+    field_id: Id,
+    user_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    let field: Field = sqlx::query_as(
+        r#"
+            UPDATE meta_field
+            SET deleted_at = NULL
+            WHERE field_id = $1
+            RETURNING
+                field_id,
+                table_id,
+                name,
+                ordering,
+                field_kind,
+                default_value,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(field_id)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    if field.field_kind.is_searchable() {
+        set_search_document(tx.as_mut(), field.table_id).await?;
+    }
+
+    rebuild_field_chart_views(tx.as_mut(), &[field_id]).await?;
+
+    enqueue_webhook_deliveries(
+        tx.as_mut(),
+        field.table_id,
+        WebhookEvent::FieldChanged,
+        &[WebhookPayload::field(&field)],
+    )
+    .await?;
+
+    create_schema_changes(
+        tx.as_mut(),
+        field.table_id,
+        Some(user_id),
+        &[SchemaChange::FieldRestored {
+            field_id,
+            name: field.name,
+        }],
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Permanently delete a field and all cells in its column.
+/// The axes on the field are removed from their charts.
+/// The purge is logged as a schema change without user.
+pub async fn purge_field(
+    conn: impl Acquire<'_, Database = Postgres>,
+    field_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    remove_field_axes(tx.as_mut(), field_id).await?;
+
+    let (table_id, name, Json(field_kind)): (Id, String, Json<FieldKind>) = sqlx::query_as(
         r#"
             DELETE FROM meta_field
             WHERE field_id = $1
-            RETURNING table_id
+            RETURNING table_id, name, field_kind
         "#,
     )
     .bind(field_id)
     .fetch_one(tx.as_mut())
     .await?;
 
+    // The search document depends on the column
+    if field_kind.is_searchable() {
+        set_search_document(tx.as_mut(), table_id).await?;
+    }
+
     let table_ident = TableIdentifier::new(table_id, "data_table");
     let field_ident = FieldIdentifier::new(field_id);
 
@@ -273,11 +718,57 @@ pub async fn delete_field(
     .execute(tx.as_mut())
     .await?;
 
+    create_schema_changes(
+        tx.as_mut(),
+        table_id,
+        None,
+        &[SchemaChange::FieldPurged { field_id, name }],
+    )
+    .await?;
+
     tx.commit().await?;
 
     Ok(())
 }
 
+pub async fn get_field(executor: impl PgExecutor<'_>, field_id: Id) -> sqlx::Result<Field> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                field_id,
+                table_id,
+                name,
+                ordering,
+                field_kind,
+                default_value,
+                created_at,
+                updated_at
+            FROM meta_field
+            WHERE field_id = $1
+        "#,
+    )
+    .bind(field_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Get the table of a field, if the field exists.
+pub async fn get_field_table_id(
+    executor: impl PgExecutor<'_>,
+    field_id: Id,
+) -> sqlx::Result<Option<Id>> {
+    sqlx::query_scalar(
+        r#"
+            SELECT table_id
+            FROM meta_field
+            WHERE field_id = $1 AND deleted_at IS NULL
+        "#,
+    )
+    .bind(field_id)
+    .fetch_optional(executor)
+    .await
+}
+
 pub async fn get_fields(executor: impl PgExecutor<'_>, table_id: Id) -> sqlx::Result<Vec<Field>> {
     sqlx::query_as(
         r#"
@@ -287,10 +778,11 @@ pub async fn get_fields(executor: impl PgExecutor<'_>, table_id: Id) -> sqlx::Re
                 name,
                 ordering,
                 field_kind,
+                default_value,
                 created_at,
                 updated_at
             FROM meta_field
-            WHERE table_id = $1
+            WHERE table_id = $1 AND deleted_at IS NULL
         "#,
     )
     .bind(table_id)
@@ -303,7 +795,7 @@ pub async fn get_field_ids(executor: impl PgExecutor<'_>, table_id: Id) -> sqlx:
         r#"
             SELECT field_id
             FROM meta_field
-            WHERE table_id = $1
+            WHERE table_id = $1 AND deleted_at IS NULL
         "#,
     )
     .bind(table_id)
@@ -349,7 +841,7 @@ pub async fn get_fields_metadata(
                 field_id,
                 field_kind
             FROM meta_field
-            WHERE table_id = $1
+            WHERE table_id = $1 AND deleted_at IS NULL
         "#,
     )
     .bind(table_id)
@@ -366,7 +858,7 @@ pub async fn check_field_relation(
         r#"
             SELECT table_id
             FROM meta_field
-            WHERE field_id = $1
+            WHERE field_id = $1 AND deleted_at IS NULL
         "#,
     )
     .bind(field_id)
@@ -378,3 +870,102 @@ pub async fn check_field_relation(
         Some(_) => Relation::NotOwned,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{create_dashboard, create_filter, create_table, get_filters},
+        model::{
+            data::{ConversionFailureMode, CreateTable},
+            viz::{CreateDashboard, CreateFilter, FilterKind},
+        },
+    };
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn filters_repointed_to_converted_field(pool: PgPool) -> sqlx::Result<()> {
+        let user_id: Id = sqlx::query_scalar(
+            r#"
+                INSERT INTO app_user (username, password_hash)
+                VALUES ('user', '')
+                RETURNING user_id
+            "#,
+        )
+        .fetch_one(&pool)
+        .await?;
+        let table = create_table(
+            &pool,
+            user_id,
+            CreateTable {
+                parent_id: None,
+                name: "Sales".to_string(),
+                description: String::new(),
+            },
+        )
+        .await?;
+        let field = create_field(
+            &pool,
+            table.table_id,
+            CreateField {
+                name: "Amount".to_string(),
+                field_kind: FieldKind::Integer {
+                    is_required: false,
+                    range_start: None,
+                    range_end: None,
+                },
+                default_value: None,
+            },
+        )
+        .await?;
+        let dashboard = create_dashboard(
+            &pool,
+            user_id,
+            CreateDashboard {
+                name: "Sales".to_string(),
+                description: String::new(),
+            },
+        )
+        .await?;
+        create_filter(
+            &pool,
+            dashboard.dashboard_id,
+            CreateFilter {
+                chart_id: None,
+                field_id: field.field_id,
+                filter_kind: FilterKind::NumberRange {
+                    start: None,
+                    end: None,
+                },
+            },
+        )
+        .await?;
+
+        let converted_field = update_field(
+            &pool,
+            field.field_id,
+            UpdateField {
+                name: "Amount".to_string(),
+                field_kind: FieldKind::Float {
+                    is_required: false,
+                    range_start: None,
+                    range_end: None,
+                    scientific_notation: false,
+                    number_precision: None,
+                    number_scale: None,
+                },
+                default_value: None,
+                on_failure: ConversionFailureMode::SetNull,
+                drop_backup: true,
+                axes: DependentAxesMode::Repoint,
+            },
+        )
+        .await?;
+
+        let filters = get_filters(&pool, dashboard.dashboard_id).await?;
+        assert_eq!(filters.len(), 1);
+        assert_eq!(filters[0].field_id, converted_field.field_id);
+
+        Ok(())
+    }
+}
@@ -1,8 +1,14 @@
 //! Query functions for the Data Management feature.
 
+mod collaborators;
+mod comments;
 mod entries;
 mod fields;
+mod schema;
+mod search;
 mod tables;
+mod templates;
+mod webhooks;
 
 use crate::model::{
     data::{Entry, FieldIdentifier, FieldMetadata},
@@ -10,7 +16,7 @@ use crate::model::{
 };
 use itertools::Itertools;
 use sqlx::{postgres::PgRow, Row};
-pub use {entries::*, fields::*, tables::*};
+pub use {collaborators::*, comments::*, entries::*, fields::*, schema::*, search::*, tables::*, templates::*, webhooks::*};
 
 fn select_columns(with_parent: bool, field_idents: &[FieldIdentifier]) -> String {
     field_idents
@@ -0,0 +1,63 @@
+use crate::{
+    model::data::{SchemaChange, SchemaChangeEntry},
+    Id,
+};
+use sqlx::{types::Json, Acquire, PgExecutor, Postgres};
+
+/// Add the changes to the schema change log of a table, in order.
+/// The changes made by the background tasks have no user.
+pub async fn create_schema_changes(
+    conn: impl Acquire<'_, Database = Postgres>,
+    table_id: Id,
+    user_id: Option<Id>,
+    changes: &[SchemaChange],
+) -> sqlx::Result<Vec<SchemaChangeEntry>> {
+    let mut tx = conn.begin().await?;
+
+    let entries = sqlx::query_as(
+        r#"
+            INSERT INTO schema_change (table_id, user_id, change)
+            SELECT $1, $2, c.change
+            FROM unnest($3::jsonb[]) WITH ORDINALITY AS c (change, position)
+            ORDER BY c.position
+            RETURNING
+                schema_change_id,
+                table_id,
+                user_id,
+                change,
+                created_at
+        "#,
+    )
+    .bind(table_id)
+    .bind(user_id)
+    .bind(changes.iter().map(Json).collect::<Vec<_>>())
+    .fetch_all(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(entries)
+}
+
+/// Get the schema change log of a table, oldest first.
+pub async fn get_schema_changes(
+    executor: impl PgExecutor<'_>,
+    table_id: Id,
+) -> sqlx::Result<Vec<SchemaChangeEntry>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                schema_change_id,
+                table_id,
+                user_id,
+                change,
+                created_at
+            FROM schema_change
+            WHERE table_id = $1
+            ORDER BY schema_change_id
+        "#,
+    )
+    .bind(table_id)
+    .fetch_all(executor)
+    .await
+}
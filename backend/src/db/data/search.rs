@@ -0,0 +1,109 @@
+use crate::{
+    model::data::{EntrySearchResult, TableIdentifier, TableSearchResult},
+    Id,
+};
+use itertools::Itertools;
+use sqlx::{Acquire, FromRow, PgExecutor, Postgres, QueryBuilder};
+use std::collections::HashMap;
+
+/// Rebuild the search document of a table after its searchable fields changed.
+/// The document and its index are kept if the fields or labels it is built from are unchanged.
+pub async fn set_search_document(
+    conn: impl Acquire<'_, Database = Postgres>,
+    table_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query("SELECT set_search_document($1)")
+        .bind(table_id)
+        .execute(tx.as_mut())
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Search the entries of every table of the user with a web search query.
+///
+/// Tables are ordered by their best match and at most `limit` entries are returned per table.
+pub async fn search_entries(
+    executor: impl PgExecutor<'_> + Copy,
+    user_id: Id,
+    query: &str,
+    limit: i64,
+) -> sqlx::Result<Vec<TableSearchResult>> {
+    let tables: Vec<(Id, String)> = sqlx::query_as(
+        r#"
+            SELECT DISTINCT t.table_id, t.name
+            FROM meta_table AS t
+            JOIN meta_field AS f
+            ON t.table_id = f.table_id
+            WHERE t.user_id = $1
+                AND t.deleted_at IS NULL
+                AND f.deleted_at IS NULL
+                AND f.field_kind->>'type' IN ('Text', 'WebLink', 'Enumeration')
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(executor)
+    .await?;
+
+    if tables.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut builder = QueryBuilder::new("");
+    let mut separated = builder.separated(" UNION ALL ");
+    for (table_id, _) in &tables {
+        let table_ident = TableIdentifier::new(*table_id, "data_table");
+        separated.push(format!(
+            r#"
+                (
+                    SELECT
+                        {table_id} AS table_id,
+                        entry_id,
+                        ts_rank(to_tsvector('simple', search_document), q) AS rank,
+                        ts_headline('simple', search_document, q) AS headline
+                    FROM {table_ident}, websearch_to_tsquery('simple', 
+            "#
+        ));
+        separated.push_bind_unseparated(query);
+        separated.push_unseparated(
+            r#"
+                    ) AS q
+                    WHERE to_tsvector('simple', search_document) @@ q
+                    ORDER BY rank DESC, entry_id
+                    LIMIT 
+            "#,
+        );
+        separated.push_bind_unseparated(limit);
+        separated.push_unseparated(")");
+    }
+
+    #[derive(FromRow)]
+    struct SearchRow {
+        table_id: Id,
+        #[sqlx(flatten)]
+        entry: EntrySearchResult,
+    }
+
+    let rows: Vec<SearchRow> = builder.build_query_as().fetch_all(executor).await?;
+
+    let mut names: HashMap<Id, String> = tables.into_iter().collect();
+    Ok(rows
+        .into_iter()
+        .into_group_map_by(|row| row.table_id)
+        .into_iter()
+        .map(|(table_id, rows)| TableSearchResult {
+            table_id,
+            name: names.remove(&table_id).unwrap_or_default(),
+            entries: rows
+                .into_iter()
+                .map(|row| row.entry)
+                .sorted_by(|a, b| b.rank.total_cmp(&a.rank))
+                .collect(),
+        })
+        .sorted_by(|a, b| b.entries[0].rank.total_cmp(&a.entries[0].rank))
+        .collect())
+}
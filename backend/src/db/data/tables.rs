@@ -1,15 +1,19 @@
-use super::{entry_from_row, select_columns};
+use super::{
+    create_field, create_schema_changes, enqueue_webhook_deliveries, entry_from_row, get_fields,
+    select_columns,
+};
 use crate::{
-    db::Relation,
-    model::{data::{
-        CreateTable, Field, FieldIdentifier, FieldMetadata, Table, TableData, TableIdentifier,
-        UpdateTable,
-    }, viz::ChartIdentifier},
+    db::{drop_chart_view, rebuild_field_chart_views, remove_field_axes, Relation},
+    model::data::{
+        CreateField, CreateTable, Field, FieldIdentifier, FieldMetadata, SchemaChange, Table,
+        TableData, TableIdentifier, UpdateTable, WebhookEvent, WebhookPayload,
+    },
     Id,
 };
 use futures::future::join_all;
 use itertools::Itertools;
 use sqlx::{Acquire, PgExecutor, Postgres};
+use std::collections::{HashMap, VecDeque};
 
 pub async fn create_table(
     conn: impl Acquire<'_, Database = Postgres>,
@@ -68,6 +72,16 @@ pub async fn create_table(
         .execute(tx.as_mut())
         .await?;
 
+    sqlx::query(&format!(r#"SELECT trigger_notify_entry('{table_ident}', $1)"#))
+        .bind(table.table_id)
+        .execute(tx.as_mut())
+        .await?;
+
+    sqlx::query(&format!(r#"SELECT trigger_count_rows('{table_ident}', $1)"#))
+        .bind(table.table_id)
+        .execute(tx.as_mut())
+        .await?;
+
     tx.commit().await?;
 
     Ok(table)
@@ -106,9 +120,123 @@ pub async fn update_table(
     Ok(table)
 }
 
+/// Move a table and its descendants to the trash.
+/// The charts with axes on their fields are rebuilt without them.
+/// The deletion is logged as a schema change of the user for each table.
 pub async fn delete_table(
     conn: impl Acquire<'_, Database = Postgres>,
     table_id: Id,
+    user_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    let table_ids: Vec<Id> = sqlx::query_scalar(
+        r#"
+            WITH RECURSIVE descendant AS (
+                SELECT table_id
+                FROM meta_table
+                WHERE table_id = $1
+                UNION ALL
+                SELECT t.table_id
+                FROM meta_table AS t
+                JOIN descendant AS d
+                ON t.parent_id = d.table_id
+                WHERE t.deleted_at IS NULL
+            )
+            UPDATE meta_table
+            SET deleted_at = now()
+            WHERE table_id IN (SELECT table_id FROM descendant)
+            RETURNING table_id
+        "#,
+    )
+    .bind(table_id)
+    .fetch_all(tx.as_mut())
+    .await?;
+
+    let field_ids = get_all_field_ids(tx.as_mut(), &table_ids).await?;
+    rebuild_field_chart_views(tx.as_mut(), &field_ids).await?;
+
+    for table_id in table_ids {
+        create_schema_changes(
+            tx.as_mut(),
+            table_id,
+            Some(user_id),
+            &[SchemaChange::TableDeleted],
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Restore a table from the trash with the descendants deleted at the same time.
+///
+/// The charts with axes on their fields are rebuilt with them. The fields of the restored tables are delivered to their webhooks as changed.
+/// The restore is logged as a schema change of the user for each table.
+pub async fn restore_table(
+    conn: impl Acquire<'_, Database = Postgres>,
+    table_id: Id,
+    user_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    let table_ids: Vec<Id> = sqlx::query_scalar(
+        r#"
+            WITH RECURSIVE descendant AS (
+                SELECT table_id, deleted_at
+                FROM meta_table
+                WHERE table_id = $1
+                UNION ALL
+                SELECT t.table_id, t.deleted_at
+                FROM meta_table AS t
+                JOIN descendant AS d
+                ON t.parent_id = d.table_id
+                WHERE t.deleted_at = d.deleted_at
+            )
+            UPDATE meta_table
+            SET deleted_at = NULL
+            WHERE table_id IN (SELECT table_id FROM descendant)
+            RETURNING table_id
+        "#,
+    )
+    .bind(table_id)
+    .fetch_all(tx.as_mut())
+    .await?;
+
+    let field_ids = get_all_field_ids(tx.as_mut(), &table_ids).await?;
+    rebuild_field_chart_views(tx.as_mut(), &field_ids).await?;
+
+    for table_id in table_ids {
+        let fields = get_fields(tx.as_mut(), table_id).await?;
+        enqueue_webhook_deliveries(
+            tx.as_mut(),
+            table_id,
+            WebhookEvent::FieldChanged,
+            &fields.iter().map(WebhookPayload::field).collect_vec(),
+        )
+        .await?;
+        create_schema_changes(
+            tx.as_mut(),
+            table_id,
+            Some(user_id),
+            &[SchemaChange::TableRestored],
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Permanently delete a table, including all fields, entries and charts.
+/// The axes of the other charts on its fields are removed from their charts.
+/// The children of the table must be purged first.
+pub async fn purge_table(
+    conn: impl Acquire<'_, Database = Postgres>,
+    table_id: Id,
 ) -> sqlx::Result<()> {
     let mut tx = conn.begin().await?;
 
@@ -124,10 +252,12 @@ pub async fn delete_table(
     .await?;
 
     for chart_id in chart_ids {
-        let chart_ident = ChartIdentifier::new(chart_id, "data_view");
-        sqlx::query(&format!(r#"DROP VIEW {chart_ident}"#))
-            .execute(tx.as_mut())
-            .await?;
+        drop_chart_view(tx.as_mut(), chart_id).await?;
+    }
+
+    // The views of the charts of the related tables must not depend on the dropped table
+    for field_id in get_all_field_ids(tx.as_mut(), &[table_id]).await? {
+        remove_field_axes(tx.as_mut(), field_id).await?;
     }
 
     sqlx::query(
@@ -142,7 +272,7 @@ pub async fn delete_table(
 
     let table_ident = TableIdentifier::new(table_id, "data_table");
 
-    sqlx::query(&format!(r#"DROP TABLE {table_ident} CASCADE"#))
+    sqlx::query(&format!(r#"DROP TABLE {table_ident}"#))
         .execute(tx.as_mut())
         .await?;
 
@@ -151,6 +281,130 @@ pub async fn delete_table(
     Ok(())
 }
 
+/// Copy a table with its fields and children, and optionally their entries.
+///
+/// The copied entries keep their IDs so the parents of the entries of the children
+/// are the copied entries. The copies are renamed if their names are taken.
+/// The copies have no webhooks yet, so the copied entries are inserted
+/// without webhook deliveries. The copied fields are logged as created by the owner.
+pub async fn copy_table(
+    conn: impl Acquire<'_, Database = Postgres>,
+    table_id: Id,
+    name: Option<String>,
+    include_entries: bool,
+) -> sqlx::Result<Table> {
+    let mut tx = conn.begin().await?;
+
+    let mut root_copy = None;
+    // Parents are copied before their children
+    let mut tables = VecDeque::from([(table_id, None)]);
+    while let Some((table_id, parent_id)) = tables.pop_front() {
+        let table = get_table(tx.as_mut(), table_id).await?;
+        let name = match (&root_copy, &name) {
+            (None, Some(name)) => name.clone(),
+            _ => table.name,
+        };
+        let copy = create_table(
+            tx.as_mut(),
+            table.user_id,
+            CreateTable {
+                parent_id,
+                name,
+                description: table.description,
+            },
+        )
+        .await?;
+
+        let mut columns = Vec::new();
+        let mut changes = Vec::new();
+        let fields = get_fields(tx.as_mut(), table_id).await?;
+        for field in fields.into_iter().sorted_by_key(|field| field.ordering) {
+            let field_copy = create_field(
+                tx.as_mut(),
+                copy.table_id,
+                CreateField {
+                    name: field.name,
+                    field_kind: field.field_kind.0,
+                    default_value: field.default_value.map(|value| value.0),
+                },
+            )
+            .await?;
+            changes.push(SchemaChange::field_created(&field_copy));
+            // Generated columns are computed again in the copy
+            if !field_copy.field_kind.is_generated() {
+                columns.push((
+                    FieldIdentifier::new(field.field_id),
+                    FieldIdentifier::new(field_copy.field_id),
+                ));
+            }
+        }
+
+        create_schema_changes(tx.as_mut(), copy.table_id, Some(copy.user_id), &changes).await?;
+
+        if include_entries {
+            let table_ident = TableIdentifier::new(table_id, "data_table");
+            let copy_ident = TableIdentifier::new(copy.table_id, "data_table");
+            let (field_idents, copy_idents): (Vec<_>, Vec<_>) = columns.into_iter().unzip();
+
+            sqlx::query(&format!(
+                r#"
+                    INSERT INTO {copy_ident} ({})
+                    SELECT {}
+                    FROM {table_ident}
+                    ORDER BY entry_id
+                "#,
+                select_columns(parent_id.is_some(), &copy_idents),
+                select_columns(parent_id.is_some(), &field_idents),
+            ))
+            .execute(tx.as_mut())
+            .await?;
+
+            sqlx::query(&format!(
+                r#"
+                    SELECT setval(
+                        pg_get_serial_sequence('{copy_ident}', 'entry_id'),
+                        COALESCE(MAX(entry_id), 0) + 1,
+                        false
+                    )
+                    FROM {copy_ident}
+                "#,
+            ))
+            .execute(tx.as_mut())
+            .await?;
+        }
+
+        for child in get_table_children(tx.as_mut(), table_id).await? {
+            tables.push_back((child.table_id, Some(copy.table_id)));
+        }
+
+        root_copy.get_or_insert(copy);
+    }
+
+    tx.commit().await?;
+
+    Ok(root_copy.expect("the copied table should be the first one"))
+}
+
+pub async fn get_table(executor: impl PgExecutor<'_>, table_id: Id) -> sqlx::Result<Table> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                table_id,
+                user_id,
+                parent_id,
+                name,
+                description,
+                created_at,
+                updated_at
+            FROM meta_table
+            WHERE table_id = $1
+        "#,
+    )
+    .bind(table_id)
+    .fetch_one(executor)
+    .await
+}
+
 pub async fn get_table_parent_id(executor: impl PgExecutor<'_>, table_id: Id) -> sqlx::Result<Id> {
     sqlx::query_scalar(
         r#"
@@ -164,6 +418,60 @@ pub async fn get_table_parent_id(executor: impl PgExecutor<'_>, table_id: Id) ->
     .await
 }
 
+/// Get the fields of the tables, including the fields in the trash and the backups.
+async fn get_all_field_ids(
+    executor: impl PgExecutor<'_>,
+    table_ids: &[Id],
+) -> sqlx::Result<Vec<Id>> {
+    sqlx::query_scalar(
+        r#"
+            SELECT field_id
+            FROM meta_field
+            WHERE table_id = ANY($1)
+        "#,
+    )
+    .bind(table_ids)
+    .fetch_all(executor)
+    .await
+}
+
+/// Get the ancestors and descendants of a table, including itself, mapped to their parent.
+pub async fn get_related_tables(
+    executor: impl PgExecutor<'_>,
+    table_id: Id,
+) -> sqlx::Result<HashMap<Id, Option<Id>>> {
+    sqlx::query_as::<_, (Id, Option<Id>)>(
+        r#"
+            WITH RECURSIVE ancestor AS (
+                SELECT table_id, parent_id
+                FROM meta_table
+                WHERE table_id = $1
+                UNION ALL
+                SELECT t.table_id, t.parent_id
+                FROM meta_table AS t
+                JOIN ancestor AS a
+                ON t.table_id = a.parent_id
+            ), descendant AS (
+                SELECT table_id, parent_id
+                FROM meta_table
+                WHERE parent_id = $1
+                UNION ALL
+                SELECT t.table_id, t.parent_id
+                FROM meta_table AS t
+                JOIN descendant AS d
+                ON t.parent_id = d.table_id
+            )
+            SELECT table_id, parent_id FROM ancestor
+            UNION
+            SELECT table_id, parent_id FROM descendant
+        "#,
+    )
+    .bind(table_id)
+    .fetch_all(executor)
+    .await
+    .map(|rows| rows.into_iter().collect())
+}
+
 pub async fn get_tables(executor: impl PgExecutor<'_>, user_id: Id) -> sqlx::Result<Vec<Table>> {
     sqlx::query_as(
         r#"
@@ -176,7 +484,7 @@ pub async fn get_tables(executor: impl PgExecutor<'_>, user_id: Id) -> sqlx::Res
                 created_at,
                 updated_at
             FROM meta_table
-            WHERE user_id = $1
+            WHERE user_id = $1 AND deleted_at IS NULL
         "#,
     )
     .bind(user_id)
@@ -199,7 +507,7 @@ pub async fn get_table_children(
                 created_at,
                 updated_at
             FROM meta_table
-            WHERE parent_id = $1
+            WHERE parent_id = $1 AND deleted_at IS NULL
         "#,
     )
     .bind(table_id)
@@ -237,10 +545,11 @@ pub async fn get_table_data(
                 name,
                 ordering,
                 field_kind,
+                default_value,
                 created_at,
                 updated_at
             FROM meta_field
-            WHERE table_id = $1
+            WHERE table_id = $1 AND deleted_at IS NULL
             ORDER BY field_id
         "#,
     )
@@ -289,7 +598,7 @@ pub async fn get_table_data(
         r#"
             SELECT table_id
             FROM meta_table
-            WHERE parent_id = $1
+            WHERE parent_id = $1 AND deleted_at IS NULL
          "#,
     )
     .bind(table_id)
@@ -313,24 +622,33 @@ pub async fn get_table_data(
     })
 }
 
+/// Check whether the user owns the table, or is one of its collaborators.
 pub async fn check_table_relation(
     executor: impl PgExecutor<'_>,
     user_id: Id,
     table_id: Id,
 ) -> sqlx::Result<Relation> {
-    sqlx::query_scalar::<_, Id>(
+    sqlx::query_as::<_, (Id, bool)>(
         r#"
-            SELECT user_id
-            FROM meta_table
-            WHERE table_id = $1
+            SELECT
+                t.user_id,
+                EXISTS (
+                    SELECT 1
+                    FROM table_collaborator AS c
+                    WHERE c.table_id = t.table_id AND c.user_id = $2
+                )
+            FROM meta_table AS t
+            WHERE t.table_id = $1 AND t.deleted_at IS NULL
         "#,
     )
     .bind(table_id)
+    .bind(user_id)
     .fetch_optional(executor)
     .await
-    .map(|id| match id {
+    .map(|table| match table {
         None => Relation::Absent,
-        Some(id) if id == user_id => Relation::Owned,
+        Some((id, _)) if id == user_id => Relation::Owned,
+        Some((_, true)) => Relation::Shared,
         Some(_) => Relation::NotOwned,
     })
 }
@@ -0,0 +1,471 @@
+use super::{
+    create_field, create_schema_changes, create_table, get_fields, get_table, get_table_children,
+};
+use crate::{
+    db::{create_chart, create_dashboard, get_axis_fields, set_axes, Relation},
+    model::{
+        data::{
+            CreateField, CreateTable, SchemaChange, TableTemplate, TemplateChart, TemplateField,
+            TemplateInstance, TemplateTable,
+        },
+        viz::{CacheMode, Chart, CreateAxis, CreateChart, CreateDashboard},
+    },
+    Id,
+};
+use futures::future::join_all;
+use itertools::Itertools;
+use sqlx::{types::Json, Acquire, PgExecutor, Postgres};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub async fn create_table_template(
+    conn: impl Acquire<'_, Database = Postgres>,
+    user_id: Id,
+    name: String,
+    description: String,
+    content: TemplateTable,
+) -> sqlx::Result<TableTemplate> {
+    let mut tx = conn.begin().await?;
+
+    let template = sqlx::query_as(
+        r#"
+            INSERT INTO table_template (user_id, name, description, content)
+            VALUES ($1, $2, $3, $4)
+            RETURNING
+                template_id,
+                user_id,
+                name,
+                description,
+                content,
+                is_published,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(description)
+    .bind(Json(content))
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(template)
+}
+
+pub async fn update_table_template(
+    conn: impl Acquire<'_, Database = Postgres>,
+    template_id: Id,
+    name: String,
+    description: String,
+) -> sqlx::Result<TableTemplate> {
+    let mut tx = conn.begin().await?;
+
+    let template = sqlx::query_as(
+        r#"
+            UPDATE table_template
+            SET name = $1, description = $2
+            WHERE template_id = $3
+            RETURNING
+                template_id,
+                user_id,
+                name,
+                description,
+                content,
+                is_published,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(name)
+    .bind(description)
+    .bind(template_id)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(template)
+}
+
+pub async fn publish_table_template(
+    conn: impl Acquire<'_, Database = Postgres>,
+    template_id: Id,
+    is_published: bool,
+) -> sqlx::Result<TableTemplate> {
+    let mut tx = conn.begin().await?;
+
+    let template = sqlx::query_as(
+        r#"
+            UPDATE table_template
+            SET is_published = $1
+            WHERE template_id = $2
+            RETURNING
+                template_id,
+                user_id,
+                name,
+                description,
+                content,
+                is_published,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(is_published)
+    .bind(template_id)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(template)
+}
+
+pub async fn delete_table_template(
+    conn: impl Acquire<'_, Database = Postgres>,
+    template_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            DELETE FROM table_template
+            WHERE template_id = $1
+        "#,
+    )
+    .bind(template_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn get_table_template(
+    executor: impl PgExecutor<'_>,
+    template_id: Id,
+) -> sqlx::Result<TableTemplate> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                template_id,
+                user_id,
+                name,
+                description,
+                content,
+                is_published,
+                created_at,
+                updated_at
+            FROM table_template
+            WHERE template_id = $1
+        "#,
+    )
+    .bind(template_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Get the templates of the user and the published templates.
+pub async fn get_table_templates(
+    executor: impl PgExecutor<'_>,
+    user_id: Id,
+) -> sqlx::Result<Vec<TableTemplate>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                template_id,
+                user_id,
+                name,
+                description,
+                content,
+                is_published,
+                created_at,
+                updated_at
+            FROM table_template
+            WHERE user_id = $1 OR is_published
+            ORDER BY template_id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(executor)
+    .await
+}
+
+/// Get the structure of a table and its children to save in a template.
+///
+/// Charts are only included if all the fields of their axes are in the template.
+pub async fn get_template_table(
+    executor: impl PgExecutor<'_> + Copy,
+    table_id: Id,
+    include_charts: bool,
+) -> sqlx::Result<TemplateTable> {
+    let mut template_table = get_template_table_tree(executor, table_id, include_charts).await?;
+
+    let mut field_ids = HashSet::new();
+    collect_template_field_ids(&template_table, &mut field_ids);
+    retain_template_charts(&mut template_table, &field_ids);
+
+    Ok(template_table)
+}
+
+async fn get_template_table_tree(
+    executor: impl PgExecutor<'_> + Copy,
+    table_id: Id,
+    include_charts: bool,
+) -> sqlx::Result<TemplateTable> {
+    let table = get_table(executor, table_id).await?;
+
+    let fields = get_fields(executor, table_id)
+        .await?
+        .into_iter()
+        .sorted_by_key(|field| field.ordering)
+        .map(|field| TemplateField {
+            field_id: field.field_id,
+            name: field.name,
+            field_kind: field.field_kind.0,
+            default_value: field.default_value.map(|value| value.0),
+        })
+        .collect();
+
+    let mut charts = Vec::new();
+    if include_charts {
+        let table_charts: Vec<Chart> = sqlx::query_as(
+            r#"
+                SELECT
+                    chart_id,
+                    dashboard_id,
+                    table_id,
+                    name,
+                    chart_kind,
+                    row_limit,
+                    top_n,
+                    cache_mode,
+                    refresh_interval,
+                    refreshed_at,
+                    created_at,
+                    updated_at
+                FROM chart
+                WHERE table_id = $1
+                    AND deleted_at IS NULL
+                    AND dashboard_id IN (
+                        SELECT dashboard_id
+                        FROM dashboard
+                        WHERE deleted_at IS NULL
+                    )
+                ORDER BY chart_id
+            "#,
+        )
+        .bind(table_id)
+        .fetch_all(executor)
+        .await?;
+
+        for chart in table_charts {
+            let axes = get_axis_fields(executor, chart.chart_id)
+                .await?
+                .into_iter()
+                .map(|axis_field| CreateAxis {
+                    field_id: axis_field.axis.field_id,
+                    axis_kind: axis_field.axis.axis_kind,
+                    aggregate: axis_field.axis.aggregate.map(|Json(aggregate)| aggregate),
+                    time_bucket: axis_field.axis.time_bucket,
+                    time_zone: axis_field.axis.time_zone,
+                    fill_gaps: axis_field.axis.fill_gaps,
+                    sort: axis_field.axis.sort,
+                })
+                .collect();
+            charts.push(TemplateChart {
+                name: chart.name,
+                chart_kind: chart.chart_kind,
+                row_limit: chart.row_limit,
+                top_n: chart.top_n,
+                axes,
+            });
+        }
+    }
+
+    let children = join_all(
+        get_table_children(executor, table_id)
+            .await?
+            .into_iter()
+            .map(|child| get_template_table_tree(executor, child.table_id, include_charts)),
+    )
+    .await
+    .into_iter()
+    .try_collect()?;
+
+    Ok(TemplateTable {
+        name: table.name,
+        description: table.description,
+        fields,
+        children,
+        charts,
+    })
+}
+
+fn collect_template_field_ids(template_table: &TemplateTable, field_ids: &mut HashSet<Id>) {
+    field_ids.extend(template_table.fields.iter().map(|field| field.field_id));
+    for child in &template_table.children {
+        collect_template_field_ids(child, field_ids);
+    }
+}
+
+fn retain_template_charts(template_table: &mut TemplateTable, field_ids: &HashSet<Id>) {
+    template_table.charts.retain(|chart| {
+        chart.axes.iter().all(|axis| {
+            field_ids.contains(&axis.field_id)
+                && axis
+                    .aggregate
+                    .as_ref()
+                    .and_then(|aggregate| aggregate.get_sort_field_id())
+                    .is_none_or(|field_id| field_ids.contains(&field_id))
+        })
+    });
+    for child in &mut template_table.children {
+        retain_template_charts(child, field_ids);
+    }
+}
+
+/// Create the tables of a template for a user.
+///
+/// If the template has charts, they are created in a new dashboard named after the table.
+/// The fields are logged as created by the user.
+pub async fn instantiate_table_template(
+    conn: impl Acquire<'_, Database = Postgres>,
+    user_id: Id,
+    template_table: TemplateTable,
+    name: Option<String>,
+) -> sqlx::Result<TemplateInstance> {
+    let mut tx = conn.begin().await?;
+
+    let mut root_table = None;
+    let mut field_ids = HashMap::new();
+    let mut charts = Vec::new();
+    // Parents are created before their children
+    let mut template_tables = VecDeque::from([(template_table, None)]);
+    while let Some((template_table, parent_id)) = template_tables.pop_front() {
+        let name = match (&root_table, &name) {
+            (None, Some(name)) => name.clone(),
+            _ => template_table.name,
+        };
+        let table = create_table(
+            tx.as_mut(),
+            user_id,
+            CreateTable {
+                parent_id,
+                name,
+                description: template_table.description,
+            },
+        )
+        .await?;
+
+        let mut changes = Vec::new();
+        for template_field in template_table.fields {
+            let field = create_field(
+                tx.as_mut(),
+                table.table_id,
+                CreateField {
+                    name: template_field.name,
+                    field_kind: template_field.field_kind,
+                    default_value: template_field.default_value,
+                },
+            )
+            .await?;
+            field_ids.insert(template_field.field_id, field.field_id);
+            changes.push(SchemaChange::field_created(&field));
+        }
+        create_schema_changes(tx.as_mut(), table.table_id, Some(user_id), &changes).await?;
+
+        charts.extend(
+            template_table
+                .charts
+                .into_iter()
+                .map(|chart| (table.table_id, chart)),
+        );
+        template_tables.extend(
+            template_table
+                .children
+                .into_iter()
+                .map(|child| (child, Some(table.table_id))),
+        );
+
+        root_table.get_or_insert(table);
+    }
+    let table = root_table.expect("the template table should be the first one created");
+
+    let dashboard = if charts.is_empty() {
+        None
+    } else {
+        let dashboard = create_dashboard(
+            tx.as_mut(),
+            user_id,
+            CreateDashboard {
+                name: table.name.clone(),
+                description: String::new(),
+            },
+        )
+        .await?;
+
+        for (table_id, template_chart) in charts {
+            let chart = create_chart(
+                tx.as_mut(),
+                dashboard.dashboard_id,
+                CreateChart {
+                    table_id,
+                    name: template_chart.name,
+                    chart_kind: template_chart.chart_kind,
+                    row_limit: template_chart.row_limit,
+                    top_n: template_chart.top_n,
+                    cache_mode: CacheMode::default(),
+                    refresh_interval: None,
+                },
+            )
+            .await?;
+
+            if template_chart.axes.is_empty() {
+                continue;
+            }
+            // The fields of the axes of template charts are always in the template
+            let axes = template_chart
+                .axes
+                .into_iter()
+                .map(|axis| CreateAxis {
+                    field_id: field_ids[&axis.field_id],
+                    aggregate: axis
+                        .aggregate
+                        .map(|aggregate| aggregate.map_sort_field_id(|id| field_ids[&id])),
+                    ..axis
+                })
+                .collect();
+            set_axes(tx.as_mut(), chart.chart_id, axes).await?;
+        }
+
+        Some(dashboard)
+    };
+
+    tx.commit().await?;
+
+    Ok(TemplateInstance { table, dashboard })
+}
+
+pub async fn check_table_template_relation(
+    executor: impl PgExecutor<'_>,
+    user_id: Id,
+    template_id: Id,
+) -> sqlx::Result<Relation> {
+    sqlx::query_scalar::<_, Id>(
+        r#"
+            SELECT user_id
+            FROM table_template
+            WHERE template_id = $1
+        "#,
+    )
+    .bind(template_id)
+    .fetch_optional(executor)
+    .await
+    .map(|id| match id {
+        None => Relation::Absent,
+        Some(id) if id == user_id => Relation::Owned,
+        Some(_) => Relation::NotOwned,
+    })
+}
@@ -0,0 +1,352 @@
+use crate::{
+    db::Relation,
+    model::data::{
+        CreateWebhook, DeliveryStatus, PendingDelivery, UpdateWebhook, Webhook, WebhookDelivery,
+        WebhookEvent, WebhookPayload,
+    },
+    Id,
+};
+use sqlx::{types::Json, Acquire, PgExecutor, Postgres};
+
+pub async fn create_webhook(
+    conn: impl Acquire<'_, Database = Postgres>,
+    table_id: Id,
+    secret: String,
+    CreateWebhook { url, events }: CreateWebhook,
+) -> sqlx::Result<Webhook> {
+    let mut tx = conn.begin().await?;
+
+    let webhook = sqlx::query_as(
+        r#"
+            INSERT INTO webhook (table_id, url, secret, events)
+            VALUES ($1, $2, $3, $4)
+            RETURNING
+                webhook_id,
+                table_id,
+                url,
+                events,
+                is_active,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(table_id)
+    .bind(url)
+    .bind(secret)
+    .bind(events)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(webhook)
+}
+
+pub async fn update_webhook(
+    conn: impl Acquire<'_, Database = Postgres>,
+    webhook_id: Id,
+    UpdateWebhook {
+        url,
+        events,
+        is_active,
+    }: UpdateWebhook,
+) -> sqlx::Result<Webhook> {
+    let mut tx = conn.begin().await?;
+
+    let webhook = sqlx::query_as(
+        r#"
+            UPDATE webhook
+            SET url = $1, events = $2, is_active = $3
+            WHERE webhook_id = $4
+            RETURNING
+                webhook_id,
+                table_id,
+                url,
+                events,
+                is_active,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(url)
+    .bind(events)
+    .bind(is_active)
+    .bind(webhook_id)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(webhook)
+}
+
+/// Replace the secret signing the payloads of a webhook.
+pub async fn set_webhook_secret(
+    conn: impl Acquire<'_, Database = Postgres>,
+    webhook_id: Id,
+    secret: &str,
+) -> sqlx::Result<Webhook> {
+    let mut tx = conn.begin().await?;
+
+    let webhook = sqlx::query_as(
+        r#"
+            UPDATE webhook
+            SET secret = $1
+            WHERE webhook_id = $2
+            RETURNING
+                webhook_id,
+                table_id,
+                url,
+                events,
+                is_active,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(secret)
+    .bind(webhook_id)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(webhook)
+}
+
+pub async fn delete_webhook(
+    conn: impl Acquire<'_, Database = Postgres>,
+    webhook_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            DELETE FROM webhook
+            WHERE webhook_id = $1
+        "#,
+    )
+    .bind(webhook_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn get_webhooks(
+    executor: impl PgExecutor<'_>,
+    table_id: Id,
+) -> sqlx::Result<Vec<Webhook>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                webhook_id,
+                table_id,
+                url,
+                events,
+                is_active,
+                created_at,
+                updated_at
+            FROM webhook
+            WHERE table_id = $1
+        "#,
+    )
+    .bind(table_id)
+    .fetch_all(executor)
+    .await
+}
+
+/// Get the most recent deliveries of a webhook.
+pub async fn get_webhook_deliveries(
+    executor: impl PgExecutor<'_>,
+    webhook_id: Id,
+    limit: i64,
+) -> sqlx::Result<Vec<WebhookDelivery>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                delivery_id,
+                webhook_id,
+                event,
+                payload,
+                status,
+                attempts,
+                next_attempt_at,
+                response_status,
+                last_error,
+                delivered_at,
+                created_at,
+                updated_at
+            FROM webhook_delivery
+            WHERE webhook_id = $1
+            ORDER BY delivery_id DESC
+            LIMIT $2
+        "#,
+    )
+    .bind(webhook_id)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+}
+
+/// Add a delivery of each payload to the outbox of every active webhook
+/// of the table subscribed to the event.
+///
+/// Called by the query functions changing entries and fields, so every change
+/// is delivered whichever request or task made it.
+pub async fn enqueue_webhook_deliveries(
+    conn: impl Acquire<'_, Database = Postgres>,
+    table_id: Id,
+    event: WebhookEvent,
+    payloads: &[WebhookPayload<'_>],
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    // Most tables have no webhooks, the payloads are only serialized when needed
+    let is_subscribed: bool = sqlx::query_scalar(
+        r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM webhook
+                WHERE table_id = $1
+                    AND is_active
+                    AND $2 = ANY(events)
+            )
+        "#,
+    )
+    .bind(table_id)
+    .bind(event)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    if !is_subscribed || payloads.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+            INSERT INTO webhook_delivery (webhook_id, event, payload)
+            SELECT w.webhook_id, $2, p.payload
+            FROM webhook AS w
+            CROSS JOIN unnest($3::jsonb[]) AS p (payload)
+            WHERE w.table_id = $1
+                AND w.is_active
+                AND $2 = ANY(w.events)
+        "#,
+    )
+    .bind(table_id)
+    .bind(event)
+    .bind(payloads.iter().map(Json).collect::<Vec<_>>())
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Claim pending deliveries that are due, and postpone them by the lease
+/// so they are not claimed again while they are sent.
+///
+/// Deliveries locked by another transaction are skipped, and the claim is a single
+/// statement, so no lock is held while the deliveries are sent.
+/// A delivery whose result is never recorded is claimed again after the lease.
+/// The deliveries of inactive webhooks stay pending until the webhook is activated again.
+pub async fn claim_pending_deliveries(
+    executor: impl PgExecutor<'_>,
+    limit: i64,
+    lease_secs: f64,
+) -> sqlx::Result<Vec<PendingDelivery>> {
+    sqlx::query_as(
+        r#"
+            UPDATE webhook_delivery AS d
+            SET next_attempt_at = now() + make_interval(secs => $2)
+            FROM webhook AS w
+            WHERE d.webhook_id = w.webhook_id
+                AND d.delivery_id IN (
+                    SELECT pd.delivery_id
+                    FROM webhook_delivery AS pd
+                    JOIN webhook AS pw
+                    ON pd.webhook_id = pw.webhook_id
+                    WHERE pd.status = 'Pending'
+                        AND pd.next_attempt_at <= now()
+                        AND pw.is_active
+                    ORDER BY pd.next_attempt_at
+                    LIMIT $1
+                    FOR UPDATE OF pd SKIP LOCKED
+                )
+            RETURNING
+                d.delivery_id,
+                d.event,
+                d.payload,
+                d.attempts,
+                w.url,
+                w.secret
+        "#,
+    )
+    .bind(limit)
+    .bind(lease_secs)
+    .fetch_all(executor)
+    .await
+}
+
+/// Record the result of a delivery attempt, unless the delivery was claimed again.
+///
+/// A delivery which is still pending is retried after `retry_delay_secs`.
+pub async fn set_delivery_result(
+    executor: impl PgExecutor<'_>,
+    delivery_id: Id,
+    attempts: i32,
+    status: DeliveryStatus,
+    response_status: Option<i32>,
+    last_error: Option<String>,
+    retry_delay_secs: f64,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"
+            UPDATE webhook_delivery
+            SET
+                status = $2,
+                attempts = attempts + 1,
+                response_status = $3,
+                last_error = $4,
+                next_attempt_at = now() + make_interval(secs => $5),
+                delivered_at = CASE WHEN $2 = 'Delivered' THEN now() END
+            WHERE delivery_id = $1
+                AND attempts = $6
+        "#,
+    )
+    .bind(delivery_id)
+    .bind(status)
+    .bind(response_status)
+    .bind(last_error)
+    .bind(retry_delay_secs)
+    .bind(attempts)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn check_webhook_relation(
+    executor: impl PgExecutor<'_>,
+    table_id: Id,
+    webhook_id: Id,
+) -> sqlx::Result<Relation> {
+    sqlx::query_scalar::<_, Id>(
+        r#"
+            SELECT table_id
+            FROM webhook
+            WHERE webhook_id = $1
+        "#,
+    )
+    .bind(webhook_id)
+    .fetch_optional(executor)
+    .await
+    .map(|id| match id {
+        None => Relation::Absent,
+        Some(id) if id == table_id => Relation::Owned,
+        Some(_) => Relation::NotOwned,
+    })
+}
@@ -0,0 +1,47 @@
+use crate::model::events::Event;
+use sqlx::{postgres::PgListener, PgPool};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{error, warn};
+
+/// Channel on which the database triggers publish change events.
+const EVENT_CHANNEL: &str = "chronicle_event";
+
+/// Delay before reconnecting after the listener connection is lost.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Listen to the change events published by the database and forward them
+/// to the broadcast channel. Events from every server instance are received
+/// since they are sent through PostgreSQL `LISTEN/NOTIFY`.
+///
+/// Runs forever and reconnects on connection failures.
+/// [Event::Resync] is sent on every (re)connection, since the events published
+/// while disconnected are lost.
+pub async fn listen_events(pool: PgPool, sender: broadcast::Sender<Event>) {
+    loop {
+        match forward_events(&pool, &sender).await {
+            Ok(()) => warn!("Event listener connection lost"),
+            Err(e) => error!("Event listener error: {e:?}"),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Forward the events until the connection is lost.
+async fn forward_events(pool: &PgPool, sender: &broadcast::Sender<Event>) -> sqlx::Result<()> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(EVENT_CHANNEL).await?;
+
+    // Sending only fails when there are no subscribers
+    _ = sender.send(Event::Resync);
+
+    // Unlike `recv`, `try_recv` does not reconnect silently
+    while let Some(notification) = listener.try_recv().await? {
+        match serde_json::from_str::<Event>(notification.payload()) {
+            Ok(event) => _ = sender.send(event),
+            Err(e) => warn!("Invalid event payload {}: {e}", notification.payload()),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,408 @@
+use super::Relation;
+use crate::{
+    model::jobs::{ClaimedJob, Job, JobKind, JobOutput},
+    Id,
+};
+use sqlx::{types::Json, Acquire, PgExecutor, Postgres};
+
+pub async fn create_job(
+    conn: impl Acquire<'_, Database = Postgres>,
+    user_id: Id,
+    job: &JobKind,
+    input: Option<Vec<u8>>,
+) -> sqlx::Result<Job> {
+    let mut tx = conn.begin().await?;
+
+    let job = sqlx::query_as(
+        r#"
+            INSERT INTO job (user_id, job, input)
+            VALUES ($1, $2, $3)
+            RETURNING
+                job_id,
+                user_id,
+                job,
+                status,
+                progress,
+                attempts,
+                output,
+                error,
+                started_at,
+                finished_at,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(Json(job))
+    .bind(input)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(job)
+}
+
+/// Cancel a job if it is pending or running, and drop its input file.
+/// A running job is stopped by its worker on the next heartbeat.
+pub async fn cancel_job(
+    conn: impl Acquire<'_, Database = Postgres>,
+    job_id: Id,
+) -> sqlx::Result<Job> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            UPDATE job
+            SET status = 'Cancelled', input = NULL, finished_at = now()
+            WHERE job_id = $1 AND status IN ('Pending', 'Running')
+        "#,
+    )
+    .bind(job_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    let job = get_job(tx.as_mut(), job_id).await?;
+
+    tx.commit().await?;
+
+    Ok(job)
+}
+
+pub async fn get_job(executor: impl PgExecutor<'_>, job_id: Id) -> sqlx::Result<Job> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                job_id,
+                user_id,
+                job,
+                status,
+                progress,
+                attempts,
+                output,
+                error,
+                started_at,
+                finished_at,
+                created_at,
+                updated_at
+            FROM job
+            WHERE job_id = $1
+        "#,
+    )
+    .bind(job_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Get the most recent jobs of a user.
+pub async fn get_jobs(
+    executor: impl PgExecutor<'_>,
+    user_id: Id,
+    limit: i64,
+) -> sqlx::Result<Vec<Job>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                job_id,
+                user_id,
+                job,
+                status,
+                progress,
+                attempts,
+                output,
+                error,
+                started_at,
+                finished_at,
+                created_at,
+                updated_at
+            FROM job
+            WHERE user_id = $1
+            ORDER BY job_id DESC
+            LIMIT $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+}
+
+/// Get the file produced by a job.
+pub async fn get_job_result(
+    executor: impl PgExecutor<'_>,
+    job_id: Id,
+) -> sqlx::Result<Option<Vec<u8>>> {
+    sqlx::query_scalar(
+        r#"
+            SELECT result
+            FROM job
+            WHERE job_id = $1
+        "#,
+    )
+    .bind(job_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Claim the oldest pending job, or a running job without heartbeat since `lease_secs`.
+///
+/// Jobs locked by another transaction are skipped,
+/// which allows many workers to run jobs concurrently.
+pub async fn claim_job(
+    conn: impl Acquire<'_, Database = Postgres>,
+    lease_secs: f64,
+    max_attempts: i32,
+) -> sqlx::Result<Option<ClaimedJob>> {
+    let mut tx = conn.begin().await?;
+
+    let job = sqlx::query_as(
+        r#"
+            UPDATE job
+            SET
+                status = 'Running',
+                attempts = attempts + 1,
+                reserved_tables = 0,
+                heartbeat_at = now(),
+                started_at = now()
+            WHERE job_id = (
+                SELECT job_id
+                FROM job
+                WHERE (
+                        status = 'Pending'
+                        OR status = 'Running'
+                        AND heartbeat_at < now() - make_interval(secs => $1)
+                    )
+                    AND attempts < $2
+                ORDER BY job_id
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING
+                job_id,
+                user_id,
+                job,
+                attempts,
+                input
+        "#,
+    )
+    .bind(lease_secs)
+    .bind(max_attempts)
+    .fetch_optional(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(job)
+}
+
+/// Fail the running jobs without heartbeat since `lease_secs`
+/// which were interrupted `max_attempts` times, and return them.
+/// Their input files are dropped.
+pub async fn fail_abandoned_jobs(
+    conn: impl Acquire<'_, Database = Postgres>,
+    lease_secs: f64,
+    max_attempts: i32,
+) -> sqlx::Result<Vec<Job>> {
+    let mut tx = conn.begin().await?;
+
+    let jobs = sqlx::query_as(
+        r#"
+            UPDATE job
+            SET
+                status = 'Failed',
+                error = 'Job was interrupted too many times',
+                input = NULL,
+                finished_at = now()
+            WHERE status = 'Running'
+                AND heartbeat_at < now() - make_interval(secs => $1)
+                AND attempts >= $2
+            RETURNING
+                job_id,
+                user_id,
+                job,
+                status,
+                progress,
+                attempts,
+                output,
+                error,
+                started_at,
+                finished_at,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(lease_secs)
+    .bind(max_attempts)
+    .fetch_all(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(jobs)
+}
+
+/// Record that the attempt of a job is still running.
+/// Returns false when the job was cancelled or claimed again.
+pub async fn beat_job(
+    executor: impl PgExecutor<'_>,
+    job_id: Id,
+    attempts: i32,
+) -> sqlx::Result<bool> {
+    sqlx::query(
+        r#"
+            UPDATE job
+            SET heartbeat_at = now()
+            WHERE job_id = $1 AND attempts = $2 AND status = 'Running'
+        "#,
+    )
+    .bind(job_id)
+    .bind(attempts)
+    .execute(executor)
+    .await
+    .map(|result| result.rows_affected() > 0)
+}
+
+/// Reserve the tables created by the attempt of an import job,
+/// which are counted in the table limit of the user while the job is running.
+/// Returns false when the job was cancelled or claimed again.
+pub async fn reserve_job_tables(
+    executor: impl PgExecutor<'_>,
+    job_id: Id,
+    attempts: i32,
+    tables: i32,
+) -> sqlx::Result<bool> {
+    sqlx::query(
+        r#"
+            UPDATE job
+            SET reserved_tables = $3
+            WHERE job_id = $1 AND attempts = $2 AND status = 'Running'
+        "#,
+    )
+    .bind(job_id)
+    .bind(attempts)
+    .bind(tables)
+    .execute(executor)
+    .await
+    .map(|result| result.rows_affected() > 0)
+}
+
+/// Set the progress of the attempt of a job.
+/// Returns false when the job was cancelled or claimed again.
+pub async fn set_job_progress(
+    executor: impl PgExecutor<'_>,
+    job_id: Id,
+    attempts: i32,
+    progress: f64,
+) -> sqlx::Result<bool> {
+    sqlx::query(
+        r#"
+            UPDATE job
+            SET progress = $3
+            WHERE job_id = $1 AND attempts = $2 AND status = 'Running'
+        "#,
+    )
+    .bind(job_id)
+    .bind(attempts)
+    .bind(progress)
+    .execute(executor)
+    .await
+    .map(|result| result.rows_affected() > 0)
+}
+
+/// Complete the attempt of a job with its output and result file.
+/// The input file is dropped.
+/// Returns false when the job was cancelled or claimed again.
+pub async fn complete_job(
+    executor: impl PgExecutor<'_>,
+    job_id: Id,
+    attempts: i32,
+    output: Option<JobOutput>,
+    result: Option<Vec<u8>>,
+) -> sqlx::Result<bool> {
+    sqlx::query(
+        r#"
+            UPDATE job
+            SET
+                status = 'Completed',
+                progress = 1,
+                output = $3,
+                result = $4,
+                input = NULL,
+                finished_at = now()
+            WHERE job_id = $1 AND attempts = $2 AND status = 'Running'
+        "#,
+    )
+    .bind(job_id)
+    .bind(attempts)
+    .bind(output.map(Json))
+    .bind(result)
+    .execute(executor)
+    .await
+    .map(|result| result.rows_affected() > 0)
+}
+
+/// Fail the attempt of a job with the error shown to its user.
+/// The input file is dropped.
+/// Returns false when the job was cancelled or claimed again.
+pub async fn fail_job(
+    executor: impl PgExecutor<'_>,
+    job_id: Id,
+    attempts: i32,
+    error: String,
+) -> sqlx::Result<bool> {
+    sqlx::query(
+        r#"
+            UPDATE job
+            SET status = 'Failed', error = $3, input = NULL, finished_at = now()
+            WHERE job_id = $1 AND attempts = $2 AND status = 'Running'
+        "#,
+    )
+    .bind(job_id)
+    .bind(attempts)
+    .bind(error)
+    .execute(executor)
+    .await
+    .map(|result| result.rows_affected() > 0)
+}
+
+/// Drop the input and result files of the jobs finished for longer than `retention_secs`.
+pub async fn drop_expired_job_files(
+    executor: impl PgExecutor<'_>,
+    retention_secs: f64,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"
+            UPDATE job
+            SET input = NULL, result = NULL
+            WHERE finished_at < now() - make_interval(secs => $1)
+                AND (input IS NOT NULL OR result IS NOT NULL)
+        "#,
+    )
+    .bind(retention_secs)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn check_job_relation(
+    executor: impl PgExecutor<'_>,
+    user_id: Id,
+    job_id: Id,
+) -> sqlx::Result<Relation> {
+    sqlx::query_scalar::<_, Id>(
+        r#"
+            SELECT user_id
+            FROM job
+            WHERE job_id = $1
+        "#,
+    )
+    .bind(job_id)
+    .fetch_optional(executor)
+    .await
+    .map(|id| match id {
+        None => Relation::Absent,
+        Some(id) if id == user_id => Relation::Owned,
+        Some(_) => Relation::NotOwned,
+    })
+}
@@ -7,14 +7,21 @@
 //! only database errors on failures.
 
 mod data;
+mod events;
+mod jobs;
+mod notifications;
+mod trash;
+mod usage;
 mod viz;
 mod users;
 
 use crate::error::{ApiError, ApiResult};
-pub use {data::*, viz::*, users::*};
+pub use {data::*, events::*, jobs::*, notifications::*, trash::*, usage::*, viz::*, users::*};
 
 pub enum Relation {
     Owned,
+    /// Not owned, but shared with the user.
+    Shared,
     NotOwned,
     Absent,
 }
@@ -23,8 +30,21 @@ impl Relation {
     pub fn to_api_result(self) -> ApiResult<()> {
         match self {
             Relation::Owned => Ok(()),
-            Relation::NotOwned => Err(ApiError::Forbidden),
+            Relation::Shared | Relation::NotOwned => Err(ApiError::Forbidden),
             Relation::Absent => Err(ApiError::NotFound),
         }
     }
+
+    /// Like [Relation::to_api_result], for the requests also allowed on shared resources.
+    pub fn to_shared_api_result(self) -> ApiResult<()> {
+        match self {
+            Relation::Shared => Ok(()),
+            relation => relation.to_api_result(),
+        }
+    }
+}
+
+/// Quote a string as an SQL literal, for the statements which can not have bind parameters.
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
 }
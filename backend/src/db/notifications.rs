@@ -0,0 +1,160 @@
+use super::Relation;
+use crate::{
+    model::notifications::{
+        Notification, NotificationKind, NotificationsQuery, UnreadNotifications, UpdateNotification,
+    },
+    Id,
+};
+use sqlx::{types::Json, Acquire, PgExecutor, Postgres};
+
+/// Add the same notification to the inbox of each user.
+pub async fn create_notifications(
+    conn: impl Acquire<'_, Database = Postgres>,
+    user_ids: &[Id],
+    notification: &NotificationKind,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            INSERT INTO notification (user_id, notification)
+            SELECT user_id, $2
+            FROM unnest($1::int[]) AS u (user_id)
+        "#,
+    )
+    .bind(user_ids)
+    .bind(Json(notification))
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Mark a notification as read or unread.
+/// A notification keeps its first read date when it is marked as read again.
+pub async fn update_notification(
+    conn: impl Acquire<'_, Database = Postgres>,
+    notification_id: Id,
+    UpdateNotification { is_read }: UpdateNotification,
+) -> sqlx::Result<Notification> {
+    let mut tx = conn.begin().await?;
+
+    let notification = sqlx::query_as(
+        r#"
+            UPDATE notification
+            SET read_at = CASE WHEN $1 THEN coalesce(read_at, now()) END
+            WHERE notification_id = $2
+            RETURNING
+                notification_id,
+                user_id,
+                notification,
+                read_at,
+                created_at
+        "#,
+    )
+    .bind(is_read)
+    .bind(notification_id)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(notification)
+}
+
+/// Mark all the unread notifications of a user as read.
+pub async fn read_notifications(
+    conn: impl Acquire<'_, Database = Postgres>,
+    user_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            UPDATE notification
+            SET read_at = now()
+            WHERE user_id = $1 AND read_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Get the notifications of a user, most recent first.
+pub async fn get_notifications(
+    executor: impl PgExecutor<'_>,
+    user_id: Id,
+    NotificationsQuery { unread, before }: NotificationsQuery,
+    limit: i64,
+) -> sqlx::Result<Vec<Notification>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                notification_id,
+                user_id,
+                notification,
+                read_at,
+                created_at
+            FROM notification
+            WHERE user_id = $1
+                AND (NOT $2 OR read_at IS NULL)
+                AND ($3::int IS NULL OR notification_id < $3)
+            ORDER BY notification_id DESC
+            LIMIT $4
+        "#,
+    )
+    .bind(user_id)
+    .bind(unread)
+    .bind(before)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+}
+
+/// Get the number of unread notifications of a user.
+pub async fn get_unread_notifications(
+    executor: impl PgExecutor<'_>,
+    user_id: Id,
+) -> sqlx::Result<UnreadNotifications> {
+    let unread = sqlx::query_scalar(
+        r#"
+            SELECT count(*)
+            FROM notification
+            WHERE user_id = $1 AND read_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(UnreadNotifications { unread })
+}
+
+pub async fn check_notification_relation(
+    executor: impl PgExecutor<'_>,
+    user_id: Id,
+    notification_id: Id,
+) -> sqlx::Result<Relation> {
+    sqlx::query_scalar::<_, Id>(
+        r#"
+            SELECT user_id
+            FROM notification
+            WHERE notification_id = $1
+        "#,
+    )
+    .bind(notification_id)
+    .fetch_optional(executor)
+    .await
+    .map(|id| match id {
+        None => Relation::Absent,
+        Some(id) if id == user_id => Relation::Owned,
+        Some(_) => Relation::NotOwned,
+    })
+}
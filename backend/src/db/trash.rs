@@ -0,0 +1,182 @@
+use super::Relation;
+use crate::{
+    model::trash::{TrashItem, TrashItemKind},
+    Id,
+};
+use sqlx::PgExecutor;
+
+/// Get the items in the trash of a user, most recently deleted first.
+///
+/// Descendant tables deleted with their parent are not listed, nor the fields
+/// of tables and the charts of dashboards in the trash.
+pub async fn get_trash(executor: impl PgExecutor<'_>, user_id: Id) -> sqlx::Result<Vec<TrashItem>> {
+    sqlx::query_as(
+        r#"
+            SELECT 'Table' AS item_kind, t.table_id AS item_id, t.parent_id, t.name, t.deleted_at
+            FROM meta_table AS t
+            LEFT JOIN meta_table AS p
+            ON t.parent_id = p.table_id
+            WHERE t.user_id = $1
+                AND t.deleted_at IS NOT NULL
+                AND p.deleted_at IS DISTINCT FROM t.deleted_at
+            UNION ALL
+            SELECT 'Field', f.field_id, f.table_id, f.name, f.deleted_at
+            FROM meta_field AS f
+            JOIN meta_table AS t
+            ON f.table_id = t.table_id
+            WHERE t.user_id = $1
+                AND f.deleted_at IS NOT NULL
+                AND t.deleted_at IS NULL
+            UNION ALL
+            SELECT 'Dashboard', dashboard_id, NULL, name, deleted_at
+            FROM dashboard
+            WHERE user_id = $1 AND deleted_at IS NOT NULL
+            UNION ALL
+            SELECT 'Chart', c.chart_id, c.dashboard_id, c.name, c.deleted_at
+            FROM chart AS c
+            JOIN dashboard AS d
+            ON c.dashboard_id = d.dashboard_id
+            WHERE d.user_id = $1
+                AND c.deleted_at IS NOT NULL
+                AND d.deleted_at IS NULL
+            ORDER BY deleted_at DESC, item_id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(executor)
+    .await
+}
+
+/// Get the items deleted for longer than the retention period, in the order they must be purged.
+///
+/// Charts and fields are purged before tables, and descendant tables before their ancestors.
+pub async fn get_expired_trash(
+    executor: impl PgExecutor<'_>,
+    retention_days: i32,
+) -> sqlx::Result<Vec<(TrashItemKind, Id)>> {
+    sqlx::query_as(
+        r#"
+            WITH expired AS (
+                SELECT 'Chart' AS item_kind, chart_id AS item_id, 1 AS rank
+                FROM chart
+                WHERE deleted_at < now() - make_interval(days => $1)
+                UNION ALL
+                SELECT 'Field', field_id, 2
+                FROM meta_field
+                WHERE deleted_at < now() - make_interval(days => $1)
+                UNION ALL
+                SELECT 'Table', table_id, 3
+                FROM meta_table
+                WHERE deleted_at < now() - make_interval(days => $1)
+                UNION ALL
+                SELECT 'Dashboard', dashboard_id, 4
+                FROM dashboard
+                WHERE deleted_at < now() - make_interval(days => $1)
+            )
+            SELECT item_kind, item_id
+            FROM expired
+            -- Children are always created after their parent
+            ORDER BY rank, CASE WHEN item_kind = 'Table' THEN -item_id ELSE item_id END
+        "#,
+    )
+    .bind(retention_days)
+    .fetch_all(executor)
+    .await
+}
+
+/// Check if the item is in the trash of the user.
+pub async fn check_trash_relation(
+    executor: impl PgExecutor<'_>,
+    user_id: Id,
+    item_kind: TrashItemKind,
+    item_id: Id,
+) -> sqlx::Result<Relation> {
+    let query = match item_kind {
+        TrashItemKind::Table => {
+            r#"
+                SELECT user_id
+                FROM meta_table
+                WHERE table_id = $1 AND deleted_at IS NOT NULL
+            "#
+        }
+        TrashItemKind::Field => {
+            r#"
+                SELECT t.user_id
+                FROM meta_field AS f
+                JOIN meta_table AS t
+                ON f.table_id = t.table_id
+                WHERE f.field_id = $1 AND f.deleted_at IS NOT NULL
+            "#
+        }
+        TrashItemKind::Dashboard => {
+            r#"
+                SELECT user_id
+                FROM dashboard
+                WHERE dashboard_id = $1 AND deleted_at IS NOT NULL
+            "#
+        }
+        TrashItemKind::Chart => {
+            r#"
+                SELECT d.user_id
+                FROM chart AS c
+                JOIN dashboard AS d
+                ON c.dashboard_id = d.dashboard_id
+                WHERE c.chart_id = $1 AND c.deleted_at IS NOT NULL
+            "#
+        }
+    };
+
+    sqlx::query_scalar::<_, Id>(query)
+        .bind(item_id)
+        .fetch_optional(executor)
+        .await
+        .map(|id| match id {
+            None => Relation::Absent,
+            Some(id) if id == user_id => Relation::Owned,
+            Some(_) => Relation::NotOwned,
+        })
+}
+
+/// Check if the parent of an item in the trash is also in the trash,
+/// in which case the item cannot be restored before its parent.
+pub async fn is_trash_parent_deleted(
+    executor: impl PgExecutor<'_>,
+    item_kind: TrashItemKind,
+    item_id: Id,
+) -> sqlx::Result<bool> {
+    let query = match item_kind {
+        TrashItemKind::Table => {
+            r#"
+                SELECT p.deleted_at IS NOT NULL
+                FROM meta_table AS t
+                LEFT JOIN meta_table AS p
+                ON t.parent_id = p.table_id
+                WHERE t.table_id = $1
+            "#
+        }
+        TrashItemKind::Field => {
+            r#"
+                SELECT t.deleted_at IS NOT NULL
+                FROM meta_field AS f
+                JOIN meta_table AS t
+                ON f.table_id = t.table_id
+                WHERE f.field_id = $1
+            "#
+        }
+        TrashItemKind::Dashboard => return Ok(false),
+        TrashItemKind::Chart => {
+            r#"
+                SELECT d.deleted_at IS NOT NULL
+                FROM chart AS c
+                JOIN dashboard AS d
+                ON c.dashboard_id = d.dashboard_id
+                WHERE c.chart_id = $1
+            "#
+        }
+    };
+
+    sqlx::query_scalar(query)
+        .bind(item_id)
+        .fetch_one(executor)
+        .await
+}
@@ -0,0 +1,138 @@
+use crate::{model::usage::TableCount, Id};
+use sqlx::PgExecutor;
+
+/// Classes of the advisory locks on the resources counted against the limits.
+const TABLE_LOCK: i32 = 1;
+const FIELD_LOCK: i32 = 2;
+const ROW_LOCK: i32 = 3;
+
+/// Lock the creation of tables of a user until the end of the transaction.
+pub async fn lock_user_tables(executor: impl PgExecutor<'_>, user_id: Id) -> sqlx::Result<()> {
+    lock(executor, TABLE_LOCK, user_id).await
+}
+
+/// Lock the creation of fields of a table until the end of the transaction.
+pub async fn lock_table_fields(executor: impl PgExecutor<'_>, table_id: Id) -> sqlx::Result<()> {
+    lock(executor, FIELD_LOCK, table_id).await
+}
+
+/// Lock the creation of entries of a table until the end of the transaction.
+pub async fn lock_table_rows(executor: impl PgExecutor<'_>, table_id: Id) -> sqlx::Result<()> {
+    lock(executor, ROW_LOCK, table_id).await
+}
+
+async fn lock(executor: impl PgExecutor<'_>, class: i32, id: Id) -> sqlx::Result<()> {
+    sqlx::query("SELECT pg_advisory_xact_lock($1, $2)")
+        .bind(class)
+        .bind(id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Get the number of tables of a user, including the tables in the trash
+/// and the tables reserved by the running imports of the user.
+pub async fn get_table_count(executor: impl PgExecutor<'_>, user_id: Id) -> sqlx::Result<i64> {
+    sqlx::query_scalar(
+        r#"
+            SELECT
+                (
+                    SELECT count(*)
+                    FROM meta_table
+                    WHERE user_id = $1
+                )
+                + (
+                    SELECT coalesce(sum(reserved_tables), 0)
+                    FROM job
+                    WHERE user_id = $1 AND status = 'Running'
+                )
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Get the number of tables copied with a table, which are the table and its descendants.
+pub async fn get_table_tree_count(
+    executor: impl PgExecutor<'_>,
+    table_id: Id,
+) -> sqlx::Result<i64> {
+    sqlx::query_scalar(
+        r#"
+            WITH RECURSIVE descendant AS (
+                SELECT table_id
+                FROM meta_table
+                WHERE table_id = $1
+                UNION ALL
+                SELECT t.table_id
+                FROM meta_table AS t
+                JOIN descendant AS d
+                ON t.parent_id = d.table_id
+                WHERE t.deleted_at IS NULL
+            )
+            SELECT count(*)
+            FROM descendant
+        "#,
+    )
+    .bind(table_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Get the number of fields of a table, including the fields in the trash
+/// and the backups of converted fields.
+pub async fn get_field_count(executor: impl PgExecutor<'_>, table_id: Id) -> sqlx::Result<i64> {
+    sqlx::query_scalar(
+        r#"
+            SELECT count(*)
+            FROM meta_field
+            WHERE table_id = $1
+        "#,
+    )
+    .bind(table_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Get the number of entries of a table, as maintained by the triggers of the table.
+pub async fn get_row_count(executor: impl PgExecutor<'_>, table_id: Id) -> sqlx::Result<i64> {
+    sqlx::query_scalar(
+        r#"
+            SELECT row_count
+            FROM table_row_count
+            WHERE table_id = $1
+        "#,
+    )
+    .bind(table_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Get the number of fields and entries of every table of a user, including the tables in the trash.
+pub async fn get_table_counts(
+    executor: impl PgExecutor<'_>,
+    user_id: Id,
+) -> sqlx::Result<Vec<TableCount>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                t.table_id,
+                t.name,
+                (
+                    SELECT count(*)
+                    FROM meta_field AS f
+                    WHERE f.table_id = t.table_id
+                ) AS field_count,
+                coalesce(r.row_count, 0) AS row_count
+            FROM meta_table AS t
+            LEFT JOIN table_row_count AS r
+            ON t.table_id = r.table_id
+            WHERE t.user_id = $1
+            ORDER BY t.table_id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(executor)
+    .await
+}
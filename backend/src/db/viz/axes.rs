@@ -1,19 +1,14 @@
-use std::collections::HashMap;
-
+use super::{create_view_statement, drop_chart_view, get_chart, get_chart_joins, push_chart_query};
 use crate::{
-    model::{
-        data::{FieldIdentifier, FieldKind, TableIdentifier},
-        viz::{Axis, AxisIdentifier, ChartIdentifier, CreateAxis},
-    },
+    db::get_field_chart_ids,
+    model::viz::{Axis, AxisField, ChartDependency, CreateAxis},
     Id,
 };
-use sqlx::{Acquire, Postgres, QueryBuilder};
+use sqlx::{types::Json, Acquire, PgExecutor, Postgres, QueryBuilder};
 
 pub async fn set_axes(
     conn: impl Acquire<'_, Database = Postgres>,
     chart_id: Id,
-    table_id: Id,
-    field_kinds: &HashMap<Id, FieldKind>,
     axes: Vec<CreateAxis>,
 ) -> sqlx::Result<Vec<Axis>> {
     let mut tx = conn.begin().await?;
@@ -28,77 +23,261 @@ pub async fn set_axes(
     .execute(tx.as_mut())
     .await?;
 
-    let axes: Vec<Axis> =
-        QueryBuilder::new(r#"INSERT INTO axis (chart_id, field_id, axis_kind, aggregate)"#)
-            .push_values(axes, |mut builder, axis| {
-                builder
-                    .push_bind(chart_id)
-                    .push_bind(axis.field_id)
-                    .push_bind(axis.axis_kind)
-                    .push_bind(axis.aggregate);
-            })
-            .push(
-                r#"
-                    RETURNING
-                        axis_id,
-                        chart_id,
-                        field_id,
-                        axis_kind,
-                        aggregate,
-                        created_at,
-                        updated_at
-                "#,
-            )
-            .build_query_as()
-            .fetch_all(tx.as_mut())
-            .await?;
-
-    let mut group_by_columns = Vec::new();
-    let mut select_columns = Vec::new();
-    for axis in &axes {
-        let field_ident = FieldIdentifier::new(axis.field_id);
-        let item = if let Some(aggregate) = &axis.aggregate {
-            &format!(
-                "{}({})::{}",
-                aggregate.get_sql_aggregate(),
-                field_ident,
-                aggregate.get_sql_type(&field_kinds.get(&axis.field_id).unwrap()),
+    let axes: Vec<Axis> = QueryBuilder::new(
+        r#"
+            INSERT INTO axis (
+                chart_id,
+                field_id,
+                axis_kind,
+                aggregate,
+                time_bucket,
+                time_zone,
+                fill_gaps,
+                sort
             )
-        } else {
-            group_by_columns.push(field_ident.to_string());
-            &field_ident.to_string()
-        };
-        let axis_ident = AxisIdentifier::new(axis.axis_id);
-        select_columns.push(format!("{item} AS {}", axis_ident));
+        "#,
+    )
+    .push_values(axes, |mut builder, axis| {
+        builder
+            .push_bind(chart_id)
+            .push_bind(axis.field_id)
+            .push_bind(axis.axis_kind)
+            .push_bind(axis.aggregate.map(Json))
+            .push_bind(axis.time_bucket)
+            .push_bind(axis.time_zone)
+            .push_bind(axis.fill_gaps)
+            .push_bind(axis.sort);
+    })
+    .push(
+        r#"
+            RETURNING
+                axis_id,
+                chart_id,
+                field_id,
+                axis_kind,
+                aggregate,
+                time_bucket,
+                time_zone,
+                fill_gaps,
+                sort,
+                created_at,
+                updated_at
+        "#,
+    )
+    .build_query_as()
+    .fetch_all(tx.as_mut())
+    .await?;
+
+    rebuild_chart_view(tx.as_mut(), chart_id).await?;
+
+    tx.commit().await?;
+
+    Ok(axes)
+}
+
+/// Replace the view of a chart with the current axes, without filters.
+pub async fn rebuild_chart_view(
+    conn: impl Acquire<'_, Database = Postgres>,
+    chart_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    let chart = get_chart(tx.as_mut(), chart_id).await?;
+    let axes = get_axis_fields(tx.as_mut(), chart_id).await?;
+    let joins = get_chart_joins(tx.as_mut(), &chart, &axes).await?;
+
+    drop_chart_view(tx.as_mut(), chart_id).await?;
+
+    let mut builder = QueryBuilder::new(create_view_statement(&chart));
+    push_chart_query(&mut builder, &chart, &axes, &joins, &[]);
+    builder.build().execute(tx.as_mut()).await?;
+
+    // A materialized view is populated when created
+    sqlx::query(
+        r#"
+            UPDATE chart
+            SET refreshed_at = CASE WHEN cache_mode = 'Materialized' THEN now() END
+            WHERE chart_id = $1
+        "#,
+    )
+    .bind(chart_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Get the axes of a chart with their fields.
+///
+/// The axes on fields in the trash, or sorted by them, and the axes on tables
+/// in the trash are left out until they are restored.
+pub async fn get_axis_fields(
+    executor: impl PgExecutor<'_>,
+    chart_id: Id,
+) -> sqlx::Result<Vec<AxisField>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                a.axis_id,
+                a.chart_id,
+                a.field_id,
+                a.axis_kind,
+                a.aggregate,
+                a.time_bucket,
+                a.time_zone,
+                a.fill_gaps,
+                a.sort,
+                a.created_at,
+                a.updated_at,
+                f.table_id,
+                f.name AS field_name,
+                f.field_kind
+            FROM axis AS a
+            JOIN meta_field AS f
+            ON a.field_id = f.field_id
+            JOIN meta_table AS t
+            ON f.table_id = t.table_id
+            WHERE a.chart_id = $1
+                AND f.deleted_at IS NULL
+                AND t.deleted_at IS NULL
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM meta_field AS sf
+                    WHERE sf.deleted_at IS NOT NULL
+                        AND sf.field_id IN (
+                            (a.aggregate->'First'->>'field_id')::INT,
+                            (a.aggregate->'Last'->>'field_id')::INT
+                        )
+                )
+            ORDER BY a.axis_id
+        "#,
+    )
+    .bind(chart_id)
+    .fetch_all(executor)
+    .await
+}
+
+/// Get the charts which are not in the trash and have axes on the field,
+/// including the sort field of an aggregate.
+pub async fn get_field_dependencies(
+    executor: impl PgExecutor<'_>,
+    field_id: Id,
+) -> sqlx::Result<Vec<ChartDependency>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                c.chart_id,
+                c.dashboard_id,
+                c.name,
+                array_agg(a.axis_id ORDER BY a.axis_id) AS axis_ids
+            FROM axis AS a
+            JOIN chart AS c
+            ON a.chart_id = c.chart_id
+            JOIN dashboard AS d
+            ON c.dashboard_id = d.dashboard_id
+            WHERE c.deleted_at IS NULL
+                AND d.deleted_at IS NULL
+                AND (
+                    a.field_id = $1
+                    OR (a.aggregate->'First'->>'field_id')::INT = $1
+                    OR (a.aggregate->'Last'->>'field_id')::INT = $1
+                )
+            GROUP BY c.chart_id
+            ORDER BY c.chart_id
+        "#,
+    )
+    .bind(field_id)
+    .fetch_all(executor)
+    .await
+}
+
+/// Move the axes on a field to another field, including the sort field of an aggregate,
+/// and rebuild the views of their charts.
+pub async fn repoint_field_axes(
+    conn: impl Acquire<'_, Database = Postgres>,
+    field_id: Id,
+    new_field_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    let chart_ids = get_field_chart_ids(tx.as_mut(), &[field_id]).await?;
+
+    sqlx::query(
+        r#"
+            UPDATE axis
+            SET
+                field_id = CASE WHEN field_id = $1 THEN $2 ELSE field_id END,
+                aggregate = CASE
+                    WHEN (aggregate->'First'->>'field_id')::INT = $1
+                        THEN jsonb_set(aggregate, '{First,field_id}', to_jsonb($2))
+                    WHEN (aggregate->'Last'->>'field_id')::INT = $1
+                        THEN jsonb_set(aggregate, '{Last,field_id}', to_jsonb($2))
+                    ELSE aggregate
+                END
+            WHERE chart_id = ANY($3)
+        "#,
+    )
+    .bind(field_id)
+    .bind(new_field_id)
+    .bind(&chart_ids)
+    .execute(tx.as_mut())
+    .await?;
+
+    for chart_id in chart_ids {
+        rebuild_chart_view(tx.as_mut(), chart_id).await?;
     }
-    let group_by_columns = group_by_columns.join(", ");
-    let select_columns = select_columns.join(", ");
 
-    let group_by_statement = if group_by_columns.len() > 0 {
-        format!("GROUP BY {group_by_columns}")
-    } else {
-        String::new()
-    };
+    tx.commit().await?;
+
+    Ok(())
+}
 
-    let chart_ident = ChartIdentifier::new(chart_id, "data_view");
-    let table_ident = TableIdentifier::new(table_id, "data_table");
+/// Remove the axes on a field, including the axes sorted by the field,
+/// and rebuild the views of their charts.
+pub async fn remove_field_axes(
+    conn: impl Acquire<'_, Database = Postgres>,
+    field_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
 
-    sqlx::query(&format!(r#"DROP VIEW {chart_ident}"#))
-        .execute(tx.as_mut())
-        .await?;
+    let chart_ids = get_field_chart_ids(tx.as_mut(), &[field_id]).await?;
 
-    sqlx::query(&format!(
+    sqlx::query(
         r#"
-            CREATE VIEW {chart_ident} AS
-            SELECT {select_columns}
-            FROM {table_ident}
-            {group_by_statement}
-        "#
-    ))
+            DELETE FROM axis
+            WHERE field_id = $1
+                OR (aggregate->'First'->>'field_id')::INT = $1
+                OR (aggregate->'Last'->>'field_id')::INT = $1
+        "#,
+    )
+    .bind(field_id)
     .execute(tx.as_mut())
     .await?;
 
+    for chart_id in chart_ids {
+        rebuild_chart_view(tx.as_mut(), chart_id).await?;
+    }
+
     tx.commit().await?;
 
-    Ok(axes)
+    Ok(())
+}
+
+/// Rebuild the views of the charts with axes on the fields,
+/// after the fields or their tables are moved to or restored from the trash.
+pub async fn rebuild_field_chart_views(
+    conn: impl Acquire<'_, Database = Postgres>,
+    field_ids: &[Id],
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    for chart_id in get_field_chart_ids(tx.as_mut(), field_ids).await? {
+        rebuild_chart_view(tx.as_mut(), chart_id).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
 }
@@ -1,17 +1,23 @@
 use std::collections::HashMap;
 
+use super::{
+    create_view_statement, get_axis_fields, get_chart_filters, get_chart_joins, get_pivot_axes,
+    push_chart_query, push_data_query, push_other_query, rebuild_chart_view,
+};
 use crate::{
     db::Relation,
     model::{
         viz::{
-            AxisField, AxisIdentifier, Chart, ChartData, ChartIdentifier, CreateChart, UpdateChart,
+            AxisField, AxisIdentifier, CacheMode, Chart, ChartData, ChartIdentifier, ChartKind,
+            CreateChart, FilterKind, UpdateChart,
         },
         Cell,
     },
     Id,
 };
+use chrono::Utc;
 use itertools::Itertools;
-use sqlx::{Acquire, PgExecutor, Postgres};
+use sqlx::{postgres::PgRow, types::Json, Acquire, PgExecutor, Postgres, QueryBuilder, Row};
 
 pub async fn create_chart(
     conn: impl Acquire<'_, Database = Postgres>,
@@ -20,20 +26,42 @@ pub async fn create_chart(
         table_id,
         name,
         chart_kind,
+        row_limit,
+        top_n,
+        cache_mode,
+        refresh_interval,
     }: CreateChart,
 ) -> sqlx::Result<Chart> {
     let mut tx = conn.begin().await?;
 
     let chart: Chart = sqlx::query_as(
         r#"
-            INSERT INTO chart (dashboard_id, table_id, name, chart_kind)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO chart (
+                dashboard_id,
+                table_id,
+                name,
+                chart_kind,
+                row_limit,
+                top_n,
+                cache_mode,
+                refresh_interval,
+                refreshed_at
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8,
+                CASE WHEN $7 = 'Materialized' THEN now() END
+            )
             RETURNING
                 chart_id,
                 dashboard_id,
                 table_id,
                 name,
                 chart_kind,
+                row_limit,
+                top_n,
+                cache_mode,
+                refresh_interval,
+                refreshed_at,
                 created_at,
                 updated_at
         "#,
@@ -42,84 +70,354 @@ pub async fn create_chart(
     .bind(table_id)
     .bind(name)
     .bind(chart_kind)
+    .bind(row_limit)
+    .bind(top_n)
+    .bind(cache_mode)
+    .bind(refresh_interval)
     .fetch_one(tx.as_mut())
     .await?;
 
-    let chart_ident = ChartIdentifier::new(chart.chart_id, "data_view");
-    // A view which always returns zero rows
-    sqlx::query(&format!(
+    // A view which always returns zero rows without axes
+    let mut builder = QueryBuilder::new(create_view_statement(&chart));
+    push_chart_query(&mut builder, &chart, &[], &[], &[]);
+    builder.build().execute(tx.as_mut()).await?;
+
+    tx.commit().await?;
+
+    Ok(chart)
+}
+
+pub async fn update_chart(
+    conn: impl Acquire<'_, Database = Postgres>,
+    chart_id: Id,
+    UpdateChart {
+        name,
+        chart_kind,
+        row_limit,
+        top_n,
+        cache_mode,
+        refresh_interval,
+    }: UpdateChart,
+) -> sqlx::Result<Chart> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
         r#"
-            CREATE VIEW {chart_ident} AS
-            SELECT NULL WHERE FALSE
-        "#
-    ))
+            UPDATE chart
+            SET
+                name = $1,
+                chart_kind = $2,
+                row_limit = $3,
+                top_n = $4,
+                cache_mode = $5,
+                refresh_interval = $6
+            WHERE chart_id = $7
+        "#,
+    )
+    .bind(name)
+    .bind(chart_kind)
+    .bind(row_limit)
+    .bind(top_n)
+    .bind(cache_mode)
+    .bind(refresh_interval)
+    .bind(chart_id)
     .execute(tx.as_mut())
     .await?;
 
+    // The grouping of the view depends on the chart kind
+    // and the view is materialized depending on the cache mode
+    rebuild_chart_view(tx.as_mut(), chart_id).await?;
+
+    let chart = get_chart(tx.as_mut(), chart_id).await?;
+
     tx.commit().await?;
 
     Ok(chart)
 }
 
-pub async fn update_chart(
+/// Move a chart to the trash.
+pub async fn delete_chart(
     conn: impl Acquire<'_, Database = Postgres>,
     chart_id: Id,
-    UpdateChart { name, chart_kind }: UpdateChart,
-) -> sqlx::Result<Chart> {
+) -> sqlx::Result<()> {
     let mut tx = conn.begin().await?;
 
-    let chart = sqlx::query_as(
+    sqlx::query(
         r#"
             UPDATE chart
-            SET name = $1, chart_kind = $2
-            WHERE chart_id = $3
+            SET deleted_at = now()
+            WHERE chart_id = $1
+        "#,
+    )
+    .bind(chart_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Restore a chart from the trash.
+pub async fn restore_chart(
+    conn: impl Acquire<'_, Database = Postgres>,
+    chart_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            UPDATE chart
+            SET deleted_at = NULL
+            WHERE chart_id = $1
+        "#,
+    )
+    .bind(chart_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Permanently delete a chart with its axes and view.
+pub async fn purge_chart(
+    conn: impl Acquire<'_, Database = Postgres>,
+    chart_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            DELETE FROM chart
+            WHERE chart_id = $1
+        "#,
+    )
+    .bind(chart_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    drop_chart_view(tx.as_mut(), chart_id).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Copy a chart with its axes and its own filters to a dashboard and a table.
+///
+/// The fields of the axes and filters in `field_ids` are replaced by the mapped fields,
+/// the other fields are kept.
+pub async fn copy_chart(
+    conn: impl Acquire<'_, Database = Postgres>,
+    chart_id: Id,
+    dashboard_id: Id,
+    table_id: Id,
+    field_ids: &HashMap<Id, Id>,
+) -> sqlx::Result<Chart> {
+    let mut tx = conn.begin().await?;
+
+    let copy: Chart = sqlx::query_as(
+        r#"
+            INSERT INTO chart (
+                dashboard_id,
+                table_id,
+                name,
+                chart_kind,
+                row_limit,
+                top_n,
+                cache_mode,
+                refresh_interval
+            )
+            SELECT
+                $2,
+                $3,
+                name,
+                chart_kind,
+                row_limit,
+                top_n,
+                cache_mode,
+                refresh_interval
+            FROM chart
+            WHERE chart_id = $1
             RETURNING
                 chart_id,
                 dashboard_id,
                 table_id,
                 name,
                 chart_kind,
+                row_limit,
+                top_n,
+                cache_mode,
+                refresh_interval,
+                refreshed_at,
                 created_at,
                 updated_at
         "#,
     )
-    .bind(name)
-    .bind(chart_kind)
     .bind(chart_id)
+    .bind(dashboard_id)
+    .bind(table_id)
     .fetch_one(tx.as_mut())
     .await?;
 
+    let map_field = |field_id: Id| field_ids.get(&field_id).copied().unwrap_or(field_id);
+
+    let axes = get_axis_fields(tx.as_mut(), chart_id).await?;
+    if !axes.is_empty() {
+        QueryBuilder::new(
+            r#"
+                INSERT INTO axis (
+                    chart_id,
+                    field_id,
+                    axis_kind,
+                    aggregate,
+                    time_bucket,
+                    time_zone,
+                    fill_gaps,
+                    sort
+                )
+            "#,
+        )
+        .push_values(axes, |mut builder, AxisField { axis, .. }| {
+            let aggregate = axis
+                .aggregate
+                .map(|Json(aggregate)| Json(aggregate.map_sort_field_id(map_field)));
+            builder
+                .push_bind(copy.chart_id)
+                .push_bind(map_field(axis.field_id))
+                .push_bind(axis.axis_kind)
+                .push_bind(aggregate)
+                .push_bind(axis.time_bucket)
+                .push_bind(axis.time_zone)
+                .push_bind(axis.fill_gaps)
+                .push_bind(axis.sort);
+        })
+        .build()
+        .execute(tx.as_mut())
+        .await?;
+    }
+
+    sqlx::query(
+        r#"
+            INSERT INTO filter (dashboard_id, chart_id, field_id, filter_kind)
+            SELECT $2, $3, COALESCE(m.copy_id, fl.field_id), fl.filter_kind
+            FROM filter AS fl
+            LEFT JOIN (
+                SELECT
+                    unnest($4::int[]) AS field_id,
+                    unnest($5::int[]) AS copy_id
+            ) AS m
+            ON fl.field_id = m.field_id
+            WHERE fl.chart_id = $1
+            ORDER BY fl.filter_id
+        "#,
+    )
+    .bind(chart_id)
+    .bind(dashboard_id)
+    .bind(copy.chart_id)
+    .bind(field_ids.keys().collect_vec())
+    .bind(field_ids.values().collect_vec())
+    .execute(tx.as_mut())
+    .await?;
+
+    // The view is created like the one of a new chart, then rebuilt with the copied axes
+    let mut builder = QueryBuilder::new(create_view_statement(&copy));
+    push_chart_query(&mut builder, &copy, &[], &[], &[]);
+    builder.build().execute(tx.as_mut()).await?;
+    rebuild_chart_view(tx.as_mut(), copy.chart_id).await?;
+
+    let copy = get_chart(tx.as_mut(), copy.chart_id).await?;
+
     tx.commit().await?;
 
-    Ok(chart)
+    Ok(copy)
 }
 
-pub async fn delete_chart(
+/// Drop the view of a chart, which is materialized if the chart was cached in one.
+pub async fn drop_chart_view(
     conn: impl Acquire<'_, Database = Postgres>,
     chart_id: Id,
 ) -> sqlx::Result<()> {
     let mut tx = conn.begin().await?;
 
-    sqlx::query(
+    let is_materialized: bool = sqlx::query_scalar(
         r#"
-            DELETE FROM chart
-            WHERE chart_id = $1
+            SELECT EXISTS (
+                SELECT 1
+                FROM pg_matviews
+                WHERE schemaname = 'data_view' AND matviewname = $1
+            )
         "#,
     )
-    .bind(chart_id)
-    .execute(tx.as_mut())
+    .bind(format!("c{chart_id}"))
+    .fetch_one(tx.as_mut())
     .await?;
 
     let chart_ident = ChartIdentifier::new(chart_id, "data_view");
-    sqlx::query(&format!(r#"DROP VIEW {chart_ident}"#))
+    let view_kind = if is_materialized {
+        "MATERIALIZED VIEW"
+    } else {
+        "VIEW"
+    };
+    sqlx::query(&format!(r#"DROP {view_kind} {chart_ident}"#))
+        .execute(tx.as_mut())
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Refresh the materialized view of a chart.
+pub async fn refresh_chart_view(
+    conn: impl Acquire<'_, Database = Postgres>,
+    chart_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    let chart_ident = ChartIdentifier::new(chart_id, "data_view");
+    sqlx::query(&format!(r#"REFRESH MATERIALIZED VIEW {chart_ident}"#))
         .execute(tx.as_mut())
         .await?;
 
+    sqlx::query(
+        r#"
+            UPDATE chart
+            SET refreshed_at = now()
+            WHERE chart_id = $1
+        "#,
+    )
+    .bind(chart_id)
+    .execute(tx.as_mut())
+    .await?;
+
     tx.commit().await?;
 
     Ok(())
 }
 
+/// Get the materialized charts which were not refreshed since their refresh interval.
+pub async fn get_stale_chart_ids(executor: impl PgExecutor<'_>) -> sqlx::Result<Vec<Id>> {
+    sqlx::query_scalar(
+        r#"
+            SELECT chart_id
+            FROM chart
+            WHERE cache_mode = 'Materialized'
+                AND refresh_interval IS NOT NULL
+                AND deleted_at IS NULL
+                AND (
+                    refreshed_at IS NULL
+                    OR refreshed_at + refresh_interval * INTERVAL '1 second' <= now()
+                )
+            ORDER BY refreshed_at NULLS FIRST
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+}
+
 pub async fn get_chart_table_id(executor: impl PgExecutor<'_>, chart_id: Id) -> sqlx::Result<Id> {
     sqlx::query_scalar(
         r#"
@@ -133,10 +431,7 @@ pub async fn get_chart_table_id(executor: impl PgExecutor<'_>, chart_id: Id) ->
     .await
 }
 
-pub async fn get_charts(
-    executor: impl PgExecutor<'_> + Copy,
-    dashboard_id: Id,
-) -> sqlx::Result<Vec<Chart>> {
+pub async fn get_chart(executor: impl PgExecutor<'_>, chart_id: Id) -> sqlx::Result<Chart> {
     sqlx::query_as(
         r#"
             SELECT
@@ -145,22 +440,27 @@ pub async fn get_charts(
                 table_id,
                 name,
                 chart_kind,
+                row_limit,
+                top_n,
+                cache_mode,
+                refresh_interval,
+                refreshed_at,
                 created_at,
                 updated_at
             FROM chart
-            WHERE dashboard_id = $1
+            WHERE chart_id = $1
         "#,
     )
-    .bind(dashboard_id)
-    .fetch_all(executor)
+    .bind(chart_id)
+    .fetch_one(executor)
     .await
 }
 
-pub async fn get_chart_data(
-    executor: impl PgExecutor<'_> + Copy,
-    chart_id: Id,
-) -> sqlx::Result<ChartData> {
-    let chart: Chart = sqlx::query_as(
+pub async fn get_charts(
+    executor: impl PgExecutor<'_>,
+    dashboard_id: Id,
+) -> sqlx::Result<Vec<Chart>> {
+    sqlx::query_as(
         r#"
             SELECT
                 chart_id,
@@ -168,80 +468,101 @@ pub async fn get_chart_data(
                 table_id,
                 name,
                 chart_kind,
+                row_limit,
+                top_n,
+                cache_mode,
+                refresh_interval,
+                refreshed_at,
                 created_at,
                 updated_at
             FROM chart
-            WHERE chart_id = $1
-        "#,
-    )
-    .bind(chart_id)
-    .fetch_one(executor)
-    .await?;
-
-    let axes: Vec<AxisField> = sqlx::query_as(
-        r#"
-            SELECT
-                a.axis_id,
-                a.chart_id,
-                a.field_id,
-                a.axis_kind,
-                a.aggregate,
-                a.created_at,
-                a.updated_at,
-                f.name AS field_name,
-                f.field_kind
-            FROM axis AS a
-            JOIN meta_field AS f
-            ON a.field_id = f.field_id
-            WHERE a.chart_id = $1
+            WHERE dashboard_id = $1 AND deleted_at IS NULL
         "#,
     )
-    .bind(chart_id)
+    .bind(dashboard_id)
     .fetch_all(executor)
-    .await?;
+    .await
+}
 
-    let chart_ident = ChartIdentifier::new(chart_id, "data_view");
-    let select_columns = axes
-        .iter()
-        .map(|axis_field| AxisIdentifier::new(axis_field.axis.axis_id))
-        .join(", ");
-    let rows = sqlx::query(&format!(
-        r#"
-            SELECT {select_columns}
-            FROM {chart_ident}
-        "#
-    ))
-    .fetch_all(executor)
-    .await?;
+/// Get the chart data, applying the filters of the chart.
+///
+/// The values of the filters in `overrides` replace the saved ones.
+/// Without filters, the chart view is queried directly,
+/// so the data of a materialized view is the one of its last refresh.
+pub async fn get_chart_data(
+    executor: impl PgExecutor<'_> + Copy,
+    chart_id: Id,
+    overrides: &HashMap<Id, FilterKind>,
+) -> sqlx::Result<ChartData> {
+    let chart = get_chart(executor, chart_id).await?;
+
+    let axes = get_axis_fields(executor, chart_id).await?;
+    let joins = get_chart_joins(executor, &chart, &axes).await?;
 
-    let mut cells: Vec<HashMap<Id, Cell>> = Vec::new();
-
-    for row in rows {
-        let mut entry = HashMap::new();
-        for AxisField {
-            axis, field_kind, ..
-        } in &axes
-        {
-            let axis_ident = AxisIdentifier::new(axis.axis_id);
-            entry.insert(
-                axis.axis_id,
-                axis.aggregate.as_ref().map_or_else(
-                    || Cell::from_field_row(&row, &axis_ident.unquoted(), &field_kind),
-                    |aggregate| {
-                        Cell::from_aggregate_row(
-                            &row,
-                            &axis_ident.unquoted(),
-                            aggregate,
-                            &field_kind,
-                        )
-                    },
-                )?,
-            );
+    let mut filters = get_chart_filters(executor, chart_id).await?;
+    for filter in &mut filters {
+        if let Some(filter_kind) = overrides.get(&filter.filter_id) {
+            filter.filter_kind = Json(filter_kind.clone());
         }
-        cells.push(entry);
     }
 
-    Ok(ChartData { chart, axes, cells })
+    let computed_at = match chart.refreshed_at {
+        Some(refreshed_at) if chart.cache_mode == CacheMode::Materialized && filters.is_empty() => {
+            refreshed_at
+        }
+        _ => Utc::now(),
+    };
+
+    let mut builder = QueryBuilder::new("");
+    push_data_query(&mut builder, &chart, &axes, &joins, &filters);
+    let rows = builder.build().fetch_all(executor).await?;
+    let cells = rows
+        .iter()
+        .map(|row| cells_from_row(row, &axes))
+        .try_collect()?;
+
+    let totals = if chart.chart_kind == ChartKind::Pivot {
+        let pivot_axes = get_pivot_axes(chart.chart_kind, &axes);
+        Some(
+            rows.iter()
+                .map(|row| {
+                    let grouping: i32 = row.try_get("grouping")?;
+                    // The first axis is the most significant bit
+                    Ok(pivot_axes
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| grouping & (1 << (pivot_axes.len() - 1 - i)) != 0)
+                        .map(|(_, axis)| axis.axis_id)
+                        .collect())
+                })
+                .collect::<sqlx::Result<_>>()?,
+        )
+    } else {
+        None
+    };
+
+    let other = if chart.top_n.is_some() {
+        let mut builder = QueryBuilder::new("");
+        push_other_query(&mut builder, &chart, &axes, &joins, &filters);
+        let row = builder.build().fetch_one(executor).await?;
+        if row.try_get::<i64, _>("other_count")? > 0 {
+            Some(cells_from_row(&row, &axes)?)
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    Ok(ChartData {
+        chart,
+        axes,
+        filters,
+        cells,
+        other,
+        totals,
+        computed_at,
+    })
 }
 
 pub async fn check_chart_relation(
@@ -253,7 +574,7 @@ pub async fn check_chart_relation(
         r#"
             SELECT dashboard_id
             FROM chart
-            WHERE chart_id = $1
+            WHERE chart_id = $1 AND deleted_at IS NULL
         "#,
     )
     .bind(chart_id)
@@ -265,3 +586,29 @@ pub async fn check_chart_relation(
         Some(_) => Relation::NotOwned,
     })
 }
+
+fn cells_from_row(row: &PgRow, axes: &[AxisField]) -> sqlx::Result<HashMap<Id, Cell>> {
+    axes.iter()
+        .map(
+            |AxisField {
+                 axis, field_kind, ..
+             }| {
+                let axis_ident = AxisIdentifier::new(axis.axis_id);
+                axis.aggregate
+                    .as_deref()
+                    .map_or_else(
+                        || Cell::from_field_row(row, &axis_ident.unquoted(), field_kind),
+                        |aggregate| {
+                            Cell::from_aggregate_row(
+                                row,
+                                &axis_ident.unquoted(),
+                                aggregate,
+                                field_kind,
+                            )
+                        },
+                    )
+                    .map(|cell| (axis.axis_id, cell))
+            },
+        )
+        .try_collect()
+}
@@ -1,9 +1,12 @@
+use super::copy_chart;
 use crate::{
-    db::Relation,
-    model::viz::{ChartIdentifier, CreateDashboard, Dashboard, UpdateDashboard},
+    db::{drop_chart_view, get_charts, Relation},
+    model::viz::{CreateDashboard, Dashboard, UpdateDashboard},
     Id,
 };
+use itertools::Itertools;
 use sqlx::{Acquire, PgExecutor, Postgres};
+use std::collections::HashMap;
 
 pub async fn create_dashboard(
     conn: impl Acquire<'_, Database = Postgres>,
@@ -68,12 +71,59 @@ pub async fn update_dashboard(
     Ok(dashboard)
 }
 
+/// Move a dashboard to the trash. Its charts are kept with it.
 pub async fn delete_dashboard(
     conn: impl Acquire<'_, Database = Postgres>,
     dashboard_id: Id,
 ) -> sqlx::Result<()> {
     let mut tx = conn.begin().await?;
 
+    sqlx::query(
+        r#"
+            UPDATE dashboard
+            SET deleted_at = now()
+            WHERE dashboard_id = $1
+        "#,
+    )
+    .bind(dashboard_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Restore a dashboard from the trash.
+pub async fn restore_dashboard(
+    conn: impl Acquire<'_, Database = Postgres>,
+    dashboard_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            UPDATE dashboard
+            SET deleted_at = NULL
+            WHERE dashboard_id = $1
+        "#,
+    )
+    .bind(dashboard_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Permanently delete a dashboard with its charts.
+pub async fn purge_dashboard(
+    conn: impl Acquire<'_, Database = Postgres>,
+    dashboard_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
     let chart_ids: Vec<Id> = sqlx::query_scalar(
         r#"
             DELETE FROM chart
@@ -86,10 +136,7 @@ pub async fn delete_dashboard(
     .await?;
 
     for chart_id in chart_ids {
-        let chart_ident = ChartIdentifier::new(chart_id, "data_view");
-        sqlx::query(&format!(r#"DROP VIEW {chart_ident}"#))
-            .execute(tx.as_mut())
-            .await?;
+        drop_chart_view(tx.as_mut(), chart_id).await?;
     }
 
     sqlx::query(
@@ -107,6 +154,89 @@ pub async fn delete_dashboard(
     Ok(())
 }
 
+/// Copy a dashboard with its charts, filters and layout.
+/// The reports of the dashboard are not copied.
+pub async fn copy_dashboard(
+    conn: impl Acquire<'_, Database = Postgres>,
+    dashboard_id: Id,
+    name: Option<String>,
+) -> sqlx::Result<Dashboard> {
+    let mut tx = conn.begin().await?;
+
+    let dashboard: Dashboard = sqlx::query_as(
+        r#"
+            INSERT INTO dashboard (user_id, name, description)
+            SELECT user_id, COALESCE($2, name), description
+            FROM dashboard
+            WHERE dashboard_id = $1
+            RETURNING
+                dashboard_id,
+                user_id,
+                name,
+                description,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(dashboard_id)
+    .bind(name)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    let mut chart_ids = HashMap::new();
+    for chart in get_charts(tx.as_mut(), dashboard_id).await? {
+        let copy = copy_chart(
+            tx.as_mut(),
+            chart.chart_id,
+            dashboard.dashboard_id,
+            chart.table_id,
+            &HashMap::new(),
+        )
+        .await?;
+        chart_ids.insert(chart.chart_id, copy.chart_id);
+    }
+
+    sqlx::query(
+        r#"
+            INSERT INTO filter (dashboard_id, field_id, filter_kind)
+            SELECT $2, field_id, filter_kind
+            FROM filter
+            WHERE dashboard_id = $1 AND chart_id IS NULL
+            ORDER BY filter_id
+        "#,
+    )
+    .bind(dashboard_id)
+    .bind(dashboard.dashboard_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    sqlx::query(
+        r#"
+            INSERT INTO layout_item (dashboard_id, chart_id, block, positions)
+            SELECT $2, m.copy_id, li.block, li.positions
+            FROM layout_item AS li
+            LEFT JOIN (
+                SELECT
+                    unnest($3::int[]) AS chart_id,
+                    unnest($4::int[]) AS copy_id
+            ) AS m
+            ON li.chart_id = m.chart_id
+            WHERE li.dashboard_id = $1 AND (li.chart_id IS NULL OR m.copy_id IS NOT NULL)
+            ORDER BY li.item_id
+        "#,
+    )
+    .bind(dashboard_id)
+    .bind(dashboard.dashboard_id)
+    .bind(chart_ids.keys().collect_vec())
+    .bind(chart_ids.values().collect_vec())
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(dashboard)
+}
+
 pub async fn get_dashboards(
     executor: impl PgExecutor<'_>,
     user_id: Id,
@@ -121,7 +251,7 @@ pub async fn get_dashboards(
                 created_at,
                 updated_at
             FROM dashboard
-            WHERE user_id = $1
+            WHERE user_id = $1 AND deleted_at IS NULL
         "#,
     )
     .bind(user_id)
@@ -129,6 +259,28 @@ pub async fn get_dashboards(
     .await
 }
 
+pub async fn get_dashboard(
+    executor: impl PgExecutor<'_>,
+    dashboard_id: Id,
+) -> sqlx::Result<Dashboard> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                dashboard_id,
+                user_id,
+                name,
+                description,
+                created_at,
+                updated_at
+            FROM dashboard
+            WHERE dashboard_id = $1
+        "#,
+    )
+    .bind(dashboard_id)
+    .fetch_one(executor)
+    .await
+}
+
 pub async fn check_dashboard_relation(
     executor: impl PgExecutor<'_>,
     user_id: Id,
@@ -138,7 +290,7 @@ pub async fn check_dashboard_relation(
         r#"
             SELECT user_id
             FROM dashboard
-            WHERE dashboard_id = $1
+            WHERE dashboard_id = $1 AND deleted_at IS NULL
         "#,
     )
     .bind(dashboard_id)
@@ -0,0 +1,279 @@
+use crate::{
+    db::Relation,
+    model::viz::{CreateFilter, Filter, UpdateFilter},
+    Id,
+};
+use sqlx::{types::Json, Acquire, PgExecutor, Postgres};
+
+pub async fn create_filter(
+    conn: impl Acquire<'_, Database = Postgres>,
+    dashboard_id: Id,
+    CreateFilter {
+        chart_id,
+        field_id,
+        filter_kind,
+    }: CreateFilter,
+) -> sqlx::Result<Filter> {
+    let mut tx = conn.begin().await?;
+
+    let filter = sqlx::query_as(
+        r#"
+            INSERT INTO filter (dashboard_id, chart_id, field_id, filter_kind)
+            VALUES ($1, $2, $3, $4)
+            RETURNING
+                filter_id,
+                dashboard_id,
+                chart_id,
+                field_id,
+                filter_kind,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(dashboard_id)
+    .bind(chart_id)
+    .bind(field_id)
+    .bind(Json(filter_kind))
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(filter)
+}
+
+pub async fn update_filter(
+    conn: impl Acquire<'_, Database = Postgres>,
+    filter_id: Id,
+    UpdateFilter { filter_kind }: UpdateFilter,
+) -> sqlx::Result<Filter> {
+    let mut tx = conn.begin().await?;
+
+    let filter = sqlx::query_as(
+        r#"
+            UPDATE filter
+            SET filter_kind = $1
+            WHERE filter_id = $2
+            RETURNING
+                filter_id,
+                dashboard_id,
+                chart_id,
+                field_id,
+                filter_kind,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(Json(filter_kind))
+    .bind(filter_id)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(filter)
+}
+
+pub async fn delete_filter(
+    conn: impl Acquire<'_, Database = Postgres>,
+    filter_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            DELETE FROM filter
+            WHERE filter_id = $1
+        "#,
+    )
+    .bind(filter_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn get_filters(
+    executor: impl PgExecutor<'_>,
+    dashboard_id: Id,
+) -> sqlx::Result<Vec<Filter>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                filter_id,
+                dashboard_id,
+                chart_id,
+                field_id,
+                filter_kind,
+                created_at,
+                updated_at
+            FROM filter
+            WHERE dashboard_id = $1
+            ORDER BY filter_id
+        "#,
+    )
+    .bind(dashboard_id)
+    .fetch_all(executor)
+    .await
+}
+
+pub async fn get_filter(executor: impl PgExecutor<'_>, filter_id: Id) -> sqlx::Result<Filter> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                filter_id,
+                dashboard_id,
+                chart_id,
+                field_id,
+                filter_kind,
+                created_at,
+                updated_at
+            FROM filter
+            WHERE filter_id = $1
+        "#,
+    )
+    .bind(filter_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Get the filters applied to a chart.
+///
+/// These are the filters of the chart and the dashboard filters on the table of the chart.
+pub async fn get_chart_filters(
+    executor: impl PgExecutor<'_>,
+    chart_id: Id,
+) -> sqlx::Result<Vec<Filter>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                fl.filter_id,
+                fl.dashboard_id,
+                fl.chart_id,
+                fl.field_id,
+                fl.filter_kind,
+                fl.created_at,
+                fl.updated_at
+            FROM filter AS fl
+            JOIN meta_field AS f
+            ON fl.field_id = f.field_id
+            JOIN chart AS c
+            ON fl.dashboard_id = c.dashboard_id
+            WHERE c.chart_id = $1
+                AND f.deleted_at IS NULL
+                AND (
+                    fl.chart_id = c.chart_id
+                    OR (fl.chart_id IS NULL AND f.table_id = c.table_id)
+                )
+            ORDER BY fl.filter_id
+        "#,
+    )
+    .bind(chart_id)
+    .fetch_all(executor)
+    .await
+}
+
+/// Get the filters on a field.
+pub async fn get_field_filters(
+    executor: impl PgExecutor<'_>,
+    field_id: Id,
+) -> sqlx::Result<Vec<Filter>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                filter_id,
+                dashboard_id,
+                chart_id,
+                field_id,
+                filter_kind,
+                created_at,
+                updated_at
+            FROM filter
+            WHERE field_id = $1
+            ORDER BY filter_id
+        "#,
+    )
+    .bind(field_id)
+    .fetch_all(executor)
+    .await
+}
+
+/// Check if filters are on the field.
+pub async fn is_field_in_filters(
+    executor: impl PgExecutor<'_>,
+    field_id: Id,
+) -> sqlx::Result<bool> {
+    sqlx::query_scalar(
+        r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM filter
+                WHERE field_id = $1
+            )
+        "#,
+    )
+    .bind(field_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Move the filters on a field to another field.
+pub async fn repoint_field_filters(
+    executor: impl PgExecutor<'_>,
+    field_id: Id,
+    new_field_id: Id,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"
+            UPDATE filter
+            SET field_id = $2
+            WHERE field_id = $1
+        "#,
+    )
+    .bind(field_id)
+    .bind(new_field_id)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Check if the name is a time zone known by PostgreSQL.
+pub async fn is_valid_time_zone(executor: impl PgExecutor<'_>, name: &str) -> sqlx::Result<bool> {
+    sqlx::query_scalar(
+        r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM pg_timezone_names
+                WHERE name = $1
+            )
+        "#,
+    )
+    .bind(name)
+    .fetch_one(executor)
+    .await
+}
+
+pub async fn check_filter_relation(
+    executor: impl PgExecutor<'_>,
+    dashboard_id: Id,
+    filter_id: Id,
+) -> sqlx::Result<Relation> {
+    sqlx::query_scalar::<_, Id>(
+        r#"
+            SELECT dashboard_id
+            FROM filter
+            WHERE filter_id = $1
+        "#,
+    )
+    .bind(filter_id)
+    .fetch_optional(executor)
+    .await
+    .map(|id| match id {
+        None => Relation::Absent,
+        Some(id) if id == dashboard_id => Relation::Owned,
+        Some(_) => Relation::NotOwned,
+    })
+}
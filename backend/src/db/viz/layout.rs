@@ -0,0 +1,92 @@
+use crate::{
+    model::viz::{CreateLayoutItem, LayoutItem},
+    Id,
+};
+use sqlx::{types::Json, Acquire, PgExecutor, Postgres, QueryBuilder};
+
+/// Replace all the items of the layout of a dashboard.
+pub async fn set_layout(
+    conn: impl Acquire<'_, Database = Postgres>,
+    dashboard_id: Id,
+    items: Vec<CreateLayoutItem>,
+) -> sqlx::Result<Vec<LayoutItem>> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            DELETE FROM layout_item
+            WHERE dashboard_id = $1
+        "#,
+    )
+    .bind(dashboard_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    if items.is_empty() {
+        tx.commit().await?;
+        return Ok(Vec::new());
+    }
+
+    let items = QueryBuilder::new(
+        r#"
+            INSERT INTO layout_item (
+                dashboard_id,
+                chart_id,
+                block,
+                positions
+            )
+        "#,
+    )
+    .push_values(items, |mut builder, item| {
+        builder
+            .push_bind(dashboard_id)
+            .push_bind(item.chart_id)
+            .push_bind(item.block.map(Json))
+            .push_bind(Json(item.positions));
+    })
+    .push(
+        r#"
+            RETURNING
+                item_id,
+                dashboard_id,
+                chart_id,
+                block,
+                positions,
+                created_at,
+                updated_at
+        "#,
+    )
+    .build_query_as()
+    .fetch_all(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(items)
+}
+
+pub async fn get_layout(
+    executor: impl PgExecutor<'_>,
+    dashboard_id: Id,
+) -> sqlx::Result<Vec<LayoutItem>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                li.item_id,
+                li.dashboard_id,
+                li.chart_id,
+                li.block,
+                li.positions,
+                li.created_at,
+                li.updated_at
+            FROM layout_item AS li
+            LEFT JOIN chart AS c
+            ON li.chart_id = c.chart_id
+            WHERE li.dashboard_id = $1 AND c.deleted_at IS NULL
+            ORDER BY li.item_id
+        "#,
+    )
+    .bind(dashboard_id)
+    .fetch_all(executor)
+    .await
+}
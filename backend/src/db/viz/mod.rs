@@ -1,5 +1,756 @@
+//! Query functions for the Visualization feature.
+
 mod axes;
 mod charts;
 mod dashboards;
+mod filters;
+mod layout;
+mod reports;
+
+use crate::{
+    db::{get_related_tables, quote_literal},
+    model::{
+        data::{FieldIdentifier, TableIdentifier},
+        viz::{
+            Aggregate, Axis, AxisField, AxisIdentifier, AxisKind, CacheMode, Chart,
+            ChartIdentifier, ChartKind, Filter, FilterKind, SortDirection,
+        },
+    },
+    Id,
+};
+use itertools::Itertools;
+use sqlx::{types::Json, PgExecutor, Postgres, QueryBuilder};
+use std::collections::HashMap;
+pub use {axes::*, charts::*, dashboards::*, filters::*, layout::*, reports::*};
+
+/// A table joined to the query of a chart through the `parent_id` column.
+#[derive(Debug)]
+pub struct TableJoin {
+    table_id: Id,
+    /// The table already in the query which this table is joined to.
+    joined_to: Id,
+    /// This table is a child of the other table, otherwise it is its parent.
+    is_child: bool,
+}
+
+/// Get the joins from the table of a chart to the tables of its axes.
+///
+/// `related_tables` are the ancestors and descendants of the chart table mapped to their parent.
+/// Ancestors are joined by many-to-one joins which keep the rows of the chart table.
+/// Descendants are left joined, with one row per child entry, so they must all be
+/// on a single branch of the hierarchy or the rows of sibling tables would multiply.
+///
+/// Returns `None` if a table is not related or the descendants are on different branches.
+pub fn get_table_joins(
+    table_id: Id,
+    related_tables: &HashMap<Id, Option<Id>>,
+    joined_table_ids: impl IntoIterator<Item = Id>,
+) -> Option<Vec<TableJoin>> {
+    let mut ancestors = vec![table_id];
+    while let Some(Some(parent_id)) = related_tables.get(ancestors.last().unwrap()) {
+        ancestors.push(*parent_id);
+    }
+
+    let mut parent_depth = 0;
+    let mut child_branch: Vec<Id> = Vec::new();
+    for joined_table_id in joined_table_ids {
+        if let Some(depth) = ancestors.iter().position(|id| *id == joined_table_id) {
+            parent_depth = parent_depth.max(depth);
+            continue;
+        }
+
+        let mut branch = vec![joined_table_id];
+        loop {
+            match related_tables.get(branch.last().unwrap())? {
+                Some(parent_id) if *parent_id == table_id => break,
+                Some(parent_id) => branch.push(*parent_id),
+                None => return None,
+            }
+        }
+        branch.reverse();
+
+        if branch.starts_with(&child_branch) {
+            child_branch = branch;
+        } else if !child_branch.starts_with(&branch) {
+            return None;
+        }
+    }
+
+    let parent_joins =
+        ancestors
+            .iter()
+            .tuple_windows()
+            .take(parent_depth)
+            .map(|(child_id, parent_id)| TableJoin {
+                table_id: *parent_id,
+                joined_to: *child_id,
+                is_child: false,
+            });
+    let child_joins = std::iter::once(&table_id)
+        .chain(&child_branch)
+        .tuple_windows()
+        .map(|(parent_id, child_id)| TableJoin {
+            table_id: *child_id,
+            joined_to: *parent_id,
+            is_child: true,
+        });
+
+    Some(parent_joins.chain(child_joins).collect())
+}
+
+/// Get the joins from the table of a chart to the tables of its axes,
+/// assuming the axes were validated with [get_table_joins].
+async fn get_chart_joins(
+    executor: impl PgExecutor<'_>,
+    chart: &Chart,
+    axes: &[AxisField],
+) -> sqlx::Result<Vec<TableJoin>> {
+    if axes
+        .iter()
+        .all(|axis_field| axis_field.table_id == chart.table_id)
+    {
+        return Ok(Vec::new());
+    }
+    let related_tables = get_related_tables(executor, chart.table_id).await?;
+    get_table_joins(
+        chart.table_id,
+        &related_tables,
+        axes.iter().map(|axis_field| axis_field.table_id),
+    )
+    .ok_or_else(|| {
+        sqlx::Error::Protocol(format!(
+            "The axes of chart {} can not be joined to its table",
+            chart.chart_id
+        ))
+    })
+}
+
+/// Get the start of the statement creating the view of a chart, before its query.
+fn create_view_statement(chart: &Chart) -> String {
+    let chart_ident = ChartIdentifier::new(chart.chart_id, "data_view");
+    match chart.cache_mode {
+        CacheMode::Materialized => format!("CREATE MATERIALIZED VIEW {chart_ident} AS "),
+        CacheMode::Live | CacheMode::Memory => format!("CREATE VIEW {chart_ident} AS "),
+    }
+}
+
+/// Push the query of the chart data, sorted and limited.
+///
+/// The chart view is used when there are no filters.
+fn push_data_query(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    chart: &Chart,
+    axes: &[AxisField],
+    joins: &[TableJoin],
+    filters: &[Filter],
+) {
+    builder.push("WITH data AS (");
+    if filters.is_empty() {
+        let chart_ident = ChartIdentifier::new(chart.chart_id, "data_view");
+        builder.push(format!("SELECT * FROM {chart_ident}"));
+    } else {
+        push_chart_query(builder, chart, axes, joins, filters);
+    }
+
+    let select_columns = axes
+        .iter()
+        .map(|axis_field| AxisIdentifier::new(axis_field.axis.axis_id).to_string())
+        .chain((chart.chart_kind == ChartKind::Pivot).then(|| r#""grouping""#.to_string()))
+        .join(", ");
+    builder.push(format!(") SELECT {select_columns} FROM data"));
+
+    let order_by = get_order_by(axes, chart.top_n.is_some());
+    if !order_by.is_empty() {
+        builder.push(format!(" ORDER BY {order_by}"));
+    }
+
+    if let Some(limit) = chart.top_n.or(chart.row_limit) {
+        builder.push(" LIMIT ").push_bind(limit);
+    }
+}
+
+/// Push the query aggregating the rows of the table which are not in the top N rows of the chart data.
+///
+/// The axes without aggregate are null and `other_count` is the number of rows aggregated.
+fn push_other_query(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    chart: &Chart,
+    axes: &[AxisField],
+    joins: &[TableJoin],
+    filters: &[Filter],
+) {
+    builder.push("WITH top AS (");
+    push_data_query(builder, chart, axes, joins, filters);
+    builder.push(") SELECT COUNT(*) AS other_count");
+
+    // The other rows are aggregated together, so their entries are counted once overall
+    let groupings = [(Vec::new(), 0)];
+    let mut top_conditions = Vec::new();
+    for axis_field in axes {
+        let axis = &axis_field.axis;
+        let column = axis_column(axis);
+        let axis_ident = AxisIdentifier::new(axis.axis_id);
+        if let Some(Json(aggregate)) = &axis.aggregate {
+            builder.push(format!(
+                ", ({})::{} AS {axis_ident}",
+                get_axis_aggregate(chart, axes, joins, &groupings, axis_field, aggregate),
+                aggregate.get_sql_type(&axis_field.field_kind),
+            ));
+        } else {
+            builder.push(format!(", NULL AS {axis_ident}"));
+            top_conditions.push(format!("top.{axis_ident} IS NOT DISTINCT FROM {column}"));
+        }
+    }
+
+    let top_condition = format!(
+        "NOT EXISTS (SELECT 1 FROM top WHERE {})",
+        top_conditions
+            .into_iter()
+            .chain(Some("TRUE".to_string()))
+            .join(" AND ")
+    );
+    push_from(
+        builder,
+        chart,
+        axes,
+        joins,
+        &groupings,
+        filters,
+        Some(&top_condition),
+    );
+}
+
+/// Get the `ORDER BY` items of the chart data.
+///
+/// The data is sorted by the sorted axes. Otherwise, the top N rows are the ones with
+/// the highest first aggregate and time buckets with gaps filled are in chronological order.
+fn get_order_by(axes: &[AxisField], is_top_n: bool) -> String {
+    let order_by = axes
+        .iter()
+        .filter_map(|AxisField { axis, .. }| {
+            axis.sort.map(|sort| {
+                format!(
+                    "{} {} NULLS LAST",
+                    AxisIdentifier::new(axis.axis_id),
+                    sort.get_sql_order()
+                )
+            })
+        })
+        .join(", ");
+    if !order_by.is_empty() {
+        return order_by;
+    }
+
+    let default_axis = if is_top_n {
+        axes.iter()
+            .map(|axis_field| &axis_field.axis)
+            .find(|axis| axis.aggregate.is_some())
+            .map(|axis| (axis, SortDirection::Descending))
+    } else {
+        axes.iter()
+            .map(|axis_field| &axis_field.axis)
+            .find(|axis| axis.fill_gaps && axis.time_bucket.is_some())
+            .map(|axis| (axis, SortDirection::Ascending))
+    };
+
+    default_axis
+        .map(|(axis, sort)| {
+            format!(
+                "{} {} NULLS LAST",
+                AxisIdentifier::new(axis.axis_id),
+                sort.get_sql_order()
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Push the query selecting the axes of a chart from its table.
+///
+/// The filters are pushed as a `WHERE` clause with bind parameters,
+/// so the query can not be used in a view unless there are no filters.
+fn push_chart_query(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    chart: &Chart,
+    axes: &[AxisField],
+    joins: &[TableJoin],
+    filters: &[Filter],
+) {
+    if axes.is_empty() {
+        // A query which always returns zero rows
+        if chart.chart_kind == ChartKind::Pivot {
+            builder.push(r#"SELECT 0 AS "grouping" WHERE FALSE"#);
+        } else {
+            builder.push("SELECT NULL WHERE FALSE");
+        }
+        return;
+    }
+
+    let gap_axis = axes
+        .iter()
+        .map(|axis_field| &axis_field.axis)
+        .find(|axis| axis.fill_gaps && axis.time_bucket.is_some());
+
+    if let Some(gap_axis) = gap_axis {
+        builder.push("WITH data AS (");
+        push_grouped_query(builder, chart, axes, joins, filters);
+        builder.push(") ");
+        push_filled_query(builder, gap_axis, axes);
+    } else {
+        push_grouped_query(builder, chart, axes, joins, filters);
+    }
+}
+
+/// Push the query aggregating the axes, grouped by the axes without aggregate.
+///
+/// Pivot charts are grouped by the rollups of the row and column axes,
+/// and the `grouping` column is the mask of the axes totaled in each row.
+fn push_grouped_query(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    chart: &Chart,
+    axes: &[AxisField],
+    joins: &[TableJoin],
+    filters: &[Filter],
+) {
+    let group_by_columns = axes
+        .iter()
+        .filter(|axis_field| axis_field.axis.aggregate.is_none())
+        .map(|axis_field| axis_column(&axis_field.axis))
+        .collect_vec();
+    let pivot_axes = get_pivot_axes(chart.chart_kind, axes);
+    let groupings = if chart.chart_kind == ChartKind::Pivot {
+        get_pivot_groupings(&pivot_axes)
+    } else {
+        vec![(group_by_columns.clone(), 0)]
+    };
+
+    builder.push("SELECT ");
+    let mut select = builder.separated(", ");
+    for axis_field in axes {
+        let axis = &axis_field.axis;
+        let axis_ident = AxisIdentifier::new(axis.axis_id);
+        if let Some(Json(aggregate)) = &axis.aggregate {
+            select.push(format!(
+                "({})::{} AS {axis_ident}",
+                get_axis_aggregate(chart, axes, joins, &groupings, axis_field, aggregate),
+                aggregate.get_sql_type(&axis_field.field_kind),
+            ));
+        } else {
+            select.push(format!("{} AS {axis_ident}", axis_column(axis)));
+        }
+    }
+
+    if chart.chart_kind == ChartKind::Pivot {
+        select.push(format!(r#"{} AS "grouping""#, get_grouping(&pivot_axes)));
+    }
+
+    push_from(builder, chart, axes, joins, &groupings, filters, None);
+
+    if chart.chart_kind == ChartKind::Pivot {
+        let rollups = [AxisKind::Row, AxisKind::Column]
+            .into_iter()
+            .map(|axis_kind| {
+                pivot_axes
+                    .iter()
+                    .filter(|axis| axis.axis_kind == axis_kind)
+                    .map(|axis| axis_column(axis))
+                    .join(", ")
+            })
+            .filter(|columns| !columns.is_empty())
+            .map(|columns| format!("ROLLUP ({columns})"))
+            .join(", ");
+        if !rollups.is_empty() {
+            builder.push(format!(" GROUP BY {rollups}"));
+        }
+    } else if !group_by_columns.is_empty() {
+        builder.push(format!(" GROUP BY {}", group_by_columns.join(", ")));
+    }
+}
+
+/// Get the grouping sets of the rollups of a pivot chart, as the grouped columns
+/// and the mask of the axes totaled, with the bits in the order of the pivot axes.
+fn get_pivot_groupings(pivot_axes: &[&Axis]) -> Vec<(Vec<String>, i32)> {
+    let columns = pivot_axes
+        .iter()
+        .map(|axis| axis_column(axis))
+        .collect_vec();
+    let row_count = pivot_axes
+        .iter()
+        .filter(|axis| axis.axis_kind == AxisKind::Row)
+        .count();
+    let column_count = columns.len() - row_count;
+
+    (0..=row_count)
+        .cartesian_product(0..=column_count)
+        .map(|(rows, columns_grouped)| {
+            let grouped = (0..rows)
+                .chain(row_count..row_count + columns_grouped)
+                .collect_vec();
+            let mask = (0..columns.len())
+                .filter(|i| !grouped.contains(i))
+                .map(|i| 1 << (columns.len() - 1 - i))
+                .sum();
+            (grouped.iter().map(|i| columns[*i].clone()).collect(), mask)
+        })
+        .collect()
+}
+
+/// Get the `GROUPING` expression of the pivot axes, which is the mask of the axes totaled.
+fn get_grouping(pivot_axes: &[&Axis]) -> String {
+    if pivot_axes.is_empty() {
+        "0".to_string()
+    } else {
+        format!(
+            "GROUPING({})",
+            pivot_axes.iter().map(|axis| axis_column(axis)).join(", ")
+        )
+    }
+}
+
+/// Get the SQL expression aggregating an axis.
+///
+/// When the rows of the table of the axis are repeated by the joins of its child tables,
+/// only the first row of each entry in the group is aggregated. With several `groupings`,
+/// the rows numbered for the grouping set of the result row are used.
+fn get_axis_aggregate(
+    chart: &Chart,
+    axes: &[AxisField],
+    joins: &[TableJoin],
+    groupings: &[(Vec<String>, i32)],
+    axis_field: &AxisField,
+    aggregate: &Aggregate,
+) -> String {
+    let column = axis_column(&axis_field.axis);
+    let Some(table_id) = get_repeated_table(chart, joins, axis_field.table_id) else {
+        return aggregate.get_sql_aggregate(&column, None);
+    };
+
+    let aggregates = groupings
+        .iter()
+        .enumerate()
+        .map(|(i, (_, mask))| {
+            let condition = format!("{} = 1", row_number_ident(table_id, i));
+            (mask, aggregate.get_sql_aggregate(&column, Some(&condition)))
+        })
+        .collect_vec();
+    match aggregates.as_slice() {
+        [(_, aggregate)] => aggregate.clone(),
+        _ => format!(
+            "CASE {} {} END",
+            get_grouping(&get_pivot_axes(chart.chart_kind, axes)),
+            aggregates
+                .iter()
+                .map(|(mask, aggregate)| format!("WHEN {mask} THEN {aggregate}"))
+                .join(" ")
+        ),
+    }
+}
+
+/// Get the tables whose rows are repeated for each row of their joined child table,
+/// with the columns identifying their entries in the joined rows.
+///
+/// Child tables are joined on a single branch, so an entry of a table is identified by
+/// the entries of the chart table and the child tables down to it.
+fn get_repeated_tables(chart: &Chart, joins: &[TableJoin]) -> Vec<(Id, Vec<String>)> {
+    let branch = std::iter::once(chart.table_id)
+        .chain(
+            joins
+                .iter()
+                .filter(|join| join.is_child)
+                .map(|join| join.table_id),
+        )
+        .collect_vec();
+    let entry_columns = branch
+        .iter()
+        .map(|table_id| format!("{}.entry_id", TableIdentifier::new(*table_id, "data_table")))
+        .collect_vec();
+
+    (0..branch.len() - 1)
+        .map(|i| (branch[i], entry_columns[..=i].to_vec()))
+        .collect()
+}
+
+/// Get the table whose entries identify the rows of a table in the chart query,
+/// if they are repeated by the joins of child tables.
+///
+/// The rows of the ancestors of the chart table are repeated for each row of the chart table,
+/// which is kept, so they are identified by the entries of the chart table.
+fn get_repeated_table(chart: &Chart, joins: &[TableJoin], table_id: Id) -> Option<Id> {
+    let mut child_ids = joins
+        .iter()
+        .filter(|join| join.is_child)
+        .map(|join| join.table_id);
+    let deepest_id = child_ids.next_back()?;
+    if table_id == deepest_id {
+        None
+    } else if child_ids.any(|child_id| child_id == table_id) {
+        Some(table_id)
+    } else {
+        Some(chart.table_id)
+    }
+}
+
+/// Get the identifier of the column numbering the rows of each entry of a table
+/// within the groups of a grouping set.
+fn row_number_ident(table_id: Id, grouping: usize) -> String {
+    format!(r#""row_{table_id}_{grouping}""#)
+}
+
+/// Push the `FROM` clause of the table of the chart and its joined tables,
+/// with the filters and the condition as a `WHERE` clause.
+///
+/// Field identifiers are unique across tables, so the columns of the axes need no qualifier.
+/// When child tables are joined, the rows of the other tables are repeated for each child row.
+/// The joined rows are then selected in a subquery numbering the rows of each entry
+/// within the groups of each of the `groupings`, so they can be aggregated once.
+fn push_from(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    chart: &Chart,
+    axes: &[AxisField],
+    joins: &[TableJoin],
+    groupings: &[(Vec<String>, i32)],
+    filters: &[Filter],
+    condition: Option<&str>,
+) {
+    let repeated_tables = get_repeated_tables(chart, joins);
+    if repeated_tables.is_empty() {
+        push_joins(builder, chart, joins);
+        push_conditions(builder, filters, condition);
+        return;
+    }
+
+    let field_columns = axes
+        .iter()
+        .flat_map(|axis_field| {
+            std::iter::once(axis_field.axis.field_id).chain(
+                axis_field
+                    .axis
+                    .aggregate
+                    .as_ref()
+                    .and_then(|aggregate| aggregate.get_sort_field_id()),
+            )
+        })
+        .unique()
+        .map(|field_id| FieldIdentifier::new(field_id).to_string());
+    let row_numbers = groupings
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (group_by_columns, _))| {
+            repeated_tables
+                .iter()
+                .map(move |(table_id, entry_columns)| {
+                    format!(
+                        "row_number() OVER (PARTITION BY {}) AS {}",
+                        group_by_columns.iter().chain(entry_columns).join(", "),
+                        row_number_ident(*table_id, i)
+                    )
+                })
+        });
+
+    builder.push(format!(
+        " FROM (SELECT {}",
+        field_columns.chain(row_numbers).join(", ")
+    ));
+    push_joins(builder, chart, joins);
+    push_conditions(builder, filters, condition);
+    builder.push(") AS joined");
+}
+
+fn push_joins(builder: &mut QueryBuilder<'_, Postgres>, chart: &Chart, joins: &[TableJoin]) {
+    let table_ident = TableIdentifier::new(chart.table_id, "data_table");
+    builder.push(format!(" FROM {table_ident}"));
+    for join in joins {
+        let table_ident = TableIdentifier::new(join.table_id, "data_table");
+        let joined_ident = TableIdentifier::new(join.joined_to, "data_table");
+        if join.is_child {
+            builder.push(format!(
+                " LEFT JOIN {table_ident} ON {table_ident}.parent_id = {joined_ident}.entry_id"
+            ));
+        } else {
+            builder.push(format!(
+                " JOIN {table_ident} ON {table_ident}.entry_id = {joined_ident}.parent_id"
+            ));
+        }
+    }
+}
+
+fn push_conditions(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    filters: &[Filter],
+    condition: Option<&str>,
+) {
+    if let Some(condition) = condition {
+        builder.push(format!(" WHERE {condition}"));
+    }
+    for (i, filter) in filters.iter().enumerate() {
+        builder.push(if i == 0 && condition.is_none() {
+            " WHERE "
+        } else {
+            " AND "
+        });
+        push_filter_condition(builder, filter);
+    }
+}
+
+/// Get the row axes followed by the column axes of a pivot chart,
+/// in the order of the bits of the `grouping` mask.
+fn get_pivot_axes(chart_kind: ChartKind, axes: &[AxisField]) -> Vec<&Axis> {
+    if chart_kind != ChartKind::Pivot {
+        return Vec::new();
+    }
+    [AxisKind::Row, AxisKind::Column]
+        .into_iter()
+        .flat_map(|axis_kind| {
+            axes.iter()
+                .map(|axis_field| &axis_field.axis)
+                .filter(move |axis| axis.axis_kind == axis_kind)
+        })
+        .collect()
+}
+
+/// Push the query adding the empty time buckets to the grouped `data`.
+///
+/// Every time bucket between the first and last ones is combined
+/// with every group of the other axes without aggregate.
+/// Missing counts are zero and other missing aggregates are null.
+fn push_filled_query(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    gap_axis: &Axis,
+    axes: &[AxisField],
+) {
+    let gap_ident = AxisIdentifier::new(gap_axis.axis_id);
+    let key_idents = axes
+        .iter()
+        .filter(|axis_field| {
+            axis_field.axis.aggregate.is_none() && axis_field.axis.axis_id != gap_axis.axis_id
+        })
+        .map(|axis_field| AxisIdentifier::new(axis_field.axis.axis_id))
+        .collect_vec();
+
+    let select_columns = axes
+        .iter()
+        .map(|AxisField { axis, .. }| {
+            let axis_ident = AxisIdentifier::new(axis.axis_id);
+            match axis.aggregate.as_deref() {
+                _ if axis.axis_id == gap_axis.axis_id => format!("b.{axis_ident} AS {axis_ident}"),
+                None => format!("k.{axis_ident} AS {axis_ident}"),
+                Some(Aggregate::Count | Aggregate::CountDistinct) => {
+                    format!("COALESCE(d.{axis_ident}, 0) AS {axis_ident}")
+                }
+                Some(_) => format!("d.{axis_ident} AS {axis_ident}"),
+            }
+        })
+        .join(", ");
+
+    let time_zone = quote_literal(axis_time_zone(gap_axis));
+    let interval = quote_literal(&gap_axis.time_bucket.unwrap().get_sql_interval(1));
+    builder.push(format!(
+        r#"
+            SELECT {select_columns}
+            FROM (
+                SELECT generate_series(
+                    min({gap_ident}) AT TIME ZONE {time_zone},
+                    max({gap_ident}) AT TIME ZONE {time_zone},
+                    {interval}::interval
+                ) AT TIME ZONE {time_zone} AS {gap_ident}
+                FROM data
+            ) AS b
+        "#
+    ));
+
+    if !key_idents.is_empty() {
+        builder.push(format!(
+            " CROSS JOIN (SELECT DISTINCT {} FROM data) AS k",
+            key_idents.iter().join(", ")
+        ));
+    }
+
+    builder.push(format!(
+        " LEFT JOIN data AS d ON d.{gap_ident} = b.{gap_ident}"
+    ));
+    for key_ident in &key_idents {
+        builder.push(format!(
+            " AND d.{key_ident} IS NOT DISTINCT FROM k.{key_ident}"
+        ));
+    }
+
+    builder.push(format!(" ORDER BY b.{gap_ident}"));
+}
+
+/// Get the SQL expression of the values of an axis before aggregation.
+fn axis_column(axis: &Axis) -> String {
+    let field_ident = FieldIdentifier::new(axis.field_id);
+    match axis.time_bucket {
+        Some(time_bucket) => format!(
+            "date_trunc('{}', {field_ident}, {})",
+            time_bucket.get_sql_unit(),
+            quote_literal(axis_time_zone(axis)),
+        ),
+        None => field_ident.to_string(),
+    }
+}
+
+fn axis_time_zone(axis: &Axis) -> &str {
+    axis.time_zone.as_deref().unwrap_or("UTC")
+}
 
-pub use {axes::*, charts::*, dashboards::*};
+fn push_filter_condition(builder: &mut QueryBuilder<'_, Postgres>, filter: &Filter) {
+    let field_ident = FieldIdentifier::new(filter.field_id);
+    match filter.filter_kind.0.clone() {
+        FilterKind::NumberRange { start, end } => {
+            builder.push("(TRUE");
+            if let Some(start) = start {
+                builder
+                    .push(format!(" AND {field_ident} >= "))
+                    .push_bind(start);
+            }
+            if let Some(end) = end {
+                builder
+                    .push(format!(" AND {field_ident} <= "))
+                    .push_bind(end);
+            }
+            builder.push(")");
+        }
+        FilterKind::DateRange { start, end } => {
+            builder.push("(TRUE");
+            if let Some(start) = start {
+                builder
+                    .push(format!(" AND {field_ident} >= "))
+                    .push_bind(start);
+            }
+            if let Some(end) = end {
+                builder
+                    .push(format!(" AND {field_ident} <= "))
+                    .push_bind(end);
+            }
+            builder.push(")");
+        }
+        FilterKind::LastPeriod { unit, amount } => {
+            builder
+                .push(format!("({field_ident} >= now() - "))
+                .push_bind(unit.get_sql_interval(amount))
+                .push(format!("::interval AND {field_ident} <= now())"));
+        }
+        FilterKind::CurrentPeriod { unit, time_zone } => {
+            let time_zone = time_zone.unwrap_or_else(|| "UTC".to_string());
+            let sql_unit = unit.get_sql_unit();
+            builder
+                .push(format!("date_trunc('{sql_unit}', {field_ident}, "))
+                .push_bind(time_zone.clone())
+                .push(format!(") = date_trunc('{sql_unit}', now(), "))
+                .push_bind(time_zone)
+                .push(")");
+        }
+        FilterKind::Enumeration { values } => {
+            builder
+                .push(format!("{field_ident} = ANY("))
+                .push_bind(values)
+                .push(")");
+        }
+        FilterKind::Text { value } => {
+            builder.push(format!("{field_ident} = ")).push_bind(value);
+        }
+        FilterKind::Checkbox { value } => {
+            builder.push(format!("{field_ident} = ")).push_bind(value);
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use crate::{
+    db::Relation,
+    model::viz::{
+        CreateReport, PendingReportRun, Report, ReportRun, ReportRunStatus, UpdateReport,
+    },
+    Id,
+};
+use sqlx::{Acquire, PgExecutor, Postgres};
+
+pub async fn create_report(
+    conn: impl Acquire<'_, Database = Postgres>,
+    dashboard_id: Id,
+    CreateReport {
+        recipients,
+        frequency,
+        time_zone,
+        next_run_at,
+    }: CreateReport,
+) -> sqlx::Result<Report> {
+    let mut tx = conn.begin().await?;
+
+    let report = sqlx::query_as(
+        r#"
+            INSERT INTO report (dashboard_id, recipients, frequency, time_zone, next_run_at)
+            VALUES ($1, $2, $3, COALESCE($4, 'UTC'), $5)
+            RETURNING
+                report_id,
+                dashboard_id,
+                recipients,
+                frequency,
+                time_zone,
+                next_run_at,
+                is_active,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(dashboard_id)
+    .bind(recipients)
+    .bind(frequency)
+    .bind(time_zone)
+    .bind(next_run_at)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(report)
+}
+
+pub async fn update_report(
+    conn: impl Acquire<'_, Database = Postgres>,
+    report_id: Id,
+    UpdateReport {
+        recipients,
+        frequency,
+        time_zone,
+        next_run_at,
+        is_active,
+    }: UpdateReport,
+) -> sqlx::Result<Report> {
+    let mut tx = conn.begin().await?;
+
+    let report = sqlx::query_as(
+        r#"
+            UPDATE report
+            SET
+                recipients = $1,
+                frequency = $2,
+                time_zone = COALESCE($3, 'UTC'),
+                next_run_at = $4,
+                is_active = $5
+            WHERE report_id = $6
+            RETURNING
+                report_id,
+                dashboard_id,
+                recipients,
+                frequency,
+                time_zone,
+                next_run_at,
+                is_active,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(recipients)
+    .bind(frequency)
+    .bind(time_zone)
+    .bind(next_run_at)
+    .bind(is_active)
+    .bind(report_id)
+    .fetch_one(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(report)
+}
+
+pub async fn delete_report(
+    conn: impl Acquire<'_, Database = Postgres>,
+    report_id: Id,
+) -> sqlx::Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+            DELETE FROM report
+            WHERE report_id = $1
+        "#,
+    )
+    .bind(report_id)
+    .execute(tx.as_mut())
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn get_reports(
+    executor: impl PgExecutor<'_>,
+    dashboard_id: Id,
+) -> sqlx::Result<Vec<Report>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                report_id,
+                dashboard_id,
+                recipients,
+                frequency,
+                time_zone,
+                next_run_at,
+                is_active,
+                created_at,
+                updated_at
+            FROM report
+            WHERE dashboard_id = $1
+            ORDER BY report_id
+        "#,
+    )
+    .bind(dashboard_id)
+    .fetch_all(executor)
+    .await
+}
+
+/// Get the most recent runs of a report.
+pub async fn get_report_runs(
+    executor: impl PgExecutor<'_>,
+    report_id: Id,
+    limit: i64,
+) -> sqlx::Result<Vec<ReportRun>> {
+    sqlx::query_as(
+        r#"
+            SELECT
+                run_id,
+                report_id,
+                status,
+                attempts,
+                next_attempt_at,
+                last_error,
+                sent_at,
+                created_at,
+                updated_at
+            FROM report_run
+            WHERE report_id = $1
+            ORDER BY run_id DESC
+            LIMIT $2
+        "#,
+    )
+    .bind(report_id)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+}
+
+/// Add a pending run of a report, sent as soon as possible.
+pub async fn create_report_run(
+    executor: impl PgExecutor<'_>,
+    report_id: Id,
+) -> sqlx::Result<ReportRun> {
+    sqlx::query_as(
+        r#"
+            INSERT INTO report_run (report_id)
+            VALUES ($1)
+            RETURNING
+                run_id,
+                report_id,
+                status,
+                attempts,
+                next_attempt_at,
+                last_error,
+                sent_at,
+                created_at,
+                updated_at
+        "#,
+    )
+    .bind(report_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Add a pending run for every active report which is due,
+/// and move their next run to the first one in the future.
+///
+/// Runs missed while the server was stopped are sent only once.
+/// Returns the number of runs added.
+pub async fn schedule_report_runs(
+    conn: impl Acquire<'_, Database = Postgres>,
+) -> sqlx::Result<u64> {
+    let mut tx = conn.begin().await?;
+
+    let report_ids: Vec<Id> = sqlx::query_scalar(
+        r#"
+            UPDATE report
+            SET next_run_at = (
+                SELECT min(local_run_at) AT TIME ZONE time_zone
+                FROM generate_series(
+                    next_run_at AT TIME ZONE time_zone,
+                    (now() AT TIME ZONE time_zone) + frequency_interval(frequency),
+                    frequency_interval(frequency)
+                ) AS local_run_at
+                WHERE local_run_at AT TIME ZONE time_zone > now()
+            )
+            WHERE is_active
+                AND next_run_at <= now()
+                AND dashboard_id IN (
+                    SELECT dashboard_id
+                    FROM dashboard
+                    WHERE deleted_at IS NULL
+                )
+            RETURNING report_id
+        "#,
+    )
+    .fetch_all(tx.as_mut())
+    .await?;
+
+    let count = sqlx::query(
+        r#"
+            INSERT INTO report_run (report_id)
+            SELECT unnest($1::INT[])
+        "#,
+    )
+    .bind(report_ids)
+    .execute(tx.as_mut())
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+
+    Ok(count)
+}
+
+/// Claim the pending runs which are due, and postpone them by the lease
+/// so they are not claimed again while they are sent.
+///
+/// Runs locked by another transaction are skipped, and the claim is a single
+/// statement, so no lock is held while the reports are rendered and sent.
+/// A run whose result is never recorded is claimed again after the lease.
+pub async fn claim_pending_report_runs(
+    executor: impl PgExecutor<'_>,
+    limit: i64,
+    lease_secs: f64,
+) -> sqlx::Result<Vec<PendingReportRun>> {
+    sqlx::query_as(
+        r#"
+            UPDATE report_run AS rr
+            SET next_attempt_at = now() + make_interval(secs => $2)
+            FROM report AS r
+            JOIN dashboard AS d
+            ON r.dashboard_id = d.dashboard_id
+            WHERE rr.report_id = r.report_id
+                AND rr.run_id IN (
+                    SELECT run_id
+                    FROM report_run
+                    WHERE status = 'Pending'
+                        AND next_attempt_at <= now()
+                    ORDER BY next_attempt_at
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+            RETURNING
+                rr.run_id,
+                rr.attempts,
+                r.report_id,
+                r.dashboard_id,
+                d.user_id,
+                r.recipients
+        "#,
+    )
+    .bind(limit)
+    .bind(lease_secs)
+    .fetch_all(executor)
+    .await
+}
+
+/// Record the result of a report run attempt, unless the run was claimed again,
+/// and return whether it was recorded.
+///
+/// A run which is still pending is retried after `retry_delay_secs`.
+pub async fn set_report_run_result(
+    executor: impl PgExecutor<'_>,
+    run_id: Id,
+    attempts: i32,
+    status: ReportRunStatus,
+    last_error: Option<String>,
+    retry_delay_secs: f64,
+) -> sqlx::Result<bool> {
+    let result = sqlx::query(
+        r#"
+            UPDATE report_run
+            SET
+                status = $2,
+                attempts = attempts + 1,
+                last_error = $3,
+                next_attempt_at = now() + make_interval(secs => $4),
+                sent_at = CASE WHEN $2 = 'Sent' THEN now() END
+            WHERE run_id = $1
+                AND attempts = $5
+        "#,
+    )
+    .bind(run_id)
+    .bind(status)
+    .bind(last_error)
+    .bind(retry_delay_secs)
+    .bind(attempts)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn check_report_relation(
+    executor: impl PgExecutor<'_>,
+    dashboard_id: Id,
+    report_id: Id,
+) -> sqlx::Result<Relation> {
+    sqlx::query_scalar::<_, Id>(
+        r#"
+            SELECT dashboard_id
+            FROM report
+            WHERE report_id = $1
+        "#,
+    )
+    .bind(report_id)
+    .fetch_optional(executor)
+    .await
+    .map(|id| match id {
+        None => Relation::Absent,
+        Some(id) if id == dashboard_id => Relation::Owned,
+        Some(_) => Relation::NotOwned,
+    })
+}
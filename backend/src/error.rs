@@ -49,6 +49,10 @@ pub enum ApiError {
     #[error("request path not found")]
     Conflict,
 
+    /// Returns `413 Payload Too Large`
+    #[error("request body is too large")]
+    PayloadTooLarge,
+
     /// Returns `422 Unprocessable Entity`
     #[error("error in the request body")]
     UnprocessableEntity {
@@ -93,6 +97,7 @@ impl ApiError {
             Self::Forbidden => StatusCode::FORBIDDEN,
             Self::NotFound => StatusCode::NOT_FOUND,
             Self::Conflict => StatusCode::CONFLICT,
+            Self::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
             Self::UnprocessableEntity { .. } => StatusCode::UNPROCESSABLE_ENTITY,
             Self::Sqlx(_) | Self::Anyhow(_) => {
                 StatusCode::INTERNAL_SERVER_ERROR
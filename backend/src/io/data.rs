@@ -1,6 +1,8 @@
+use super::render::{axis_label, cell_label, get_rows, OTHER_LABEL};
 use crate::{
     model::{
         data::{CreateField, CreateTable, CreateTableData, Field, FieldKind, TableData},
+        viz::ChartData,
         Cell,
     },
     Id,
@@ -41,6 +43,7 @@ pub fn import_table_from_excel(spreadsheet: Spreadsheet) -> Vec<CreateTableData>
             fields.push(CreateField {
                 name,
                 field_kind: FieldKind::Text { is_required: false },
+                default_value: None,
             });
         }
 
@@ -161,6 +164,7 @@ where
         fields.push(CreateField {
             name,
             field_kind: FieldKind::Text { is_required: false },
+            default_value: None,
         });
     }
 
@@ -239,3 +243,29 @@ where
 
     Ok(())
 }
+
+/// Export the cells of a chart with a column for every axis,
+/// followed by the "Other" row if there is one.
+pub fn export_chart_to_csv<W>(
+    mut csv_writer: csv::Writer<W>,
+    chart_data: &ChartData,
+) -> csv::Result<()>
+where
+    W: io::Write,
+{
+    csv_writer.write_record(chart_data.axes.iter().map(axis_label))?;
+
+    for (row_index, row) in get_rows(chart_data).into_iter().enumerate() {
+        let is_other = chart_data.other.is_some() && row_index == chart_data.cells.len();
+        csv_writer.write_record(chart_data.axes.iter().enumerate().map(
+            |(i, axis_field)| match row.get(&axis_field.axis.axis_id) {
+                Some(Cell::Null) | None if is_other && i == 0 => OTHER_LABEL.to_string(),
+                cell => cell_label(cell, axis_field),
+            },
+        ))?;
+    }
+
+    csv_writer.flush()?;
+
+    Ok(())
+}
@@ -1,3 +1,5 @@
 mod data;
+mod render;
 
-pub use data::*;
\ No newline at end of file
+pub use data::*;
+pub use render::*;
@@ -0,0 +1,339 @@
+use crate::{
+    model::{
+        data::FieldKind,
+        viz::{Aggregate, AxisField, AxisKind, ChartData, ChartKind},
+        Cell,
+    },
+    Id,
+};
+use anyhow::{bail, Context};
+use num_traits::ToPrimitive;
+use plotters::prelude::*;
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{self, fontdb},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+};
+
+/// Chart kinds which can be rendered to an image.
+pub const RENDERED_CHART_KINDS: [ChartKind; 3] =
+    [ChartKind::Bar, ChartKind::Line, ChartKind::Table];
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 450;
+const FONT: &str = "sans-serif";
+const TITLE_SIZE: u32 = 20;
+const TEXT_SIZE: u32 = 14;
+
+const TABLE_ROW_HEIGHT: u32 = 24;
+const TABLE_MAX_ROWS: usize = 50;
+
+/// Label of the row grouping the rows after the top N rows.
+pub(super) const OTHER_LABEL: &str = "Other";
+
+/// Fonts used to draw the text of the SVG images in PNG images, loaded once.
+static FONTS: LazyLock<Arc<fontdb::Database>> = LazyLock::new(|| {
+    let mut fonts = fontdb::Database::new();
+    fonts.load_system_fonts();
+
+    // The default sans-serif family might not be installed, any font is better than no text
+    let query = fontdb::Query {
+        families: &[fontdb::Family::SansSerif],
+        ..Default::default()
+    };
+    if fonts.query(&query).is_none() {
+        let families = fonts
+            .faces()
+            .filter_map(|face| face.families.first())
+            .map(|(family, _)| family)
+            .collect::<Vec<_>>();
+        let family = families
+            .iter()
+            .find(|family| family.contains("Sans"))
+            .or(families.first())
+            .map(|family| family.to_string());
+        if let Some(family) = family {
+            fonts.set_sans_serif_family(family);
+        }
+    }
+
+    Arc::new(fonts)
+});
+
+/// Render the chart data to an SVG image.
+///
+/// Only the chart kinds in [RENDERED_CHART_KINDS] can be rendered.
+pub fn render_chart_svg(chart_data: &ChartData) -> anyhow::Result<String> {
+    let rows = get_rows(chart_data);
+    let height = match chart_data.chart.chart_kind {
+        // The table grows with its rows, up to the maximum
+        ChartKind::Table => TABLE_ROW_HEIGHT * (rows.len().min(TABLE_MAX_ROWS) as u32 + 3),
+        _ => HEIGHT,
+    };
+
+    let mut svg = String::new();
+    {
+        let root = SVGBackend::with_string(&mut svg, (WIDTH, height)).into_drawing_area();
+        root.fill(&WHITE)?;
+        let root = root.titled(&chart_data.chart.name, (FONT, TITLE_SIZE))?;
+
+        match chart_data.chart.chart_kind {
+            ChartKind::Bar | ChartKind::Line => draw_xy_chart(&root, chart_data, &rows)?,
+            ChartKind::Table => draw_table(&root, chart_data, &rows)?,
+            chart_kind => bail!("{chart_kind:?} charts can not be rendered"),
+        }
+
+        root.present()?;
+    }
+
+    Ok(svg)
+}
+
+/// Render the chart data to a PNG image, by rasterizing the SVG image.
+pub fn render_chart_png(chart_data: &ChartData) -> anyhow::Result<Vec<u8>> {
+    let svg = render_chart_svg(chart_data)?;
+
+    let options = usvg::Options {
+        fontdb: FONTS.clone(),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_str(&svg, &options)?;
+
+    let size = tree.size().to_int_size();
+    let mut pixmap =
+        Pixmap::new(size.width(), size.height()).context("PNG image should not be empty")?;
+    resvg::render(&tree, Transform::default(), &mut pixmap.as_mut());
+
+    Ok(pixmap.encode_png()?)
+}
+
+/// Get the rows of cells of the chart, followed by the "Other" row if there is one.
+pub(super) fn get_rows(chart_data: &ChartData) -> Vec<&HashMap<Id, Cell>> {
+    chart_data
+        .cells
+        .iter()
+        .chain(chart_data.other.as_ref())
+        .collect()
+}
+
+/// Draw a bar or line chart of the Y axes, with the X axis as categories.
+fn draw_xy_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, plotters::coord::Shift>,
+    chart_data: &ChartData,
+    rows: &[&HashMap<Id, Cell>],
+) -> anyhow::Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    let x_axis = chart_data
+        .axes
+        .iter()
+        .find(|axis_field| axis_field.axis.axis_kind == AxisKind::X);
+    let y_axes = chart_data
+        .axes
+        .iter()
+        .filter(|axis_field| axis_field.axis.axis_kind == AxisKind::Y)
+        .collect::<Vec<_>>();
+
+    let labels = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| match x_axis {
+            _ if chart_data.other.is_some() && i == chart_data.cells.len() => {
+                OTHER_LABEL.to_string()
+            }
+            Some(x_axis) => cell_label(row.get(&x_axis.axis.axis_id), x_axis),
+            None => String::new(),
+        })
+        .collect::<Vec<_>>();
+
+    let series = y_axes
+        .iter()
+        .map(|y_axis| {
+            rows.iter()
+                .map(|row| cell_number(row.get(&y_axis.axis.axis_id)))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let values = series.iter().flatten().flatten().copied();
+    let y_min = values.clone().fold(0.0, f64::min);
+    let y_max = values.fold(0.0, f64::max);
+    let y_max = if y_max > y_min {
+        y_max + (y_max - y_min) * 0.05
+    } else {
+        y_min + 1.0
+    };
+
+    // Each category is a segment, labeled at its center. The segmented range is inclusive.
+    let mut chart = ChartBuilder::on(root)
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d((0..labels.len().max(1) - 1).into_segmented(), y_min..y_max)?;
+
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_labels(labels.len().max(1))
+        .x_label_formatter(&|x| match x {
+            SegmentValue::CenterOf(i) => labels.get(*i).cloned().unwrap_or_default(),
+            _ => String::new(),
+        })
+        .x_desc(x_axis.map_or("", |x_axis| x_axis.field_name.as_str()))
+        .label_style((FONT, TEXT_SIZE))
+        .draw()?;
+
+    // The bars of the Y axes are side by side in 80% of the segment, in pixels
+    let segment_width = chart.plotting_area().dim_in_pixel().0 as f64 / labels.len().max(1) as f64;
+    let bar_width = segment_width * 0.8 / series.len().max(1) as f64;
+
+    for (i, (y_axis, values)) in y_axes.iter().zip(&series).enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        let points = values
+            .iter()
+            .enumerate()
+            .filter_map(|(x, y)| y.map(|y| (x, y)));
+
+        let annotation = match chart_data.chart.chart_kind {
+            ChartKind::Bar => {
+                let left = segment_width * 0.1 + bar_width * i as f64;
+                let right = segment_width - left - bar_width;
+                chart.draw_series(points.map(|(x, y)| {
+                    let mut bar = Rectangle::new(
+                        [
+                            (SegmentValue::Exact(x), 0.0),
+                            (SegmentValue::Exact(x + 1), y),
+                        ],
+                        color.filled(),
+                    );
+                    bar.set_margin(0, 0, left as u32, right as u32);
+                    bar
+                }))?
+            }
+            _ => chart.draw_series(LineSeries::new(
+                points.map(|(x, y)| (SegmentValue::CenterOf(x), y)),
+                color.stroke_width(2),
+            ))?,
+        };
+        annotation
+            .label(axis_label(y_axis))
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 10, y + 5)], color.filled()));
+    }
+
+    if y_axes.len() > 1 {
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .label_font((FONT, TEXT_SIZE))
+            .draw()?;
+    }
+
+    Ok(())
+}
+
+/// Draw the axes as the columns of a table, truncated to [TABLE_MAX_ROWS] rows.
+fn draw_table<DB: DrawingBackend>(
+    root: &DrawingArea<DB, plotters::coord::Shift>,
+    chart_data: &ChartData,
+    rows: &[&HashMap<Id, Cell>],
+) -> anyhow::Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    let column_width = WIDTH as i32 / chart_data.axes.len().max(1) as i32;
+    let row_height = TABLE_ROW_HEIGHT as i32;
+    let text_style = TextStyle::from((FONT, TEXT_SIZE).into_font());
+    let header_style = TextStyle::from((FONT, TEXT_SIZE).into_font().style(FontStyle::Bold));
+
+    for (i, axis_field) in chart_data.axes.iter().enumerate() {
+        root.draw_text(
+            &axis_label(axis_field),
+            &header_style,
+            (i as i32 * column_width + 8, 4),
+        )?;
+    }
+    root.draw(&PathElement::new(
+        [(0, row_height), (WIDTH as i32, row_height)],
+        BLACK,
+    ))?;
+
+    for (row_index, row) in rows.iter().take(TABLE_MAX_ROWS).enumerate() {
+        let y = (row_index as i32 + 1) * row_height + 4;
+        let is_other = chart_data.other.is_some() && row_index == chart_data.cells.len();
+        for (i, axis_field) in chart_data.axes.iter().enumerate() {
+            let label = match row.get(&axis_field.axis.axis_id) {
+                Some(Cell::Null) | None if is_other && i == 0 => OTHER_LABEL.to_string(),
+                cell => cell_label(cell, axis_field),
+            };
+            root.draw_text(&label, &text_style, (i as i32 * column_width + 8, y))?;
+        }
+    }
+
+    if rows.len() > TABLE_MAX_ROWS {
+        let y = (TABLE_MAX_ROWS as i32 + 1) * row_height + 4;
+        root.draw_text(
+            &format!("{} more rows", rows.len() - TABLE_MAX_ROWS),
+            &text_style.color(&RGBColor(128, 128, 128)),
+            (8, y),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Get the name of an axis, with its aggregate.
+pub(super) fn axis_label(axis_field: &AxisField) -> String {
+    let Some(aggregate) = axis_field.axis.aggregate.as_deref() else {
+        return axis_field.field_name.clone();
+    };
+    let aggregate_name = match aggregate {
+        Aggregate::Sum => "Sum".to_string(),
+        Aggregate::Average => "Average".to_string(),
+        Aggregate::Min => "Min".to_string(),
+        Aggregate::Max => "Max".to_string(),
+        Aggregate::Count => "Count".to_string(),
+        Aggregate::CountDistinct => "Distinct count".to_string(),
+        Aggregate::Median => "Median".to_string(),
+        Aggregate::Percentile(fraction) => format!("P{}", fraction * 100.0),
+        Aggregate::StdDev => "Standard deviation".to_string(),
+        Aggregate::Variance => "Variance".to_string(),
+        Aggregate::First { .. } => "First".to_string(),
+        Aggregate::Last { .. } => "Last".to_string(),
+        Aggregate::BoolAnd => "All".to_string(),
+        Aggregate::BoolOr => "Any".to_string(),
+    };
+    format!("{aggregate_name} of {}", axis_field.field_name)
+}
+
+/// Get the text of a cell, using the labels of the enumeration values.
+pub(super) fn cell_label(cell: Option<&Cell>, axis_field: &AxisField) -> String {
+    match (cell, &axis_field.field_kind.0) {
+        (Some(Cell::Integer(value)), FieldKind::Enumeration { values, .. })
+            if axis_field.axis.aggregate.is_none() =>
+        {
+            values.get(value).cloned().unwrap_or_default()
+        }
+        (Some(Cell::Integer(value)), _) => value.to_string(),
+        (Some(Cell::Float(value)), _) => value.to_string(),
+        (Some(Cell::Decimal(value)), _) => value.to_string(),
+        (Some(Cell::Boolean(value)), _) => value.to_string(),
+        (Some(Cell::DateTime(value)), _) => value.format("%Y-%m-%d %H:%M").to_string(),
+        (Some(Cell::String(value)), _) => value.clone(),
+        (Some(Cell::Null) | None, _) => String::new(),
+    }
+}
+
+fn cell_number(cell: Option<&Cell>) -> Option<f64> {
+    match cell? {
+        Cell::Integer(value) => Some(*value as f64),
+        Cell::Float(value) => Some(*value),
+        Cell::Decimal(value) => value.to_f64(),
+        Cell::Boolean(_) | Cell::DateTime(_) | Cell::String(_) | Cell::Null => None,
+    }
+}
@@ -1,9 +1,11 @@
+pub mod cache;
 pub mod config;
 pub mod db;
 pub mod error;
 pub mod io;
 pub mod model;
 pub mod routes;
+pub mod tasks;
 
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
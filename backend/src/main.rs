@@ -1,14 +1,19 @@
 use std::sync::Arc;
 
 use chronicle::{
-    config::Config,
+    cache::ChartCache,
+    config::{Config, Limits},
     routes::{self, ApiState},
 };
 use shuttle_runtime::SecretStore;
 use sqlx::migrate::Migrator;
+use tokio::sync::broadcast;
 
 static MIGRATOR: Migrator = sqlx::migrate!();
 
+/// Number of change events buffered for slow subscribers.
+const EVENT_CAPACITY: usize = 1024;
+
 #[shuttle_runtime::main]
 async fn main(
     #[shuttle_shared_db::Postgres(local_uri = "{secrets.DATABASE_URL}")] database_url: String,
@@ -24,6 +29,9 @@ async fn main(
                 database_url: String::new(),
             }),
             pool,
+            events: broadcast::Sender::new(EVENT_CAPACITY),
+            chart_cache: ChartCache::default(),
+            limits: Limits::from_secrets(&secrets)?,
         },
         secrets,
     )
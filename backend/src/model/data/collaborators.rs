@@ -0,0 +1,19 @@
+use crate::Id;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// User with whom a table is shared response.
+#[derive(Debug, Serialize, FromRow)]
+pub struct Collaborator {
+    pub table_id: Id,
+    pub user_id: Id,
+    pub username: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Share a table request.
+#[derive(Debug, Deserialize)]
+pub struct CreateCollaborator {
+    pub username: String,
+}
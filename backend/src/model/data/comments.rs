@@ -0,0 +1,30 @@
+use crate::Id;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Comment on an entry response.
+#[derive(Debug, Serialize, FromRow)]
+pub struct Comment {
+    pub comment_id: Id,
+    pub table_id: Id,
+    pub entry_id: Id,
+    /// Author of the comment, if the user still exists.
+    pub user_id: Option<Id>,
+    pub username: Option<String>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Create comment request.
+#[derive(Debug, Deserialize)]
+pub struct CreateComment {
+    pub body: String,
+}
+
+/// Update comment request.
+#[derive(Debug, Deserialize)]
+pub struct UpdateComment {
+    pub body: String,
+}
@@ -1,7 +1,8 @@
-use crate::Id;
+use crate::{model::Cell, Id};
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use serde_with::{serde_as, DisplayFromStr};
 use sqlx::{types::Json, FromRow};
 use std::{collections::HashMap, fmt};
@@ -14,13 +15,15 @@ pub struct Field {
     pub name: String,
     pub ordering: i32,
     pub field_kind: Json<FieldKind>,
+    /// Value of the field in the created entries when it is not given.
+    pub default_value: Option<Json<Value>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
 /// The field kind and associated options.
 #[serde_as]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "type")]
 pub enum FieldKind {
     Text {
@@ -62,8 +65,17 @@ pub enum FieldKind {
         #[serde_as(as = "HashMap<DisplayFromStr, _>")]
         // This is necessary because of a bug with serde
         values: HashMap<i64, String>,
-        default_value: i64,
     },
+    /// Number of the entry in the order of creation, set by the database.
+    RowNumber,
+    /// Creation time of the entry.
+    CreatedAt,
+    /// Last update time of the entry.
+    UpdatedAt,
+    /// User who created the entry.
+    CreatedBy,
+    /// User who last created or updated the entry.
+    UpdatedBy,
 }
 
 impl FieldKind {
@@ -74,11 +86,94 @@ impl FieldKind {
             FieldKind::Integer { .. } => "BIGINT",
             FieldKind::Float { .. } => "DOUBLE PRECISION",
             FieldKind::Money { .. } => "numeric_money",
-            FieldKind::Progress { .. } => "BIGINT NOT NULL DEFAULT 0",
+            FieldKind::Progress { .. } => "BIGINT",
             FieldKind::DateTime { .. } => "TIMESTAMPTZ",
             FieldKind::WebLink { .. } => "TEXT COLLATE case_insensitive",
-            FieldKind::Checkbox => "BOOLEAN NOT NULL DEFAULT FALSE",
+            FieldKind::Checkbox => "BOOLEAN",
             FieldKind::Enumeration { .. } => "BIGINT",
+            FieldKind::RowNumber => "BIGINT",
+            FieldKind::CreatedAt | FieldKind::UpdatedAt => "TIMESTAMPTZ",
+            FieldKind::CreatedBy | FieldKind::UpdatedBy => "BIGINT",
+        }
+    }
+
+    /// Check if the values of the field are numbers.
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            FieldKind::Integer { .. }
+                | FieldKind::Float { .. }
+                | FieldKind::Money { .. }
+                | FieldKind::Progress { .. }
+        )
+    }
+
+    /// Check if the values of the field are in the search document of the table.
+    pub fn is_searchable(&self) -> bool {
+        matches!(
+            self,
+            FieldKind::Text { .. } | FieldKind::WebLink { .. } | FieldKind::Enumeration { .. }
+        )
+    }
+
+    /// Check if the cells of the field are populated automatically, they cannot be set by users.
+    pub fn is_auto(&self) -> bool {
+        matches!(
+            self,
+            FieldKind::RowNumber
+                | FieldKind::CreatedAt
+                | FieldKind::UpdatedAt
+                | FieldKind::CreatedBy
+                | FieldKind::UpdatedBy
+        )
+    }
+
+    /// Check if the column of the field is generated by the database, it is never written to.
+    pub fn is_generated(&self) -> bool {
+        matches!(
+            self,
+            FieldKind::RowNumber | FieldKind::CreatedAt | FieldKind::UpdatedAt
+        )
+    }
+
+    /// Check if the values of the field are date times.
+    pub fn is_date_time(&self) -> bool {
+        matches!(
+            self,
+            FieldKind::DateTime { .. } | FieldKind::CreatedAt | FieldKind::UpdatedAt
+        )
+    }
+
+    /// Map the field kind to the PostgreSQL column definition, with the constraints of the column.
+    /// The default is the SQL literal of the default value.
+    pub fn get_sql_column_definition(&self, default: Option<&str>) -> String {
+        let sql_type = self.get_sql_type();
+        let default = self
+            .get_sql_default(default)
+            .map_or_else(String::new, |default| format!(" DEFAULT {default}"));
+        match self {
+            FieldKind::Progress { .. } | FieldKind::Checkbox => {
+                format!("{sql_type} NOT NULL{default}")
+            }
+            FieldKind::RowNumber => format!("{sql_type} GENERATED ALWAYS AS IDENTITY"),
+            FieldKind::CreatedAt => format!("{sql_type} GENERATED ALWAYS AS (created_at) STORED"),
+            FieldKind::UpdatedAt => format!("{sql_type} GENERATED ALWAYS AS (updated_at) STORED"),
+            FieldKind::CreatedBy | FieldKind::UpdatedBy => {
+                format!("{sql_type} REFERENCES app_user (user_id) ON DELETE SET NULL")
+            }
+            _ => format!("{sql_type}{default}"),
+        }
+    }
+
+    /// Map the default of the field to a PostgreSQL column default.
+    /// The default is the SQL literal of the default value, which is coerced to the column type.
+    /// The columns which are not null have a default without it.
+    pub fn get_sql_default(&self, default: Option<&str>) -> Option<String> {
+        match (default, self) {
+            (Some(default), _) => Some(default.to_string()),
+            (None, FieldKind::Progress { .. }) => Some("0".to_string()),
+            (None, FieldKind::Checkbox) => Some("FALSE".to_string()),
+            (None, _) => None,
         }
     }
 }
@@ -88,6 +183,8 @@ impl FieldKind {
 pub struct CreateField {
     pub name: String,
     pub field_kind: FieldKind,
+    #[serde(default)]
+    pub default_value: Option<Value>,
 }
 
 /// Update field request.
@@ -95,6 +192,72 @@ pub struct CreateField {
 pub struct UpdateField {
     pub name: String,
     pub field_kind: FieldKind,
+    #[serde(default)]
+    pub default_value: Option<Value>,
+    /// What to do with the cells which cannot be converted to another field kind.
+    #[serde(default)]
+    pub on_failure: ConversionFailureMode,
+    /// Delete the backup field with the original cells after converting the field kind.
+    #[serde(default)]
+    pub drop_backup: bool,
+    /// What to do with the chart axes on the field when converting the field kind.
+    #[serde(default)]
+    pub axes: DependentAxesMode,
+}
+
+/// Delete field request query parameters.
+#[derive(Debug, Deserialize)]
+pub struct DeleteField {
+    /// What to do with the chart axes on the field.
+    #[serde(default)]
+    pub axes: DependentAxesMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+pub enum DependentAxesMode {
+    /// Keep the axes on the backup field after a conversion,
+    /// or on the deleted field until it is purged from the trash.
+    #[default]
+    Keep,
+    /// Move the axes to the converted field, with the dashboard filters on the field.
+    Repoint,
+    /// Remove the axes from their charts.
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+pub enum ConversionFailureMode {
+    /// Cancel the update if any cell cannot be converted.
+    Abort,
+    /// Set the cells which cannot be converted to null.
+    #[default]
+    SetNull,
+}
+
+/// Field kind conversion dry-run request.
+#[derive(Debug, Deserialize)]
+pub struct PreviewFieldConversion {
+    pub field_kind: FieldKind,
+}
+
+/// Outcome of converting the cells of a field to another field kind.
+#[derive(Debug, Serialize)]
+pub struct FieldConversionReport {
+    pub total: usize,
+    /// Non-null cells which are converted.
+    pub converted: usize,
+    /// Null cells, which stay null.
+    pub empty: usize,
+    /// Non-null cells which cannot be converted and become null.
+    pub failed: usize,
+    /// Sample of the cells which cannot be converted.
+    pub failures: Vec<ConversionFailure>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversionFailure {
+    pub entry_id: Id,
+    pub cell: Cell,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1,7 +1,13 @@
 //! Models for the Data Management feature.
 
+mod collaborators;
+mod comments;
 mod entries;
 mod fields;
+mod schema;
+mod search;
 mod tables;
+mod templates;
+mod webhooks;
 
-pub use {entries::*, fields::*, tables::*};
+pub use {collaborators::*, comments::*, entries::*, fields::*, schema::*, search::*, tables::*, templates::*, webhooks::*};
@@ -0,0 +1,108 @@
+use super::{
+    CreateField, DependentAxesMode, Field, FieldKind, SetFieldOrder, UpdateField, UpdateTable,
+};
+use crate::Id;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow};
+use std::collections::HashMap;
+
+/// Logged structural change of a table.
+#[derive(Debug, Serialize, FromRow)]
+pub struct SchemaChangeEntry {
+    pub schema_change_id: Id,
+    pub table_id: Id,
+    /// User who made the change, if the user still exists.
+    pub user_id: Option<Id>,
+    pub change: Json<SchemaChange>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SchemaChange {
+    FieldCreated {
+        field_id: Id,
+        name: String,
+        field_kind: FieldKind,
+    },
+    FieldRenamed {
+        field_id: Id,
+        old_name: String,
+        new_name: String,
+    },
+    /// A conversion moves the field to a new field ID,
+    /// the previous field keeps the original cells unless its backup is dropped.
+    FieldKindChanged {
+        field_id: Id,
+        previous_field_id: Id,
+        old_field_kind: FieldKind,
+        new_field_kind: FieldKind,
+    },
+    FieldsReordered {
+        order: HashMap<Id, i32>,
+    },
+    /// The field is moved to the trash.
+    FieldDeleted {
+        field_id: Id,
+        name: String,
+    },
+    FieldRestored {
+        field_id: Id,
+        name: String,
+    },
+    /// The field and its cells are permanently deleted.
+    FieldPurged {
+        field_id: Id,
+        name: String,
+    },
+    TableRenamed {
+        old_name: String,
+        new_name: String,
+    },
+    /// The table is moved to the trash, with its descendants.
+    TableDeleted,
+    TableRestored,
+}
+
+impl SchemaChange {
+    /// Change of a field created in the table.
+    pub fn field_created(field: &Field) -> Self {
+        Self::FieldCreated {
+            field_id: field.field_id,
+            name: field.name.clone(),
+            field_kind: field.field_kind.0.clone(),
+        }
+    }
+}
+
+/// Changes applied to a table in one transaction, in order.
+/// The table is left unchanged if any of them fails.
+#[derive(Debug, Deserialize)]
+pub struct SchemaMigration(pub Vec<SchemaOperation>);
+
+#[derive(Debug, Deserialize)]
+pub enum SchemaOperation {
+    /// The temporary ID, negative and unique in the migration, refers to the created field
+    /// in the next operations of the migration.
+    CreateField {
+        #[serde(default)]
+        temporary_id: Option<Id>,
+        #[serde(flatten)]
+        field: CreateField,
+    },
+    /// The field ID can be a temporary ID. After a conversion the temporary ID refers
+    /// to the converted field in the next operations of the migration.
+    UpdateField {
+        field_id: Id,
+        field: UpdateField,
+    },
+    /// The field ID can be a temporary ID.
+    DeleteField {
+        field_id: Id,
+        #[serde(default)]
+        axes: DependentAxesMode,
+    },
+    /// The field IDs can be temporary IDs.
+    SetFieldOrder(SetFieldOrder),
+    UpdateTable(UpdateTable),
+}
@@ -0,0 +1,28 @@
+use crate::Id;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Search query parameters.
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    /// Web search syntax: quoted phrases, `or` and `-` to exclude words.
+    pub query: String,
+    /// Maximum amount of entries per table.
+    pub limit: Option<i64>,
+}
+
+/// Matching entries of a table, ordered by rank.
+#[derive(Debug, Serialize)]
+pub struct TableSearchResult {
+    pub table_id: Id,
+    pub name: String,
+    pub entries: Vec<EntrySearchResult>,
+}
+
+/// Matching entry with the matched words wrapped in `<b>` tags.
+#[derive(Debug, Serialize, FromRow)]
+pub struct EntrySearchResult {
+    pub entry_id: Id,
+    pub rank: f32,
+    pub headline: String,
+}
@@ -33,6 +33,16 @@ pub struct UpdateTable {
     pub description: String,
 }
 
+/// Copy table request.
+#[derive(Debug, Deserialize)]
+pub struct CopyTable {
+    /// Name of the copy, the name of the table by default.
+    pub name: Option<String>,
+    /// Copy the entries of the table and its children.
+    #[serde(default)]
+    pub include_entries: bool,
+}
+
 /// Response for fetching entire table data.
 #[derive(Debug, Serialize)]
 pub struct TableData {
@@ -0,0 +1,151 @@
+use super::{FieldKind, Table};
+use crate::{
+    model::viz::{ChartKind, CreateAxis, Dashboard},
+    Id,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{types::Json, FromRow};
+
+/// Table template response.
+#[derive(Debug, Serialize, FromRow)]
+pub struct TableTemplate {
+    pub template_id: Id,
+    pub user_id: Id,
+    pub name: String,
+    pub description: String,
+    pub content: Json<TemplateTable>,
+    pub is_published: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Structure of a table saved in a template.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateTable {
+    pub name: String,
+    pub description: String,
+    pub fields: Vec<TemplateField>,
+    pub children: Vec<TemplateTable>,
+    pub charts: Vec<TemplateChart>,
+}
+
+impl TemplateTable {
+    /// Number of tables created from the template, which are the table and its descendants.
+    pub fn table_count(&self) -> i64 {
+        1 + self.children.iter().map(TemplateTable::table_count).sum::<i64>()
+    }
+
+    /// Largest number of fields of a table created from the template.
+    pub fn max_field_count(&self) -> i64 {
+        self.children
+            .iter()
+            .map(TemplateTable::max_field_count)
+            .fold(self.fields.len() as i64, i64::max)
+    }
+}
+
+/// Field saved in a template.
+///
+/// The field ID is the ID of the field the template was created from,
+/// it is only used to reference the field in the axes of the template.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateField {
+    pub field_id: Id,
+    pub name: String,
+    pub field_kind: FieldKind,
+    #[serde(default)]
+    pub default_value: Option<Value>,
+}
+
+/// Chart on a table saved in a template, with axes referencing the template fields.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateChart {
+    pub name: String,
+    pub chart_kind: ChartKind,
+    pub row_limit: Option<i32>,
+    pub top_n: Option<i32>,
+    pub axes: Vec<CreateAxis>,
+}
+
+/// Create table template request.
+#[derive(Debug, Deserialize)]
+pub struct CreateTableTemplate {
+    pub name: String,
+    pub description: String,
+    /// Save the charts on the table and its children whose axes are in the template.
+    #[serde(default)]
+    pub include_charts: bool,
+}
+
+/// Update table template request.
+#[derive(Debug, Deserialize)]
+pub struct UpdateTableTemplate {
+    pub name: String,
+    pub description: String,
+}
+
+/// Publish table template request.
+#[derive(Debug, Deserialize)]
+pub struct PublishTableTemplate {
+    pub is_published: bool,
+}
+
+/// Instantiate table template request.
+#[derive(Debug, Deserialize)]
+pub struct InstantiateTableTemplate {
+    /// Name of the created table, the name of the template table by default.
+    pub name: Option<String>,
+}
+
+/// Tables created from a template, with the dashboard of the charts of the template if it has any.
+#[derive(Debug, Serialize)]
+pub struct TemplateInstance {
+    pub table: Table,
+    pub dashboard: Option<Dashboard>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template_table(fields: usize, children: Vec<TemplateTable>) -> TemplateTable {
+        TemplateTable {
+            name: String::new(),
+            description: String::new(),
+            fields: (0..fields)
+                .map(|i| TemplateField {
+                    field_id: i as Id,
+                    name: i.to_string(),
+                    field_kind: FieldKind::Text { is_required: false },
+                    default_value: None,
+                })
+                .collect(),
+            children,
+            charts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn single_table() {
+        let table = template_table(3, Vec::new());
+
+        assert_eq!(table.table_count(), 1);
+        assert_eq!(table.max_field_count(), 3);
+    }
+
+    #[test]
+    fn nested_tables() {
+        let table = template_table(
+            2,
+            vec![
+                template_table(1, vec![template_table(5, Vec::new())]),
+                template_table(0, Vec::new()),
+            ],
+        );
+
+        assert_eq!(table.table_count(), 4);
+        assert_eq!(table.max_field_count(), 5);
+    }
+}
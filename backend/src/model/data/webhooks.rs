@@ -0,0 +1,123 @@
+use crate::Id;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{types::Json, FromRow};
+
+use super::{Entry, Field};
+
+/// Table webhook response.
+///
+/// The secret is only returned when it is generated, see [WebhookSecret].
+#[derive(Debug, Serialize, FromRow)]
+pub struct Webhook {
+    pub webhook_id: Id,
+    pub table_id: Id,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Webhook response with the secret signing the payloads,
+/// returned once when the webhook is created or its secret is rotated.
+#[derive(Debug, Serialize)]
+pub struct WebhookSecret {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+/// Table events a webhook can subscribe to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "webhook_event")]
+pub enum WebhookEvent {
+    EntryCreated,
+    EntryUpdated,
+    EntryDeleted,
+    FieldChanged,
+}
+
+/// Create webhook request.
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhook {
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+}
+
+/// Update webhook request.
+#[derive(Debug, Deserialize)]
+pub struct UpdateWebhook {
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub is_active: bool,
+}
+
+/// Status of a webhook delivery.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "delivery_status")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+/// Webhook delivery log response.
+#[derive(Debug, Serialize, FromRow)]
+pub struct WebhookDelivery {
+    pub delivery_id: Id,
+    pub webhook_id: Id,
+    pub event: WebhookEvent,
+    pub payload: Json<Value>,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A pending delivery with the webhook destination.
+#[derive(Debug, FromRow)]
+pub struct PendingDelivery {
+    pub delivery_id: Id,
+    pub event: WebhookEvent,
+    pub payload: Json<Value>,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
+
+/// JSON body sent to a webhook.
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload<'a> {
+    pub event: WebhookEvent,
+    pub table_id: Id,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<&'a Entry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<&'a Field>,
+}
+
+impl<'a> WebhookPayload<'a> {
+    pub fn entry(event: WebhookEvent, table_id: Id, entry: &'a Entry) -> Self {
+        Self {
+            event,
+            table_id,
+            entry: Some(entry),
+            field: None,
+        }
+    }
+
+    pub fn field(field: &'a Field) -> Self {
+        Self {
+            event: WebhookEvent::FieldChanged,
+            table_id: field.table_id,
+            entry: None,
+            field: Some(field),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+use super::notifications::NotificationKind;
+use crate::Id;
+
+/// Change event published by the database on every modification
+/// of entries, fields, tables, dashboards, charts and filters, and on every notification.
+///
+/// Events are serialized with a `"type"` tag, which is also the format
+/// of the payload sent by the database triggers.
+///
+/// A statement changing a single entry publishes the event of the entry,
+/// a statement changing more entries publishes [Event::EntriesChanged].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Event {
+    EntryCreated {
+        table_id: Id,
+        entry_id: Id,
+    },
+    EntryUpdated {
+        table_id: Id,
+        entry_id: Id,
+    },
+    EntryDeleted {
+        table_id: Id,
+        entry_id: Id,
+    },
+    EntriesChanged {
+        table_id: Id,
+    },
+    FieldCreated {
+        table_id: Id,
+        field_id: Id,
+    },
+    FieldUpdated {
+        table_id: Id,
+        field_id: Id,
+    },
+    FieldDeleted {
+        table_id: Id,
+        field_id: Id,
+    },
+    TableUpdated {
+        table_id: Id,
+    },
+    TableDeleted {
+        table_id: Id,
+    },
+    DashboardUpdated {
+        dashboard_id: Id,
+    },
+    DashboardDeleted {
+        dashboard_id: Id,
+    },
+    ChartCreated {
+        dashboard_id: Id,
+        chart_id: Id,
+        table_id: Id,
+    },
+    ChartUpdated {
+        dashboard_id: Id,
+        chart_id: Id,
+        table_id: Id,
+    },
+    ChartDeleted {
+        dashboard_id: Id,
+        chart_id: Id,
+        table_id: Id,
+    },
+    FilterCreated {
+        dashboard_id: Id,
+        filter_id: Id,
+    },
+    FilterUpdated {
+        dashboard_id: Id,
+        filter_id: Id,
+    },
+    FilterDeleted {
+        dashboard_id: Id,
+        filter_id: Id,
+    },
+    NotificationCreated {
+        user_id: Id,
+        notification_id: Id,
+        notification: NotificationKind,
+    },
+    /// Sent by the server when events may have been missed,
+    /// after the listener (re)connected to the database.
+    Resync,
+}
+
+impl Event {
+    /// The table on which the entry, field, or table event occured.
+    pub fn table_id(&self) -> Option<Id> {
+        match self {
+            Event::EntryCreated { table_id, .. }
+            | Event::EntryUpdated { table_id, .. }
+            | Event::EntryDeleted { table_id, .. }
+            | Event::EntriesChanged { table_id }
+            | Event::FieldCreated { table_id, .. }
+            | Event::FieldUpdated { table_id, .. }
+            | Event::FieldDeleted { table_id, .. }
+            | Event::TableUpdated { table_id }
+            | Event::TableDeleted { table_id } => Some(*table_id),
+            Event::DashboardUpdated { .. }
+            | Event::DashboardDeleted { .. }
+            | Event::ChartCreated { .. }
+            | Event::ChartUpdated { .. }
+            | Event::ChartDeleted { .. }
+            | Event::FilterCreated { .. }
+            | Event::FilterUpdated { .. }
+            | Event::FilterDeleted { .. }
+            | Event::NotificationCreated { .. }
+            | Event::Resync => None,
+        }
+    }
+
+    /// Whether the event can remove the access of users to its table or dashboard,
+    /// which is checked again by the event streams.
+    pub fn may_revoke_access(&self) -> bool {
+        matches!(
+            self,
+            Event::TableUpdated { .. }
+                | Event::TableDeleted { .. }
+                | Event::DashboardUpdated { .. }
+                | Event::DashboardDeleted { .. }
+        )
+    }
+}
@@ -0,0 +1,81 @@
+use crate::Id;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow};
+
+/// Background job response.
+#[derive(Debug, Serialize, FromRow)]
+pub struct Job {
+    pub job_id: Id,
+    pub user_id: Id,
+    pub job: Json<JobKind>,
+    pub status: JobStatus,
+    /// Fraction of the work done, from 0 to 1.
+    pub progress: f64,
+    pub attempts: i32,
+    pub output: Option<Json<JobOutput>>,
+    pub error: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Job {
+    /// Whether the job is still pending or running.
+    pub fn is_active(&self) -> bool {
+        matches!(self.status, JobStatus::Pending | JobStatus::Running)
+    }
+}
+
+/// Kind of a background job with its details.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum JobKind {
+    /// Import the sheets of the input Excel file as tables.
+    ImportExcel,
+    /// Import the input CSV file as a table.
+    ImportCsv { name: String },
+    /// Export a table to an Excel file, added to the input Excel file if there is one.
+    ExportExcel { table_id: Id },
+    /// Export a table to a CSV file.
+    ExportCsv { table_id: Id },
+    /// Refresh the materialized view of a chart.
+    RefreshChart { dashboard_id: Id, chart_id: Id },
+}
+
+/// Status of a background job.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "job_status")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Output of a completed job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum JobOutput {
+    Imported {
+        table_ids: Vec<Id>,
+    },
+    /// The exported file is the result of the job.
+    Exported {
+        file_name: String,
+        content_type: String,
+    },
+}
+
+/// A job claimed by a worker, with its input file.
+#[derive(Debug, FromRow)]
+pub struct ClaimedJob {
+    pub job_id: Id,
+    pub user_id: Id,
+    pub job: Json<JobKind>,
+    /// Number of the attempt, the job is not updated once it is claimed again.
+    pub attempts: i32,
+    pub input: Option<Vec<u8>>,
+}
@@ -10,6 +10,11 @@
 //! - FromRow: Convert from an SQL query.
 
 pub mod data;
+pub mod events;
+pub mod jobs;
+pub mod notifications;
+pub mod trash;
+pub mod usage;
 pub mod users;
 pub mod viz;
 
@@ -28,7 +33,7 @@ use std::str::FromStr;
 use viz::Aggregate;
 
 /// This represents all the data types in user entries and charts.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Cell {
     Integer(i64),
     Float(f64),
@@ -122,10 +127,15 @@ impl Cell {
             FieldKind::Text { .. } | FieldKind::WebLink { .. } => Cell::String(row.try_get(index)?),
             FieldKind::Integer { .. }
             | FieldKind::Progress { .. }
-            | FieldKind::Enumeration { .. } => Cell::Integer(row.try_get(index)?),
+            | FieldKind::Enumeration { .. }
+            | FieldKind::RowNumber
+            | FieldKind::CreatedBy
+            | FieldKind::UpdatedBy => Cell::Integer(row.try_get(index)?),
             FieldKind::Float { .. } => Cell::Float(row.try_get(index)?),
             FieldKind::Money { .. } => Cell::Decimal(row.try_get(index)?),
-            FieldKind::DateTime { .. } => Cell::DateTime(row.try_get(index)?),
+            FieldKind::DateTime { .. } | FieldKind::CreatedAt | FieldKind::UpdatedAt => {
+                Cell::DateTime(row.try_get(index)?)
+            }
             FieldKind::Checkbox => Cell::Boolean(row.try_get(index)?),
         })
     }
@@ -141,12 +151,18 @@ impl Cell {
             return Ok(Cell::Null);
         }
         Ok(match aggregate {
-            Aggregate::Sum | Aggregate::Average => match field_kind {
-                FieldKind::Float { .. } => Cell::Float(row.try_get(index)?),
-                _ => Cell::Decimal(row.try_get(index)?),
-            },
-            Aggregate::Min | Aggregate::Max => Self::from_field_row(row, index, field_kind)?,
-            Aggregate::Count => Cell::Integer(row.try_get(index)?),
+            Aggregate::Sum | Aggregate::Average | Aggregate::StdDev | Aggregate::Variance => {
+                match field_kind {
+                    FieldKind::Float { .. } => Cell::Float(row.try_get(index)?),
+                    _ => Cell::Decimal(row.try_get(index)?),
+                }
+            }
+            Aggregate::Median | Aggregate::Percentile(_) => Cell::Float(row.try_get(index)?),
+            Aggregate::Min | Aggregate::Max | Aggregate::First { .. } | Aggregate::Last { .. } => {
+                Self::from_field_row(row, index, field_kind)?
+            }
+            Aggregate::Count | Aggregate::CountDistinct => Cell::Integer(row.try_get(index)?),
+            Aggregate::BoolAnd | Aggregate::BoolOr => Cell::Boolean(row.try_get(index)?),
         })
     }
 
@@ -204,11 +220,7 @@ impl Cell {
                 Cell::Float(_) | Cell::Decimal(_) | Cell::DateTime(_) => return None,
                 Cell::Boolean(_) | Cell::Null => return Some(self),
             })),
-            FieldKind::Enumeration {
-                values,
-                default_value,
-                ..
-            } => {
+            FieldKind::Enumeration { values, .. } => {
                 let v: String = match self {
                     Cell::Integer(v) => v.to_string(),
                     Cell::Float(v) => v.to_string(),
@@ -218,14 +230,15 @@ impl Cell {
                     Cell::String(v) => v,
                     Cell::Null => return Some(self),
                 };
-                Some(
-                    if let Some((k, _)) = values.iter().find(|(_, value)| **value == v) {
-                        Cell::Integer(*k)
-                    } else {
-                        Cell::Integer(*default_value)
-                    },
-                )
+                let (k, _) = values.iter().find(|(_, value)| **value == v)?;
+                Some(Cell::Integer(*k))
             }
+            // The cells are populated automatically
+            FieldKind::RowNumber
+            | FieldKind::CreatedAt
+            | FieldKind::UpdatedAt
+            | FieldKind::CreatedBy
+            | FieldKind::UpdatedBy => None,
         }
     }
 }
@@ -0,0 +1,61 @@
+use super::jobs::JobKind;
+use crate::Id;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow};
+
+/// Notification in the inbox of a user.
+#[derive(Debug, Serialize, FromRow)]
+pub struct Notification {
+    pub notification_id: Id,
+    pub user_id: Id,
+    pub notification: Json<NotificationKind>,
+    /// The notification is unread until it is read.
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Kind of an in-app notification with its details.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum NotificationKind {
+    /// The user was mentioned in a comment on an entry.
+    Mentioned {
+        table_id: Id,
+        entry_id: Id,
+        comment_id: Id,
+        author_id: Id,
+    },
+    /// A background job of the user completed.
+    JobCompleted { job_id: Id, job: JobKind },
+    /// A background job of the user failed.
+    JobFailed { job_id: Id, job: JobKind },
+    /// A scheduled run of a dashboard report failed after all its attempts.
+    ReportFailed {
+        dashboard_id: Id,
+        report_id: Id,
+        run_id: Id,
+    },
+}
+
+/// Get notifications query parameters.
+#[derive(Debug, Deserialize)]
+pub struct NotificationsQuery {
+    /// Only get the unread notifications.
+    #[serde(default)]
+    pub unread: bool,
+    /// Only get the notifications older than this notification, to get the next page.
+    pub before: Option<Id>,
+}
+
+/// Mark notification request.
+#[derive(Debug, Deserialize)]
+pub struct UpdateNotification {
+    pub is_read: bool,
+}
+
+/// Number of unread notifications response.
+#[derive(Debug, Serialize)]
+pub struct UnreadNotifications {
+    pub unread: i64,
+}
@@ -0,0 +1,34 @@
+use crate::Id;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Kind of the items which can be moved to the trash.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "text")]
+pub enum TrashItemKind {
+    Table,
+    Field,
+    Dashboard,
+    Chart,
+}
+
+/// Item in the trash of a user.
+///
+/// The parent is the parent table of a table, the table of a field
+/// or the dashboard of a chart.
+#[derive(Debug, Serialize, FromRow)]
+pub struct TrashItem {
+    pub item_kind: TrashItemKind,
+    pub item_id: Id,
+    pub parent_id: Option<Id>,
+    pub name: String,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Restore trash item request.
+#[derive(Debug, Deserialize)]
+pub struct RestoreTrashItem {
+    pub item_kind: TrashItemKind,
+    pub item_id: Id,
+}
@@ -0,0 +1,37 @@
+use crate::Id;
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// Usage of the resources of a user against their limits.
+#[derive(Debug, Serialize)]
+pub struct Usage {
+    pub tables: ResourceUsage,
+    pub table_usage: Vec<TableUsage>,
+    pub max_import_bytes: usize,
+    pub max_body_bytes: usize,
+}
+
+/// Usage of a resource against its limit.
+#[derive(Debug, Serialize)]
+pub struct ResourceUsage {
+    pub used: i64,
+    pub limit: i64,
+}
+
+/// Usage of the resources of a table against their limits.
+#[derive(Debug, Serialize)]
+pub struct TableUsage {
+    pub table_id: Id,
+    pub name: String,
+    pub fields: ResourceUsage,
+    pub rows: ResourceUsage,
+}
+
+/// Number of fields and entries of a table.
+#[derive(Debug, FromRow)]
+pub struct TableCount {
+    pub table_id: Id,
+    pub name: String,
+    pub field_count: i64,
+    pub row_count: i64,
+}
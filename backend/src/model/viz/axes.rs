@@ -4,20 +4,29 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{types::Json, FromRow};
 
-use crate::{model::data::FieldKind, Id};
+use crate::{
+    model::data::{FieldIdentifier, FieldKind},
+    Id,
+};
 
-#[derive(Debug, Serialize, FromRow)]
+use super::TimeUnit;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
 pub struct Axis {
     pub axis_id: Id,
     pub chart_id: Id,
     pub field_id: Id,
     pub axis_kind: AxisKind,
-    pub aggregate: Option<Aggregate>,
+    pub aggregate: Option<Json<Aggregate>>,
+    pub time_bucket: Option<TimeUnit>,
+    pub time_zone: Option<String>,
+    pub fill_gaps: bool,
+    pub sort: Option<SortDirection>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, sqlx::Type)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, sqlx::Type)]
 #[sqlx(type_name = "axis_kind")]
 pub enum AxisKind {
     X,
@@ -27,55 +36,162 @@ pub enum AxisKind {
     Tooltip,
     Label,
     Detail,
+    Row,
+    Column,
+    Value,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "sort_direction")]
+pub enum SortDirection {
+    Ascending,
+    Descending,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone)]
-#[sqlx(type_name = "aggregate")]
+impl SortDirection {
+    pub fn get_sql_order(&self) -> &'static str {
+        match self {
+            SortDirection::Ascending => "ASC",
+            SortDirection::Descending => "DESC",
+        }
+    }
+}
+
+/// Aggregate function of an axis, stored as JSON because some aggregates have options.
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Aggregate {
     Sum,
     Average,
     Min,
     Max,
     Count,
+    CountDistinct,
+    Median,
+    /// Continuous percentile, the fraction is between 0 and 1.
+    Percentile(f64),
+    StdDev,
+    Variance,
+    /// Value of the entry with the lowest value of the sort field.
+    First {
+        field_id: Id,
+    },
+    /// Value of the entry with the highest value of the sort field.
+    Last {
+        field_id: Id,
+    },
+    BoolAnd,
+    BoolOr,
 }
 
 impl Aggregate {
-    pub fn get_sql_aggregate(&self) -> &'static str {
+    /// Get the field sorting the values of the First and Last aggregates.
+    pub fn get_sort_field_id(&self) -> Option<Id> {
         match self {
-            Aggregate::Sum => "SUM",
-            Aggregate::Average => "AVG",
-            Aggregate::Min => "MIN",
-            Aggregate::Max => "MAX",
-            Aggregate::Count => "COUNT",
+            Aggregate::First { field_id } | Aggregate::Last { field_id } => Some(*field_id),
+            _ => None,
         }
     }
 
-    pub fn get_sql_type(&self, field_kind: &FieldKind) -> &'static str {
+    /// Replace the field sorting the values of the First and Last aggregates.
+    pub fn map_sort_field_id(self, map: impl FnOnce(Id) -> Id) -> Self {
         match self {
-            Aggregate::Sum | Aggregate::Average => match field_kind {
-                FieldKind::Float { .. } => "DOUBLE PRECISION",
-                _ => "NUMERIC",
+            Aggregate::First { field_id } => Aggregate::First {
+                field_id: map(field_id),
             },
-            Aggregate::Min | Aggregate::Max => field_kind.get_sql_type(),
-            Aggregate::Count => "BIGINT",
+            Aggregate::Last { field_id } => Aggregate::Last {
+                field_id: map(field_id),
+            },
+            aggregate => aggregate,
+        }
+    }
+
+    /// Get the SQL expression aggregating the column, only over the rows matching the filter if any.
+    pub fn get_sql_aggregate(&self, column: &str, filter: Option<&str>) -> String {
+        let filter = filter
+            .map(|condition| format!(" FILTER (WHERE {condition})"))
+            .unwrap_or_default();
+        match self {
+            Aggregate::Sum => format!("SUM({column}){filter}"),
+            Aggregate::Average => format!("AVG({column}){filter}"),
+            Aggregate::Min => format!("MIN({column}){filter}"),
+            Aggregate::Max => format!("MAX({column}){filter}"),
+            Aggregate::Count => format!("COUNT({column}){filter}"),
+            Aggregate::CountDistinct => format!("COUNT(DISTINCT {column}){filter}"),
+            Aggregate::Median => {
+                format!("percentile_cont(0.5) WITHIN GROUP (ORDER BY {column}){filter}")
+            }
+            Aggregate::Percentile(fraction) => {
+                format!("percentile_cont({fraction}) WITHIN GROUP (ORDER BY {column}){filter}")
+            }
+            Aggregate::StdDev => format!("stddev_samp({column}){filter}"),
+            Aggregate::Variance => format!("var_samp({column}){filter}"),
+            Aggregate::First { field_id } => format!(
+                "(array_agg({column} ORDER BY {} ASC NULLS LAST){filter})[1]",
+                FieldIdentifier::new(*field_id)
+            ),
+            Aggregate::Last { field_id } => format!(
+                "(array_agg({column} ORDER BY {} DESC NULLS LAST){filter})[1]",
+                FieldIdentifier::new(*field_id)
+            ),
+            Aggregate::BoolAnd => format!("bool_and({column}){filter}"),
+            Aggregate::BoolOr => format!("bool_or({column}){filter}"),
+        }
+    }
+
+    /// Check if the aggregated values are numbers.
+    pub fn is_numeric(&self, field_kind: &FieldKind) -> bool {
+        match self {
+            Aggregate::Min | Aggregate::Max | Aggregate::First { .. } | Aggregate::Last { .. } => {
+                field_kind.is_numeric()
+            }
+            Aggregate::BoolAnd | Aggregate::BoolOr => false,
+            _ => true,
+        }
+    }
+
+    pub fn get_sql_type(&self, field_kind: &FieldKind) -> &'static str {
+        match self {
+            Aggregate::Sum | Aggregate::Average | Aggregate::StdDev | Aggregate::Variance => {
+                match field_kind {
+                    FieldKind::Float { .. } => "DOUBLE PRECISION",
+                    _ => "NUMERIC",
+                }
+            }
+            Aggregate::Median | Aggregate::Percentile(_) => "DOUBLE PRECISION",
+            Aggregate::Min | Aggregate::Max | Aggregate::First { .. } | Aggregate::Last { .. } => {
+                field_kind.get_sql_type()
+            }
+            Aggregate::Count | Aggregate::CountDistinct => "BIGINT",
+            Aggregate::BoolAnd | Aggregate::BoolOr => "BOOLEAN",
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CreateAxis {
     pub field_id: Id,
     pub axis_kind: AxisKind,
     pub aggregate: Option<Aggregate>,
+    /// Truncate the date times of a DateTime field to this unit.
+    pub time_bucket: Option<TimeUnit>,
+    /// Time zone of the time buckets, UTC by default.
+    pub time_zone: Option<String>,
+    /// Add the empty time buckets between the first and last ones.
+    #[serde(default)]
+    pub fill_gaps: bool,
+    /// Sort the chart data by this axis.
+    pub sort: Option<SortDirection>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct SetAxes(pub Vec<CreateAxis>);
 
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Debug, Clone, Serialize, FromRow)]
 pub struct AxisField {
     #[sqlx(flatten)]
     pub axis: Axis,
+    /// Table of the field, which is the table of the chart or one joined to it.
+    pub table_id: Id,
     pub field_name: String,
     pub field_kind: Json<FieldKind>,
 }
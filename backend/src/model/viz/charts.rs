@@ -1,28 +1,161 @@
 use crate::{model::Cell, Id};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_with::{json::JsonString, serde_as};
 use sqlx::prelude::FromRow;
 use std::{collections::HashMap, fmt};
 
-use super::AxisField;
+use super::{AxisField, AxisKind, Filter, FilterKind};
 
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Debug, Clone, Serialize, FromRow)]
 pub struct Chart {
     pub chart_id: Id,
     pub dashboard_id: Id,
     pub table_id: Id,
     pub name: String,
     pub chart_kind: ChartKind,
+    pub row_limit: Option<i32>,
+    pub top_n: Option<i32>,
+    pub cache_mode: CacheMode,
+    pub refresh_interval: Option<i32>,
+    pub refreshed_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
 #[sqlx(type_name = "chart_kind")]
 pub enum ChartKind {
     Table,
     Bar,
     Line,
+    Area,
+    StackedBar,
+    Pie,
+    Scatter,
+    Heatmap,
+    Histogram,
+    /// Card displaying a single value.
+    Kpi,
+    /// Cross-tab of the value axes grouped by the row and column axes.
+    Pivot,
+}
+
+impl ChartKind {
+    /// Get the rules of the axes allowed for this chart kind.
+    /// Axis kinds without a rule are not allowed.
+    ///
+    /// Returns `None` if any axes are allowed.
+    pub fn get_axis_rules(&self) -> Option<Vec<AxisRule>> {
+        use AggregateRule::*;
+        use AxisKind::*;
+
+        Some(match self {
+            ChartKind::Table => return None,
+            ChartKind::Bar | ChartKind::Line | ChartKind::Area => vec![
+                AxisRule::new(X, 1, Some(1), Any, false),
+                AxisRule::new(Y, 1, None, Any, false),
+                AxisRule::new(Color, 0, Some(1), Forbidden, false),
+                AxisRule::new(Tooltip, 0, None, Any, false),
+                AxisRule::new(Label, 0, None, Any, false),
+                AxisRule::new(Detail, 0, None, Any, false),
+            ],
+            ChartKind::StackedBar => vec![
+                AxisRule::new(X, 1, Some(1), Forbidden, false),
+                AxisRule::new(Y, 1, None, Required, true),
+                AxisRule::new(Color, 0, Some(1), Forbidden, false),
+                AxisRule::new(Tooltip, 0, None, Any, false),
+                AxisRule::new(Label, 0, None, Any, false),
+            ],
+            ChartKind::Pie => vec![
+                AxisRule::new(Label, 1, Some(1), Forbidden, false),
+                AxisRule::new(Y, 1, Some(1), Required, true),
+                AxisRule::new(Tooltip, 0, None, Any, false),
+            ],
+            ChartKind::Scatter => vec![
+                AxisRule::new(X, 1, Some(1), Any, true),
+                AxisRule::new(Y, 1, Some(1), Any, true),
+                AxisRule::new(Color, 0, Some(1), Any, false),
+                AxisRule::new(Size, 0, Some(1), Any, true),
+                AxisRule::new(Tooltip, 0, None, Any, false),
+                AxisRule::new(Label, 0, None, Any, false),
+                AxisRule::new(Detail, 0, None, Any, false),
+            ],
+            ChartKind::Heatmap => vec![
+                AxisRule::new(X, 1, Some(1), Forbidden, false),
+                AxisRule::new(Y, 1, Some(1), Forbidden, false),
+                AxisRule::new(Color, 1, Some(1), Required, true),
+                AxisRule::new(Tooltip, 0, None, Any, false),
+            ],
+            ChartKind::Histogram => vec![
+                AxisRule::new(X, 1, Some(1), Forbidden, true),
+                AxisRule::new(Y, 1, Some(1), Count, true),
+                AxisRule::new(Tooltip, 0, None, Any, false),
+            ],
+            ChartKind::Pivot => vec![
+                AxisRule::new(Row, 0, None, Forbidden, false),
+                AxisRule::new(Column, 0, None, Forbidden, false),
+                AxisRule::new(Value, 1, None, Required, false),
+            ],
+            ChartKind::Kpi => vec![
+                AxisRule::new(Y, 1, Some(1), Required, true),
+                AxisRule::new(Label, 0, Some(1), Required, false),
+            ],
+        })
+    }
+}
+
+/// Where the data of a chart comes from when it is requested.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "cache_mode")]
+pub enum CacheMode {
+    /// The view of the chart is queried on every request.
+    #[default]
+    Live,
+    /// The chart data is cached in the server process until its tables change.
+    Memory,
+    /// The view of the chart is materialized and refreshed on a schedule.
+    /// Requests with filters are not cached.
+    Materialized,
+}
+
+/// The axes of an axis kind allowed in a chart kind.
+#[derive(Debug)]
+pub struct AxisRule {
+    pub axis_kind: AxisKind,
+    pub min_count: usize,
+    pub max_count: Option<usize>,
+    pub aggregate: AggregateRule,
+    /// The values of the axes must be numbers.
+    pub is_numeric: bool,
+}
+
+impl AxisRule {
+    fn new(
+        axis_kind: AxisKind,
+        min_count: usize,
+        max_count: Option<usize>,
+        aggregate: AggregateRule,
+        is_numeric: bool,
+    ) -> Self {
+        Self {
+            axis_kind,
+            min_count,
+            max_count,
+            aggregate,
+            is_numeric,
+        }
+    }
+}
+
+/// Aggregates allowed for the axes of an axis rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateRule {
+    Any,
+    Required,
+    Forbidden,
+    /// Only a count is allowed.
+    Count,
 }
 
 #[derive(Debug, Deserialize)]
@@ -30,6 +163,14 @@ pub struct CreateChart {
     pub table_id: Id,
     pub name: String,
     pub chart_kind: ChartKind,
+    /// Maximum number of rows of the chart data.
+    pub row_limit: Option<i32>,
+    /// Number of rows kept in the chart data, the others are grouped in an "Other" row.
+    pub top_n: Option<i32>,
+    #[serde(default)]
+    pub cache_mode: CacheMode,
+    /// Seconds after which the cached chart data is recomputed.
+    pub refresh_interval: Option<i32>,
 }
 
 
@@ -37,13 +178,58 @@ pub struct CreateChart {
 pub struct UpdateChart {
     pub name: String,
     pub chart_kind: ChartKind,
+    pub row_limit: Option<i32>,
+    pub top_n: Option<i32>,
+    #[serde(default)]
+    pub cache_mode: CacheMode,
+    pub refresh_interval: Option<i32>,
 }
 
-#[derive(Debug, Serialize)]
+/// Copy chart request.
+#[derive(Debug, Default, Deserialize)]
+pub struct CopyChart {
+    /// Dashboard of the copy, the dashboard of the chart by default.
+    pub dashboard_id: Option<Id>,
+    /// Table of the copy, the table of the chart by default.
+    /// Its fields must match the fields of the axes and filters by name and kind.
+    pub table_id: Option<Id>,
+}
+
+/// Chart with axes on a field, which depends on the field.
+#[derive(Debug, Serialize, FromRow)]
+pub struct ChartDependency {
+    pub chart_id: Id,
+    pub dashboard_id: Id,
+    pub name: String,
+    /// Axes on the field or sorted by the field.
+    pub axis_ids: Vec<Id>,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ChartData {
     pub chart: Chart,
     pub axes: Vec<AxisField>,
+    /// Filters applied to the chart, with the overriden values.
+    pub filters: Vec<Filter>,
     pub cells: Vec<HashMap<Id, Cell>>,
+    /// Aggregates of the rows after the top N rows, if there are any.
+    pub other: Option<HashMap<Id, Cell>>,
+    /// For pivot charts, the row and column axes totaled in each row of cells.
+    /// Subtotals have some of these axes and the grand total has all of them.
+    pub totals: Option<Vec<Vec<Id>>>,
+    /// When the cells were computed, which is earlier than the request if they were cached.
+    pub computed_at: DateTime<Utc>,
+}
+
+/// Chart data request query parameters.
+#[serde_as]
+#[derive(Debug, Default, Deserialize)]
+pub struct ChartDataQuery {
+    /// JSON object of filter values replacing the saved ones.
+    /// Keys map to filter IDs.
+    #[serde_as(as = "Option<JsonString>")]
+    #[serde(default)]
+    pub filters: Option<HashMap<Id, FilterKind>>,
 }
 
 #[derive(Debug)]
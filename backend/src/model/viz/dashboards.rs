@@ -4,7 +4,7 @@ use sqlx::prelude::FromRow;
 use crate::Id;
 
 
-#[derive(Serialize, FromRow)]
+#[derive(Debug, Serialize, FromRow)]
 pub struct Dashboard {
     pub dashboard_id: Id,
     pub user_id: Id,
@@ -24,4 +24,10 @@ pub struct CreateDashboard {
 pub struct UpdateDashboard {
     pub name: String,
     pub description: String,
-}
\ No newline at end of file
+}
+
+#[derive(Deserialize)]
+pub struct CopyDashboard {
+    /// Name of the copy, the name of the dashboard by default.
+    pub name: Option<String>,
+}
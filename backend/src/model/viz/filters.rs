@@ -0,0 +1,162 @@
+use crate::{model::data::FieldKind, Id};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow};
+
+/// Filter on the rows of the charts of a dashboard.
+///
+/// A dashboard filter (without chart ID) applies to every chart on the table of the field.
+/// A chart filter only applies to its chart.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Filter {
+    pub filter_id: Id,
+    pub dashboard_id: Id,
+    pub chart_id: Option<Id>,
+    pub field_id: Id,
+    pub filter_kind: Json<FilterKind>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// The condition of a filter on a field.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum FilterKind {
+    /// Inclusive range on a number field.
+    NumberRange {
+        start: Option<Decimal>,
+        end: Option<Decimal>,
+    },
+    /// Inclusive range on a date time field.
+    DateRange {
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    },
+    /// Date times from an amount of time units ago until now, such as the last 30 days.
+    LastPeriod { unit: TimeUnit, amount: i32 },
+    /// Date times in the current time unit, such as this quarter.
+    CurrentPeriod {
+        unit: TimeUnit,
+        time_zone: Option<String>,
+    },
+    /// Enumeration field equal to one of the values.
+    Enumeration { values: Vec<i64> },
+    /// Text or web link field equal to the value.
+    Text { value: String },
+    /// Checkbox field equal to the value.
+    Checkbox { value: bool },
+}
+
+impl FilterKind {
+    /// Check if the filter can be applied to a field of this kind.
+    pub fn applies_to(&self, field_kind: &FieldKind) -> bool {
+        match self {
+            FilterKind::NumberRange { .. } => matches!(
+                field_kind,
+                FieldKind::Integer { .. }
+                    | FieldKind::Float { .. }
+                    | FieldKind::Money { .. }
+                    | FieldKind::Progress { .. }
+                    | FieldKind::RowNumber
+            ),
+            FilterKind::DateRange { .. }
+            | FilterKind::LastPeriod { .. }
+            | FilterKind::CurrentPeriod { .. } => field_kind.is_date_time(),
+            FilterKind::Enumeration { .. } => matches!(field_kind, FieldKind::Enumeration { .. }),
+            FilterKind::Text { .. } => matches!(
+                field_kind,
+                FieldKind::Text { .. } | FieldKind::WebLink { .. }
+            ),
+            FilterKind::Checkbox { .. } => matches!(field_kind, FieldKind::Checkbox),
+        }
+    }
+}
+
+/// Unit of time used for periods and time buckets.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "time_unit")]
+pub enum TimeUnit {
+    Hour,
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl TimeUnit {
+    /// Largest amount of time units of a period filter.
+    pub const MAX_PERIOD_AMOUNT: i32 = 10_000;
+
+    /// Map the time unit to the PostgreSQL `date_trunc` field.
+    pub fn get_sql_unit(&self) -> &'static str {
+        match self {
+            TimeUnit::Hour => "hour",
+            TimeUnit::Day => "day",
+            TimeUnit::Week => "week",
+            TimeUnit::Month => "month",
+            TimeUnit::Quarter => "quarter",
+            TimeUnit::Year => "year",
+        }
+    }
+
+    /// Get the PostgreSQL interval of an amount of this time unit.
+    pub fn get_sql_interval(&self, amount: i32) -> String {
+        match self {
+            TimeUnit::Quarter => format!("{} months", i64::from(amount) * 3),
+            unit => format!("{amount} {}s", unit.get_sql_unit()),
+        }
+    }
+}
+
+/// Create filter request.
+#[derive(Debug, Deserialize)]
+pub struct CreateFilter {
+    pub chart_id: Option<Id>,
+    pub field_id: Id,
+    pub filter_kind: FilterKind,
+}
+
+/// Update filter request.
+#[derive(Debug, Deserialize)]
+pub struct UpdateFilter {
+    pub filter_kind: FilterKind,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sql_intervals() {
+        assert_eq!(TimeUnit::Hour.get_sql_interval(1), "1 hours");
+        assert_eq!(TimeUnit::Week.get_sql_interval(2), "2 weeks");
+        assert_eq!(TimeUnit::Quarter.get_sql_interval(2), "6 months");
+        assert_eq!(TimeUnit::Year.get_sql_interval(30), "30 years");
+    }
+
+    #[test]
+    fn applies_to_field_kinds() {
+        let number_range = FilterKind::NumberRange {
+            start: None,
+            end: None,
+        };
+        let text = FilterKind::Text {
+            value: String::new(),
+        };
+
+        assert!(number_range.applies_to(&FieldKind::RowNumber));
+        assert!(!number_range.applies_to(&FieldKind::Checkbox));
+        assert!(text.applies_to(&FieldKind::Text { is_required: false }));
+        assert!(!text.applies_to(&FieldKind::RowNumber));
+    }
+
+    #[test]
+    fn large_quarter_interval() {
+        assert_eq!(
+            TimeUnit::Quarter.get_sql_interval(i32::MAX),
+            format!("{} months", i64::from(i32::MAX) * 3)
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use crate::Id;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow};
+use std::collections::HashMap;
+
+/// Item on the grid of a dashboard, which is either a chart or a block.
+#[derive(Debug, Serialize, FromRow)]
+pub struct LayoutItem {
+    pub item_id: Id,
+    pub dashboard_id: Id,
+    pub chart_id: Option<Id>,
+    pub block: Option<Json<LayoutBlock>>,
+    /// Position of the item at every breakpoint where it is shown.
+    pub positions: Json<HashMap<Breakpoint, GridPosition>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Content of a layout item other than a chart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum LayoutBlock {
+    /// Header starting a section of the dashboard.
+    Section { title: String },
+    /// Paragraph of text.
+    Text { text: String },
+}
+
+/// Screen widths for which the dashboard has a layout.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Breakpoint {
+    Large,
+    Medium,
+    Small,
+}
+
+impl Breakpoint {
+    /// Number of columns of the grid at this breakpoint.
+    pub fn get_columns(&self) -> i32 {
+        match self {
+            Breakpoint::Large => 12,
+            Breakpoint::Medium => 8,
+            Breakpoint::Small => 4,
+        }
+    }
+}
+
+/// Cells of the grid covered by an item, in columns and rows.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GridPosition {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl GridPosition {
+    /// Check if the position is inside a grid with this number of columns,
+    /// with its end columns and rows representable.
+    pub fn fits(&self, columns: i32) -> bool {
+        self.x >= 0
+            && self.y >= 0
+            && self.width >= 1
+            && self.height >= 1
+            && self
+                .x
+                .checked_add(self.width)
+                .is_some_and(|end| end <= columns)
+            && self.y.checked_add(self.height).is_some()
+    }
+
+    /// Check if the positions share a cell, computed in 64 bits so the ends do not overflow.
+    pub fn overlaps(&self, other: &GridPosition) -> bool {
+        let (x, y, width, height) = self.as_i64();
+        let (other_x, other_y, other_width, other_height) = other.as_i64();
+        x < other_x + other_width
+            && other_x < x + width
+            && y < other_y + other_height
+            && other_y < y + height
+    }
+
+    fn as_i64(&self) -> (i64, i64, i64, i64) {
+        (
+            self.x.into(),
+            self.y.into(),
+            self.width.into(),
+            self.height.into(),
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateLayoutItem {
+    pub chart_id: Option<Id>,
+    pub block: Option<LayoutBlock>,
+    pub positions: HashMap<Breakpoint, GridPosition>,
+}
+
+/// Set layout request, replacing all the items of the dashboard.
+#[derive(Debug, Deserialize)]
+pub struct SetLayout(pub Vec<CreateLayoutItem>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(x: i32, y: i32, width: i32, height: i32) -> GridPosition {
+        GridPosition {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn overlapping_positions() {
+        let item = position(2, 2, 4, 3);
+        assert!(item.overlaps(&item));
+        assert!(item.overlaps(&position(0, 0, 3, 3)));
+        assert!(item.overlaps(&position(5, 4, 1, 1)));
+        assert!(item.overlaps(&position(3, 3, 1, 1)));
+        assert!(position(3, 3, 1, 1).overlaps(&item));
+    }
+
+    #[test]
+    fn adjacent_positions() {
+        let item = position(2, 2, 4, 3);
+        assert!(!item.overlaps(&position(6, 2, 2, 3)));
+        assert!(!item.overlaps(&position(0, 2, 2, 3)));
+        assert!(!item.overlaps(&position(2, 5, 4, 1)));
+        assert!(!item.overlaps(&position(2, 0, 4, 2)));
+    }
+
+    #[test]
+    fn overflowing_positions() {
+        let item = position(0, i32::MAX - 1, 4, i32::MAX);
+        assert!(item.overlaps(&position(0, i32::MAX - 1, 1, 1)));
+        assert!(!item.overlaps(&position(0, 0, 4, i32::MAX - 1)));
+        assert!(!item.fits(12));
+        assert!(!position(i32::MAX, 0, 1, 1).fits(12));
+        assert!(position(8, i32::MAX - 1, 4, 1).fits(12));
+        assert!(!position(8, 0, 5, 1).fits(12));
+    }
+}
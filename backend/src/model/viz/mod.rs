@@ -1,6 +1,9 @@
 mod axes;
 mod charts;
 mod dashboards;
+mod filters;
+mod layout;
+mod reports;
 
-pub use {axes::*, charts::*, dashboards::*};
+pub use {axes::*, charts::*, dashboards::*, filters::*, layout::*, reports::*};
 
@@ -0,0 +1,83 @@
+use crate::Id;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Dashboard report response.
+#[derive(Debug, Serialize, FromRow)]
+pub struct Report {
+    pub report_id: Id,
+    pub dashboard_id: Id,
+    pub recipients: Vec<String>,
+    pub frequency: ReportFrequency,
+    pub time_zone: String,
+    pub next_run_at: DateTime<Utc>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// How often a report is sent.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "report_frequency")]
+pub enum ReportFrequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// Create report request.
+#[derive(Debug, Deserialize)]
+pub struct CreateReport {
+    pub recipients: Vec<String>,
+    pub frequency: ReportFrequency,
+    /// IANA time zone in which the runs keep their local time, UTC by default.
+    pub time_zone: Option<String>,
+    /// First run of the report, the next runs are repeated at the frequency.
+    pub next_run_at: DateTime<Utc>,
+}
+
+/// Update report request.
+#[derive(Debug, Deserialize)]
+pub struct UpdateReport {
+    pub recipients: Vec<String>,
+    pub frequency: ReportFrequency,
+    pub time_zone: Option<String>,
+    pub next_run_at: DateTime<Utc>,
+    pub is_active: bool,
+}
+
+/// Status of a report run.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "report_run_status")]
+pub enum ReportRunStatus {
+    Pending,
+    Sent,
+    Failed,
+}
+
+/// Report run log response.
+#[derive(Debug, Serialize, FromRow)]
+pub struct ReportRun {
+    pub run_id: Id,
+    pub report_id: Id,
+    pub status: ReportRunStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A pending run with the report to send.
+#[derive(Debug, FromRow)]
+pub struct PendingReportRun {
+    pub run_id: Id,
+    pub attempts: i32,
+    pub report_id: Id,
+    pub dashboard_id: Id,
+    /// Owner of the dashboard, notified when the run fails.
+    pub user_id: Id,
+    pub recipients: Vec<String>,
+}
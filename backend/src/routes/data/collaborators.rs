@@ -0,0 +1,113 @@
+use super::ApiState;
+use crate::{
+    db::{self, AuthSession},
+    error::{ApiError, ApiResult, ErrorMessage},
+    model::data::{Collaborator, CreateCollaborator},
+    Id,
+};
+use axum::{
+    extract::{Path, State},
+    routing::{delete, post},
+    Json, Router,
+};
+
+const USER_NOT_FOUND: ErrorMessage = ("username", "User not found");
+const USER_IS_OWNER: ErrorMessage = (
+    "username",
+    "The owner of the table cannot be a collaborator",
+);
+
+pub fn router() -> Router<ApiState> {
+    Router::new().nest(
+        "/tables/{table-id}/collaborators",
+        Router::new()
+            .route("/", post(create_collaborator).get(get_collaborators))
+            .route("/{user-id}", delete(delete_collaborator)),
+    )
+}
+
+/// Share a table with another user.
+///
+/// Collaborators can get the table data, subscribe to its events, and comment on its entries.
+/// Only the owner of the table can manage its collaborators.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table
+/// - [ApiError::NotFound]: Table not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [USER_NOT_FOUND]
+///     - [USER_IS_OWNER]
+///
+async fn create_collaborator(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(table_id): Path<Id>,
+    Json(CreateCollaborator { username }): Json<CreateCollaborator>,
+) -> ApiResult<Json<Collaborator>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+
+    let collaborator_id = db::get_user_id(&pool, &username)
+        .await?
+        .ok_or_else(|| ApiError::unprocessable_entity([USER_NOT_FOUND]))?;
+
+    if collaborator_id == user_id {
+        return Err(ApiError::unprocessable_entity([USER_IS_OWNER]));
+    }
+
+    let collaborator = db::create_collaborator(&pool, table_id, collaborator_id).await?;
+
+    Ok(Json(collaborator))
+}
+
+/// Stop sharing a table with a user.
+///
+/// The event streams of the user on the table end.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table
+/// - [ApiError::NotFound]: Table not found
+///
+async fn delete_collaborator(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((table_id, collaborator_id)): Path<(Id, Id)>,
+) -> ApiResult<()> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+
+    db::delete_collaborator(&pool, table_id, collaborator_id).await?;
+
+    Ok(())
+}
+
+/// Get the users a table is shared with, by username.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table
+/// - [ApiError::NotFound]: Table not found
+///
+async fn get_collaborators(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(table_id): Path<Id>,
+) -> ApiResult<Json<Vec<Collaborator>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+
+    let collaborators = db::get_collaborators(&pool, table_id).await?;
+
+    Ok(Json(collaborators))
+}
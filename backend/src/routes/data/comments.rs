@@ -0,0 +1,221 @@
+use super::ApiState;
+use crate::{
+    db::{self, AuthSession},
+    error::{ApiError, ApiResult, ErrorMessage},
+    model::data::{Comment, CreateComment, UpdateComment},
+    Id,
+};
+use axum::{
+    extract::{Path, State},
+    routing::{patch, post},
+    Json, Router,
+};
+use itertools::Itertools;
+
+const BODY_EMPTY: ErrorMessage = ("body", "Comment cannot be empty");
+
+pub fn router() -> Router<ApiState> {
+    Router::new().nest(
+        "/tables/{table-id}/entries/{entry-id}/comments",
+        Router::new()
+            .route("/", post(create_comment).get(get_comments))
+            .route(
+                "/{comment-id}",
+                patch(update_comment).delete(delete_comment),
+            ),
+    )
+}
+
+/// Comment on an entry.
+///
+/// The users mentioned with an `@username` in the body are notified, if they can access the table.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table
+/// - [ApiError::NotFound]: Table or entry not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [BODY_EMPTY]
+///
+async fn create_comment(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((table_id, entry_id)): Path<(Id, Id)>,
+    Json(create_comment): Json<CreateComment>,
+) -> ApiResult<Json<Comment>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_shared_api_result()?;
+    db::check_entry_relation(&pool, table_id, entry_id)
+        .await?
+        .to_api_result()?;
+
+    validate_body(&create_comment.body)?;
+
+    let mentions = get_mentions(&create_comment.body);
+
+    let comment = db::create_comment(
+        &pool,
+        table_id,
+        entry_id,
+        user_id,
+        create_comment,
+        &mentions,
+    )
+    .await?;
+
+    Ok(Json(comment))
+}
+
+/// Edit the body of a comment. Only the author of a comment can edit it.
+///
+/// The users mentioned for the first time are notified.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table or is not the author
+/// - [ApiError::NotFound]: Table, entry or comment not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [BODY_EMPTY]
+///
+async fn update_comment(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((table_id, entry_id, comment_id)): Path<(Id, Id, Id)>,
+    Json(update_comment): Json<UpdateComment>,
+) -> ApiResult<Json<Comment>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_shared_api_result()?;
+    db::check_comment_relation(&pool, user_id, table_id, entry_id, comment_id)
+        .await?
+        .to_api_result()?;
+
+    validate_body(&update_comment.body)?;
+
+    let mentions = get_mentions(&update_comment.body);
+
+    let comment = db::update_comment(&pool, comment_id, user_id, update_comment, &mentions).await?;
+
+    Ok(Json(comment))
+}
+
+/// Delete a comment. Only the author of a comment can delete it.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table or is not the author
+/// - [ApiError::NotFound]: Table, entry or comment not found
+///
+async fn delete_comment(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((table_id, entry_id, comment_id)): Path<(Id, Id, Id)>,
+) -> ApiResult<()> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_shared_api_result()?;
+    db::check_comment_relation(&pool, user_id, table_id, entry_id, comment_id)
+        .await?
+        .to_api_result()?;
+
+    db::delete_comment(&pool, comment_id).await?;
+
+    Ok(())
+}
+
+/// Get the comments of an entry, oldest first.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table
+/// - [ApiError::NotFound]: Table or entry not found
+///
+async fn get_comments(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((table_id, entry_id)): Path<(Id, Id)>,
+) -> ApiResult<Json<Vec<Comment>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_shared_api_result()?;
+    db::check_entry_relation(&pool, table_id, entry_id)
+        .await?
+        .to_api_result()?;
+
+    let comments = db::get_comments(&pool, table_id, entry_id).await?;
+
+    Ok(Json(comments))
+}
+
+fn validate_body(body: &str) -> ApiResult<()> {
+    if body.trim().is_empty() {
+        Err(ApiError::unprocessable_entity([BODY_EMPTY]))
+    } else {
+        Ok(())
+    }
+}
+
+/// Get the usernames mentioned with an `@username` in a comment body.
+///
+/// The `@` must not follow an alphanumeric character, to ignore email addresses.
+/// A mention ends at the first character which is not alphanumeric, `_`, `-` or `.`,
+/// and trailing dots are ignored to allow a mention at the end of a sentence.
+fn get_mentions(body: &str) -> Vec<String> {
+    body.match_indices('@')
+        .filter(|(i, _)| {
+            !body[..*i]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric)
+        })
+        .map(|(i, _)| {
+            body[i + 1..]
+                .split(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.')))
+                .next()
+                .unwrap_or_default()
+                .trim_end_matches('.')
+        })
+        .filter(|username| !username.is_empty())
+        .map(str::to_string)
+        .unique()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mentions_end_at_punctuation() {
+        assert_eq!(
+            get_mentions("Thanks @alice, see @bob.smith. (@carol_1) @dave-2!"),
+            ["alice", "bob.smith", "carol_1", "dave-2"]
+        );
+    }
+
+    #[test]
+    fn duplicate_mentions() {
+        assert_eq!(
+            get_mentions("@alice and @bob, @alice again"),
+            ["alice", "bob"]
+        );
+    }
+
+    #[test]
+    fn email_addresses_ignored() {
+        assert_eq!(
+            get_mentions("Mail alice@example.com or @bob at bob@example.com"),
+            ["bob"]
+        );
+        assert!(get_mentions("@ @. and a lone @").is_empty());
+    }
+}
@@ -6,6 +6,7 @@ use crate::{
         data::{CreateEntries, Entry, FieldKind, FieldMetadata, UpdateEntry},
         Cell,
     },
+    routes::usage,
     Id,
 };
 use axum::{
@@ -17,7 +18,10 @@ use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use rust_decimal::Decimal;
 use serde_json::Value;
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
 const IS_REQUIRED: &str = "A value is required";
 const OUT_OF_RANGE: &str = "Value is out of range";
@@ -37,6 +41,8 @@ pub fn router() -> Router<ApiState> {
 /// Create many entries in a table.
 /// 
 /// Can optionally take a parent entry ID.
+/// The fields missing from an entry take the default of their column.
+/// The cells of automatic fields are populated, their given values are ignored.
 ///
 /// # Errors
 /// - [`ApiError::Unauthorized`]: User not authenticated
@@ -47,10 +53,11 @@ pub fn router() -> Router<ApiState> {
 ///     - <field_id>: [`INVALID_TYPE`]
 ///     - <field_id>: [`ENUMERATION_VALUE_MISSING`]
 ///     - <field_id>: [`INVALID_FIELD_ID`]
+///     - [`usage::ROW_LIMIT`]
 ///
 async fn create_entries(
     AuthSession { user, .. }: AuthSession,
-    State(ApiState { pool, .. }): State<ApiState>,
+    State(ApiState { pool, limits, .. }): State<ApiState>,
     Path(table_id): Path<Id>,
     Json(CreateEntries { parent_id, entries }): Json<CreateEntries>,
 ) -> ApiResult<Json<Vec<Entry>>> {
@@ -67,14 +74,29 @@ async fn create_entries(
             .to_api_result()?;
     }
 
-    let fields = db::get_fields_metadata(&pool, table_id).await?;
+    let fields = db::get_fields(&pool, table_id).await?;
+    let default_field_ids: HashSet<_> = fields
+        .iter()
+        .filter(|field| field.default_value.is_some())
+        .map(|field| field.field_id)
+        .collect();
+    let fields = fields
+        .into_iter()
+        .map(FieldMetadata::from_field)
+        .collect_vec();
 
-    let entries = entries
+    let entries: Vec<_> = entries
         .into_iter()
-        .map(|cells| convert_cells(cells, &fields))
+        .map(|cells| convert_cells(cells, &fields, user_id, &default_field_ids))
         .try_collect()?;
 
-    let entries = db::create_entries(&pool, table_id, parent_id, fields, entries).await?;
+    let mut tx = pool.begin().await?;
+
+    usage::check_row_limit(tx.as_mut(), &limits, table_id, entries.len() as i64).await?;
+
+    let entries = db::create_entries(tx.as_mut(), table_id, parent_id, fields, entries).await?;
+
+    tx.commit().await?;
 
     Ok(Json(entries))
 }
@@ -82,6 +104,7 @@ async fn create_entries(
 /// Update an entry in a table.
 /// 
 /// Can optionally take a parent entry ID.
+/// The cells of automatic fields are populated, their given values are ignored.
 ///
 /// # Errors
 /// - [`ApiError::Unauthorized`]: User not authenticated
@@ -117,9 +140,18 @@ async fn update_entry(
 
     let fields = db::get_fields_metadata(&pool, table_id).await?;
 
-    let cells = convert_cells(cells, &fields)?;
+    // Without defaults every cell is set
+    let cells = convert_cells(cells, &fields, user_id, &HashSet::new())?
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let mut tx = pool.begin().await?;
 
-    let entry = db::update_entry(&pool, table_id, entry_id, parent_id, fields, cells).await?;
+    let entry =
+        db::update_entry(tx.as_mut(), table_id, entry_id, parent_id, fields, cells).await?;
+
+    tx.commit().await?;
 
     Ok(Json(entry))
 }
@@ -145,22 +177,47 @@ async fn delete_entry(
         .await?
         .to_api_result()?;
 
-    db::delete_entry(&pool, table_id, entry_id).await?;
+    let fields = db::get_fields_metadata(&pool, table_id).await?;
+
+    let mut tx = pool.begin().await?;
+
+    db::delete_entry(tx.as_mut(), table_id, entry_id, &fields).await?;
+
+    tx.commit().await?;
 
     Ok(())
 }
 
 /// Convert raw JSON cell values to a list of cells.
+/// The cells of the user fields are set to the user writing the entry.
+/// The cells of the fields with a default which are not given are not set.
 fn convert_cells(
     mut raw_cells: HashMap<Id, Value>,
     fields: &[FieldMetadata],
-) -> ApiResult<Vec<Cell>> {
+    user_id: Id,
+    default_field_ids: &HashSet<Id>,
+) -> ApiResult<Vec<Option<Cell>>> {
     let (new_cells, mut error_messages): (Vec<_>, Vec<_>) = fields
         .into_iter()
         .map(|field| {
-            let json_value = raw_cells.remove(&field.field_id).unwrap_or(Value::Null);
-            Ok(json_to_cell(json_value, &field.field_kind)
-                .map_err(|message| (field.field_id.to_string(), message))?)
+            let json_value = match raw_cells.remove(&field.field_id) {
+                Some(json_value) => json_value,
+                None if default_field_ids.contains(&field.field_id) => return Ok(None),
+                None => Value::Null,
+            };
+            match field.field_kind.0 {
+                FieldKind::CreatedBy | FieldKind::UpdatedBy => {
+                    Ok(Some(Cell::Integer(user_id.into())))
+                }
+                // Not written, the database populates the cells
+                FieldKind::RowNumber | FieldKind::CreatedAt | FieldKind::UpdatedAt => {
+                    Ok(Some(Cell::Null))
+                }
+                _ => Ok(Some(
+                    json_to_cell(json_value, &field.field_kind)
+                        .map_err(|message| (field.field_id.to_string(), message))?,
+                )),
+            }
         })
         .partition_result();
 
@@ -178,7 +235,7 @@ fn convert_cells(
 }
 
 /// Converts a JSON value to a [`Cell`] and return the correct error message on failure.
-fn json_to_cell(value: Value, field_kind: &FieldKind) -> Result<Cell, &'static str> {
+pub(super) fn json_to_cell(value: Value, field_kind: &FieldKind) -> Result<Cell, &'static str> {
     match (value, field_kind) {
         (
             Value::Null,
@@ -1,23 +1,56 @@
-use super::ApiState;
+use super::{entries::json_to_cell, ApiState};
 use crate::{
+    config::Limits,
     db::{self, AuthSession},
     error::{ApiError, ApiResult, ErrorMessage},
-    model::data::{CreateField, Field, FieldKind, SetFieldOrder, UpdateField},
+    model::{
+        data::{
+            ConversionFailureMode, CreateField, DeleteField, DependentAxesMode, Field,
+            FieldConversionReport, FieldKind, PreviewFieldConversion, SchemaChange, SetFieldOrder,
+            UpdateField,
+        },
+        viz::ChartDependency,
+    },
+    routes::{usage, viz::axes::are_converted_axes_valid},
     Id,
 };
-use anyhow::anyhow;
 use axum::{
-    extract::{Path, State},
-    routing::{patch, post},
+    extract::{Path, Query, State},
+    routing::{delete, get, patch, post},
     Json, Router,
 };
 use itertools::Itertools;
-use std::collections::HashSet;
+use serde_json::Value;
+use sqlx::{Acquire, Postgres};
+use std::{collections::HashSet, mem::discriminant};
 
 const INVALID_RANGE: ErrorMessage = ("range", "Range start bound is greater than end bound");
 const FIELD_ID_NOT_FOUND: &str = "Field ID not found";
 const FIELD_ID_MISSING: &str = "Field ID missing";
 const INVALID_ORDERING: &str = "Ordering number does not follow the sequence";
+const CONVERSION_FAILED: ErrorMessage = (
+    "field_kind",
+    "Some cells cannot be converted to the field kind",
+);
+const FIELD_IN_CHARTS: ErrorMessage = (
+    "drop_backup",
+    "Charts use the field, its backup cannot be dropped",
+);
+const FIELD_IN_FILTERS: ErrorMessage = (
+    "drop_backup",
+    "Filters use the field, its backup cannot be dropped unless they are re-pointed",
+);
+const BACKUP_IN_CHARTS: ErrorMessage = (
+    "field_id",
+    "Charts use a backup of the field, it cannot be dropped",
+);
+const DEFAULT_NOT_ALLOWED: ErrorMessage = (
+    "default_value",
+    "Automatic fields cannot have a default value",
+);
+const AXES_NOT_CONVERTIBLE: &str = "Chart axes are invalid for the field kind";
+const FILTER_NOT_CONVERTIBLE: &str = "Filter is invalid for the field kind";
+const REPOINT_NOT_ALLOWED: ErrorMessage = ("axes", "Axes cannot be re-pointed to a deleted field");
 
 pub fn router() -> Router<ApiState> {
     Router::new().nest(
@@ -25,6 +58,9 @@ pub fn router() -> Router<ApiState> {
         Router::new()
             .route("/", post(create_field).get(get_fields))
             .route("/{field_id}", patch(update_field).delete(delete_field))
+            .route("/{field_id}/conversion", post(preview_field_conversion))
+            .route("/{field_id}/backups", delete(drop_field_backups))
+            .route("/{field_id}/dependencies", get(get_field_dependencies))
             .route("/order", patch(set_field_order)),
     )
 }
@@ -37,12 +73,15 @@ pub fn router() -> Router<ApiState> {
 /// - [ApiError::NotFound]: Table not found
 /// - [ApiError::UnprocessableEntity]:
 ///     - [INVALID_RANGE]
+///     - [DEFAULT_NOT_ALLOWED]
+///     - [usage::FIELD_LIMIT]
+///     - default_value: the errors of an invalid cell
 ///
 async fn create_field(
     AuthSession { user, .. }: AuthSession,
-    State(ApiState { pool, .. }): State<ApiState>,
+    State(ApiState { pool, limits, .. }): State<ApiState>,
     Path(table_id): Path<Id>,
-    Json(mut create_field): Json<CreateField>,
+    Json(create_field): Json<CreateField>,
 ) -> ApiResult<Json<Field>> {
     let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
 
@@ -50,17 +89,24 @@ async fn create_field(
         .await?
         .to_api_result()?;
 
-    validate_field_kind(&mut create_field.field_kind)?;
+    let mut tx = pool.begin().await?;
+
+    let (field, change) = apply_create_field(tx.as_mut(), &limits, table_id, create_field).await?;
+    db::create_schema_changes(tx.as_mut(), table_id, Some(user_id), &[change]).await?;
 
-    let field = db::create_field(&pool, table_id, create_field).await?;
+    tx.commit().await?;
 
     Ok(Json(field))
 }
 
 /// Update a field's meta data in a table.
 ///
-/// Will perform conversion on the cells if the field kind changes and backup the original cells.
-/// Cells that fail to convert are set to null.
+/// Will perform conversion on the cells if the field kind changes and backup the original cells,
+/// unless the backup is dropped.
+/// Cells that fail to convert are set to null, unless the update is aborted.
+/// A kept backup counts against the field limit of the table.
+/// The chart axes on the field are kept on the backup, re-pointed to the converted field or removed.
+/// The filters on the field are kept on the backup, unless they are re-pointed with the axes.
 ///
 /// # Errors
 /// - [ApiError::Unauthorized]: User not authenticated
@@ -68,37 +114,276 @@ async fn create_field(
 /// - [ApiError::NotFound]: Table or field not found
 /// - [ApiError::UnprocessableEntity]:
 ///     - [INVALID_RANGE]
+///     - [DEFAULT_NOT_ALLOWED]
+///     - default_value: the errors of an invalid cell
+///     - [CONVERSION_FAILED]
+///     - [FIELD_IN_CHARTS]
+///     - [FIELD_IN_FILTERS]
+///     - <chart_id>: [AXES_NOT_CONVERTIBLE]
+///     - filters.<filter_id>: [FILTER_NOT_CONVERTIBLE]
+///     - [usage::FIELD_LIMIT]
 ///
 async fn update_field(
     AuthSession { user, .. }: AuthSession,
-    State(ApiState { pool, .. }): State<ApiState>,
+    State(ApiState { pool, limits, .. }): State<ApiState>,
     Path((table_id, field_id)): Path<(Id, Id)>,
-    Json(mut update_field): Json<UpdateField>,
+    Json(update_field): Json<UpdateField>,
 ) -> ApiResult<Json<Field>> {
     let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
 
     db::check_table_relation(&pool, user_id, table_id)
         .await?
         .to_api_result()?;
-    db::check_field_relation(&pool, table_id, field_id)
+
+    let mut tx = pool.begin().await?;
+
+    let (field, changes) =
+        apply_update_field(tx.as_mut(), &limits, table_id, field_id, update_field).await?;
+    db::create_schema_changes(tx.as_mut(), table_id, Some(user_id), &changes).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(field))
+}
+
+/// Move a field to the trash. The cells in its column are kept until it is purged.
+/// The chart axes on the field are kept until it is purged, unless they are removed.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table or field
+/// - [ApiError::NotFound]: Table or field not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [REPOINT_NOT_ALLOWED]
+///
+async fn delete_field(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((table_id, field_id)): Path<(Id, Id)>,
+    Query(DeleteField { axes }): Query<DeleteField>,
+) -> ApiResult<()> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+
+    let mut tx = pool.begin().await?;
+
+    let change = apply_delete_field(tx.as_mut(), table_id, field_id, axes).await?;
+    db::create_schema_changes(tx.as_mut(), table_id, Some(user_id), &[change]).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Create a field, for a single request or a schema migration.
+pub(super) async fn apply_create_field(
+    conn: impl Acquire<'_, Database = Postgres>,
+    limits: &Limits,
+    table_id: Id,
+    mut create_field: CreateField,
+) -> ApiResult<(Field, SchemaChange)> {
+    validate_field_kind(&mut create_field.field_kind)?;
+    validate_default_value(&create_field.field_kind, create_field.default_value.as_ref())?;
+
+    let mut tx = conn.begin().await?;
+
+    usage::check_field_limit(tx.as_mut(), limits, table_id, 1).await?;
+
+    let field = db::create_field(tx.as_mut(), table_id, create_field).await?;
+
+    tx.commit().await?;
+
+    let change = SchemaChange::field_created(&field);
+
+    Ok((field, change))
+}
+
+/// Update a field, for a single request or a schema migration.
+pub(super) async fn apply_update_field(
+    conn: impl Acquire<'_, Database = Postgres>,
+    limits: &Limits,
+    table_id: Id,
+    field_id: Id,
+    mut update_field: UpdateField,
+) -> ApiResult<(Field, Vec<SchemaChange>)> {
+    let mut tx = conn.begin().await?;
+
+    db::check_field_relation(tx.as_mut(), table_id, field_id)
         .await?
         .to_api_result()?;
 
     validate_field_kind(&mut update_field.field_kind)?;
+    validate_default_value(&update_field.field_kind, update_field.default_value.as_ref())?;
 
-    let field = db::update_field(&pool, field_id, update_field).await?;
+    let old_field = db::get_field(tx.as_mut(), field_id).await?;
+    if discriminant(&old_field.field_kind.0) != discriminant(&update_field.field_kind) {
+        // The entries created before the conversion would lose their converted cells
+        db::lock_entries(tx.as_mut(), table_id).await?;
 
-    Ok(Json(field))
+        if update_field.on_failure == ConversionFailureMode::Abort {
+            let report =
+                db::get_field_conversion_report(tx.as_mut(), &old_field, &update_field.field_kind)
+                    .await?;
+            if report.failed > 0 {
+                return Err(ApiError::unprocessable_entity([CONVERSION_FAILED]));
+            }
+        }
+
+        match update_field.axes {
+            // The charts stay on the backup field
+            DependentAxesMode::Keep => {
+                if update_field.drop_backup
+                    && db::is_field_in_charts(tx.as_mut(), &[field_id]).await?
+                {
+                    return Err(ApiError::unprocessable_entity([FIELD_IN_CHARTS]));
+                }
+            }
+            DependentAxesMode::Repoint => {
+                let mut error_messages = Vec::new();
+                for dependency in db::get_field_dependencies(tx.as_mut(), field_id).await? {
+                    let chart = db::get_chart(tx.as_mut(), dependency.chart_id).await?;
+                    let axes = db::get_axis_fields(tx.as_mut(), dependency.chart_id).await?;
+                    if !are_converted_axes_valid(
+                        chart.chart_kind,
+                        &axes,
+                        field_id,
+                        &update_field.field_kind,
+                    ) {
+                        error_messages
+                            .push((dependency.chart_id.to_string(), AXES_NOT_CONVERTIBLE));
+                    }
+                }
+                // The filters are re-pointed with the axes
+                for filter in db::get_field_filters(tx.as_mut(), field_id).await? {
+                    if !filter.filter_kind.applies_to(&update_field.field_kind) {
+                        error_messages.push((
+                            format!("filters.{}", filter.filter_id),
+                            FILTER_NOT_CONVERTIBLE,
+                        ));
+                    }
+                }
+                if !error_messages.is_empty() {
+                    return Err(ApiError::unprocessable_entity(error_messages));
+                }
+            }
+            DependentAxesMode::Remove => (),
+        }
+        if update_field.drop_backup
+            && update_field.axes != DependentAxesMode::Repoint
+            && db::is_field_in_filters(tx.as_mut(), field_id).await?
+        {
+            return Err(ApiError::unprocessable_entity([FIELD_IN_FILTERS]));
+        }
+
+        // The backup keeps the column of the original cells
+        if !update_field.drop_backup {
+            usage::check_field_limit(tx.as_mut(), limits, table_id, 1).await?;
+        }
+    }
+
+    let field = db::update_field(tx.as_mut(), field_id, update_field).await?;
+
+    tx.commit().await?;
+
+    let mut changes = Vec::new();
+    if field.field_kind.0 != old_field.field_kind.0 {
+        changes.push(SchemaChange::FieldKindChanged {
+            field_id: field.field_id,
+            previous_field_id: field_id,
+            old_field_kind: old_field.field_kind.0,
+            new_field_kind: field.field_kind.0.clone(),
+        });
+    }
+    if field.name != old_field.name {
+        changes.push(SchemaChange::FieldRenamed {
+            field_id: field.field_id,
+            old_name: old_field.name,
+            new_name: field.name.clone(),
+        });
+    }
+
+    Ok((field, changes))
 }
 
-/// Delete a field and all cells in its respective column in the table.
+/// Move a field to the trash, for a single request or a schema migration.
+pub(super) async fn apply_delete_field(
+    conn: impl Acquire<'_, Database = Postgres>,
+    table_id: Id,
+    field_id: Id,
+    axes: DependentAxesMode,
+) -> ApiResult<SchemaChange> {
+    if axes == DependentAxesMode::Repoint {
+        return Err(ApiError::unprocessable_entity([REPOINT_NOT_ALLOWED]));
+    }
+
+    let mut tx = conn.begin().await?;
+
+    db::check_field_relation(tx.as_mut(), table_id, field_id)
+        .await?
+        .to_api_result()?;
+
+    let field = db::get_field(tx.as_mut(), field_id).await?;
+
+    if axes == DependentAxesMode::Remove {
+        db::remove_field_axes(tx.as_mut(), field_id).await?;
+    }
+    db::delete_field(tx.as_mut(), field_id).await?;
+
+    tx.commit().await?;
+
+    Ok(SchemaChange::FieldDeleted {
+        field_id,
+        name: field.name,
+    })
+}
+
+/// Report how the cells of a field would be converted to another field kind, without updating the field.
 ///
 /// # Errors
 /// - [ApiError::Unauthorized]: User not authenticated
 /// - [ApiError::Forbidden]: User does not have access to that table or field
 /// - [ApiError::NotFound]: Table or field not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [INVALID_RANGE]
 ///
-async fn delete_field(
+async fn preview_field_conversion(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((table_id, field_id)): Path<(Id, Id)>,
+    Json(PreviewFieldConversion { mut field_kind }): Json<PreviewFieldConversion>,
+) -> ApiResult<Json<FieldConversionReport>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+    db::check_field_relation(&pool, table_id, field_id)
+        .await?
+        .to_api_result()?;
+
+    validate_field_kind(&mut field_kind)?;
+
+    let field = db::get_field(&pool, field_id).await?;
+    let report = db::get_field_conversion_report(&pool, &field, &field_kind).await?;
+
+    Ok(Json(report))
+}
+
+/// Permanently delete the backup fields kept by the conversions of a field,
+/// once the conversion is confirmed. The axes of the charts in the trash
+/// on the backups are removed from these charts.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table or field
+/// - [ApiError::NotFound]: Table or field not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [BACKUP_IN_CHARTS]
+///
+async fn drop_field_backups(
     AuthSession { user, .. }: AuthSession,
     State(ApiState { pool, .. }): State<ApiState>,
     Path((table_id, field_id)): Path<(Id, Id)>,
@@ -112,11 +397,46 @@ async fn delete_field(
         .await?
         .to_api_result()?;
 
-    db::delete_field(&pool, field_id).await?;
+    let mut tx = pool.begin().await?;
+
+    let backup_field_ids = db::get_field_backup_ids(tx.as_mut(), field_id).await?;
+    if db::is_field_in_charts(tx.as_mut(), &backup_field_ids).await? {
+        return Err(ApiError::unprocessable_entity([BACKUP_IN_CHARTS]));
+    }
+
+    db::drop_field_backups(tx.as_mut(), field_id).await?;
+
+    tx.commit().await?;
 
     Ok(())
 }
 
+/// Get the charts with axes on a field, before converting or deleting it.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table or field
+/// - [ApiError::NotFound]: Table or field not found
+///
+async fn get_field_dependencies(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((table_id, field_id)): Path<(Id, Id)>,
+) -> ApiResult<Json<Vec<ChartDependency>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+    db::check_field_relation(&pool, table_id, field_id)
+        .await?
+        .to_api_result()?;
+
+    let dependencies = db::get_field_dependencies(&pool, field_id).await?;
+
+    Ok(Json(dependencies))
+}
+
 /// Get all fields in a table.
 ///
 /// # Errors
@@ -155,7 +475,7 @@ async fn set_field_order(
     AuthSession { user, .. }: AuthSession,
     State(ApiState { pool, .. }): State<ApiState>,
     Path(table_id): Path<Id>,
-    Json(SetFieldOrder(order)): Json<SetFieldOrder>,
+    Json(set_field_order): Json<SetFieldOrder>,
 ) -> ApiResult<()> {
     let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
 
@@ -163,7 +483,25 @@ async fn set_field_order(
         .await?
         .to_api_result()?;
 
-    let mut field_ids: HashSet<_> = db::get_field_ids(&pool, table_id)
+    let mut tx = pool.begin().await?;
+
+    let change = apply_field_order(tx.as_mut(), table_id, set_field_order).await?;
+    db::create_schema_changes(tx.as_mut(), table_id, Some(user_id), &[change]).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Set the order of all fields in a table, for a single request or a schema migration.
+pub(super) async fn apply_field_order(
+    conn: impl Acquire<'_, Database = Postgres>,
+    table_id: Id,
+    SetFieldOrder(order): SetFieldOrder,
+) -> ApiResult<SchemaChange> {
+    let mut tx = conn.begin().await?;
+
+    let mut field_ids: HashSet<_> = db::get_field_ids(tx.as_mut(), table_id)
         .await?
         .into_iter()
         .collect();
@@ -194,9 +532,11 @@ async fn set_field_order(
         return Err(ApiError::unprocessable_entity(error_messages));
     }
 
-    db::set_field_order(&pool, order).await?;
+    db::set_field_order(tx.as_mut(), order.clone()).await?;
 
-    Ok(())
+    tx.commit().await?;
+
+    Ok(SchemaChange::FieldsReordered { order })
 }
 
 /// Validates a request [FieldKind].
@@ -232,22 +572,24 @@ fn validate_field_kind(field_kind: &mut FieldKind) -> ApiResult<()> {
             // date_time_format,
             ..
         } => validate_range(*range_start, *range_end)?,
-        FieldKind::Enumeration {
-            values,
-            default_value,
-            ..
-        } => {
-            if !values.contains_key(&default_value) {
-                return Err(
-                    anyhow!("enumeration field default value does not map to a value").into(),
-                );
-            }
-        }
         _ => {}
     };
     Ok(())
 }
 
+/// Validates the default value of a field, which must be a valid cell of the field kind.
+fn validate_default_value(field_kind: &FieldKind, default_value: Option<&Value>) -> ApiResult<()> {
+    match default_value {
+        None => Ok(()),
+        Some(_) if field_kind.is_auto() => {
+            Err(ApiError::unprocessable_entity([DEFAULT_NOT_ALLOWED]))
+        }
+        Some(default_value) => json_to_cell(default_value.clone(), field_kind)
+            .map(|_| ())
+            .map_err(|message| ApiError::unprocessable_entity([("default_value", message)])),
+    }
+}
+
 /// Validates the range definition of a field.
 fn validate_range<T>(range_start: Option<T>, range_end: Option<T>) -> ApiResult<()>
 where
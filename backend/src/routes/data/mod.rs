@@ -2,9 +2,15 @@
 //!
 //! Users must be authenticated for all requests.
 
+mod collaborators;
+mod comments;
 mod entries;
 mod fields;
+mod schema;
+mod search;
 mod tables;
+mod templates;
+mod webhooks;
 
 use super::ApiState;
 use axum::Router;
@@ -14,4 +20,10 @@ pub fn router() -> Router<ApiState> {
         .merge(tables::router())
         .merge(fields::router())
         .merge(entries::router())
+        .merge(collaborators::router())
+        .merge(comments::router())
+        .merge(schema::router())
+        .merge(search::router())
+        .merge(templates::router())
+        .merge(webhooks::router())
 }
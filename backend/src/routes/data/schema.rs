@@ -0,0 +1,171 @@
+use super::{
+    fields::{apply_create_field, apply_delete_field, apply_field_order, apply_update_field},
+    tables::apply_update_table,
+    ApiState,
+};
+use crate::{
+    db::{self, AuthSession},
+    error::{ApiError, ApiResult, ErrorMessage},
+    model::data::{SchemaChangeEntry, SchemaMigration, SchemaOperation, SetFieldOrder},
+    Id,
+};
+use axum::{
+    extract::{Path, State},
+    routing::{get, post},
+    Json, Router,
+};
+use std::collections::HashMap;
+
+const INVALID_TEMPORARY_ID: ErrorMessage = (
+    "temporary_id",
+    "Temporary ID must be negative and unique in the migration",
+);
+
+pub fn router() -> Router<ApiState> {
+    Router::new().nest(
+        "/tables/{table-id}",
+        Router::new()
+            .route("/schema-changes", get(get_schema_changes))
+            .route("/schema-migrations", post(apply_schema_migration)),
+    )
+}
+
+/// Get the log of the structural changes of a table, oldest first.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table
+/// - [ApiError::NotFound]: Table not found
+///
+async fn get_schema_changes(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(table_id): Path<Id>,
+) -> ApiResult<Json<Vec<SchemaChangeEntry>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+
+    let schema_changes = db::get_schema_changes(&pool, table_id).await?;
+
+    Ok(Json(schema_changes))
+}
+
+/// Apply a batch of field and table changes in order, in one transaction.
+/// Nothing is changed if any operation fails. Returns the logged schema changes.
+///
+/// A created field can have a temporary ID, so the next operations can refer to it.
+/// After a conversion, the temporary ID refers to the converted field.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table or a field
+/// - [ApiError::NotFound]: Table or a field not found
+/// - [ApiError::UnprocessableEntity]:
+///     - <index>.[INVALID_TEMPORARY_ID]
+///     - <index>.<key>: the errors of the operation at the index,
+///       as returned by the endpoint of the operation
+///
+async fn apply_schema_migration(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, limits, .. }): State<ApiState>,
+    Path(table_id): Path<Id>,
+    Json(SchemaMigration(operations)): Json<SchemaMigration>,
+) -> ApiResult<Json<Vec<SchemaChangeEntry>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+
+    let mut tx = pool.begin().await?;
+
+    let mut changes = Vec::new();
+    // Temporary field IDs mapped to the IDs of the created fields
+    let mut field_ids: HashMap<Id, Id> = HashMap::new();
+    let resolve = |field_ids: &HashMap<Id, Id>, field_id: Id| {
+        field_ids.get(&field_id).copied().unwrap_or(field_id)
+    };
+    for (index, operation) in operations.into_iter().enumerate() {
+        match operation {
+            SchemaOperation::CreateField {
+                temporary_id,
+                field,
+            } => {
+                if temporary_id.is_some_and(|temporary_id| {
+                    temporary_id >= 0 || field_ids.contains_key(&temporary_id)
+                }) {
+                    return Err(at_operation(index)(ApiError::unprocessable_entity([
+                        INVALID_TEMPORARY_ID,
+                    ])));
+                }
+                let (field, change) = apply_create_field(tx.as_mut(), &limits, table_id, field)
+                    .await
+                    .map_err(at_operation(index))?;
+                if let Some(temporary_id) = temporary_id {
+                    field_ids.insert(temporary_id, field.field_id);
+                }
+                changes.push(change);
+            }
+            SchemaOperation::UpdateField { field_id, field } => {
+                let field_id = resolve(&field_ids, field_id);
+                let (field, field_changes) =
+                    apply_update_field(tx.as_mut(), &limits, table_id, field_id, field)
+                        .await
+                        .map_err(at_operation(index))?;
+                // The previous field is kept as the backup of the converted field
+                for mapped_id in field_ids.values_mut() {
+                    if *mapped_id == field_id {
+                        *mapped_id = field.field_id;
+                    }
+                }
+                changes.extend(field_changes);
+            }
+            SchemaOperation::DeleteField { field_id, axes } => {
+                let field_id = resolve(&field_ids, field_id);
+                let change = apply_delete_field(tx.as_mut(), table_id, field_id, axes)
+                    .await
+                    .map_err(at_operation(index))?;
+                changes.push(change);
+            }
+            SchemaOperation::SetFieldOrder(SetFieldOrder(order)) => {
+                let order = order
+                    .into_iter()
+                    .map(|(field_id, ordering)| (resolve(&field_ids, field_id), ordering))
+                    .collect();
+                let change = apply_field_order(tx.as_mut(), table_id, SetFieldOrder(order))
+                    .await
+                    .map_err(at_operation(index))?;
+                changes.push(change);
+            }
+            SchemaOperation::UpdateTable(update_table) => {
+                let (_, change) = apply_update_table(tx.as_mut(), table_id, update_table)
+                    .await
+                    .map_err(at_operation(index))?;
+                changes.extend(change);
+            }
+        }
+    }
+
+    let schema_changes =
+        db::create_schema_changes(tx.as_mut(), table_id, Some(user_id), &changes).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(schema_changes))
+}
+
+/// Prefix the keys of the validation errors of an operation with its index in the migration.
+fn at_operation(index: usize) -> impl FnOnce(ApiError) -> ApiError {
+    move |error| match error {
+        ApiError::UnprocessableEntity { errors } => ApiError::UnprocessableEntity {
+            errors: errors
+                .into_iter()
+                .map(|(key, messages)| (format!("{index}.{key}").into(), messages))
+                .collect(),
+        },
+        error => error,
+    }
+}
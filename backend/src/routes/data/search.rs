@@ -0,0 +1,49 @@
+use super::ApiState;
+use crate::{
+    db::{self, AuthSession},
+    error::{ApiError, ApiResult, ErrorMessage},
+    model::data::{SearchQuery, TableSearchResult},
+};
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+
+const QUERY_EMPTY: ErrorMessage = ("query", "Search query is empty");
+const INVALID_LIMIT: ErrorMessage = ("limit", "Limit must be between 1 and 100");
+
+const DEFAULT_LIMIT: i64 = 20;
+
+pub fn router() -> Router<ApiState> {
+    Router::new().route("/search", get(search_entries))
+}
+
+/// Search the entries of every table of the user in their Text, WebLink and Enumeration fields.
+/// Results are grouped by table, ranked and highlighted.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::UnprocessableEntity]:
+///     - [QUERY_EMPTY]
+///     - [INVALID_LIMIT]
+///
+async fn search_entries(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Query(SearchQuery { query, limit }): Query<SearchQuery>,
+) -> ApiResult<Json<Vec<TableSearchResult>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    if query.trim().is_empty() {
+        return Err(ApiError::unprocessable_entity([QUERY_EMPTY]));
+    }
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=100).contains(&limit) {
+        return Err(ApiError::unprocessable_entity([INVALID_LIMIT]));
+    }
+
+    let results = db::search_entries(&pool, user_id, &query, limit).await?;
+
+    Ok(Json(results))
+}
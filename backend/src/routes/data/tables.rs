@@ -1,22 +1,23 @@
 use super::ApiState;
 use crate::{
-    db::{self, AuthSession},
+    config::Limits,
+    db::{self, AuthSession, Relation},
     error::{ApiError, ApiResult, IntoAnyhow},
-    io,
-    model::data::{CreateTable, CreateTableData, FieldMetadata, Table, TableData, UpdateTable},
+    model::{
+        data::{CopyTable, CreateTable, SchemaChange, Table, TableData, UpdateTable},
+        jobs::{Job, JobKind},
+    },
+    routes::{event_stream, usage},
     Id,
 };
 use axum::{
-    extract::{Multipart, Path, State},
+    extract::{multipart::Field, DefaultBodyLimit, Multipart, Path, State},
+    response::sse::{self, Sse},
     routing::{get, patch, post},
     Json, Router,
 };
-use itertools::Itertools;
-use std::io::Cursor;
-use umya_spreadsheet::{
-    reader::{self, xlsx},
-    writer,
-};
+use futures::Stream;
+use sqlx::{Acquire, Postgres};
 
 pub fn router() -> Router<ApiState> {
     Router::new().nest(
@@ -24,11 +25,22 @@ pub fn router() -> Router<ApiState> {
         Router::new()
             .route("/", post(create_table).get(get_tables))
             .route("/{table-id}", patch(update_table).delete(delete_table))
+            .route("/{table-id}/copy", post(copy_table))
             .route("/{table-id}/children", get(get_table_children))
             .route("/{table-id}/data", get(get_table_data))
-            .route("/excel", post(import_table_from_excel))
-            .route("/{table-id}/excel", post(export_table_to_excel))
-            .route("/csv", post(import_table_from_csv))
+            .route("/{table-id}/events", get(get_table_events))
+            .route(
+                "/excel",
+                post(import_table_from_excel).layer(DefaultBodyLimit::disable()),
+            )
+            .route(
+                "/{table-id}/excel",
+                post(export_table_to_excel).layer(DefaultBodyLimit::disable()),
+            )
+            .route(
+                "/csv",
+                post(import_table_from_csv).layer(DefaultBodyLimit::disable()),
+            )
             .route("/{table-id}/csv", post(export_table_to_csv)),
     )
 }
@@ -37,15 +49,22 @@ pub fn router() -> Router<ApiState> {
 ///
 /// # Errors
 /// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::UnprocessableEntity]:
+///     - [usage::TABLE_LIMIT]
 ///
 async fn create_table(
     AuthSession { user, .. }: AuthSession,
-    State(ApiState { pool, .. }): State<ApiState>,
+    State(ApiState { pool, limits, .. }): State<ApiState>,
     Json(create_table): Json<CreateTable>,
 ) -> ApiResult<Json<Table>> {
     let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
 
-    let table = db::create_table(&pool, user_id, create_table).await?;
+    let mut tx = pool.begin().await?;
+
+    usage::check_table_limit(tx.as_mut(), &limits, user_id, 1).await?;
+    let table = db::create_table(tx.as_mut(), user_id, create_table).await?;
+
+    tx.commit().await?;
 
     Ok(Json(table))
 }
@@ -69,12 +88,39 @@ async fn update_table(
         .await?
         .to_api_result()?;
 
-    let table = db::update_table(&pool, table_id, update_table).await?;
+    let mut tx = pool.begin().await?;
+
+    let (table, change) = apply_update_table(tx.as_mut(), table_id, update_table).await?;
+    db::create_schema_changes(tx.as_mut(), table_id, Some(user_id), change.as_slice()).await?;
+
+    tx.commit().await?;
 
     Ok(Json(table))
 }
 
-/// Delete a table, including all fields and entries.
+/// Update a table's meta data, for a single request or a schema migration.
+/// Only a rename is a schema change.
+pub(super) async fn apply_update_table(
+    conn: impl Acquire<'_, Database = Postgres>,
+    table_id: Id,
+    update_table: UpdateTable,
+) -> ApiResult<(Table, Option<SchemaChange>)> {
+    let mut tx = conn.begin().await?;
+
+    let old_table = db::get_table(tx.as_mut(), table_id).await?;
+    let table = db::update_table(tx.as_mut(), table_id, update_table).await?;
+
+    tx.commit().await?;
+
+    let change = (table.name != old_table.name).then(|| SchemaChange::TableRenamed {
+        old_name: old_table.name,
+        new_name: table.name.clone(),
+    });
+
+    Ok((table, change))
+}
+
+/// Move a table and its child tables to the trash, with all fields and entries.
 ///
 /// # Errors
 /// - [ApiError::Unauthorized]: User not authenticated
@@ -92,11 +138,47 @@ async fn delete_table(
         .await?
         .to_api_result()?;
 
-    db::delete_table(&pool, table_id).await?;
+    db::delete_table(&pool, table_id, user_id).await?;
 
     Ok(())
 }
 
+/// Copy the fields of a table and its children, and optionally their entries.
+/// The copies are renamed if their names are taken.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table
+/// - [ApiError::NotFound]: Table not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [usage::TABLE_LIMIT]
+///
+async fn copy_table(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, limits, .. }): State<ApiState>,
+    Path(table_id): Path<Id>,
+    Json(CopyTable {
+        name,
+        include_entries,
+    }): Json<CopyTable>,
+) -> ApiResult<Json<Table>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+
+    let mut tx = pool.begin().await?;
+
+    let tables = db::get_table_tree_count(tx.as_mut(), table_id).await?;
+    usage::check_table_limit(tx.as_mut(), &limits, user_id, tables).await?;
+    let table = db::copy_table(tx.as_mut(), table_id, name, include_entries).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(table))
+}
+
 /// Get all tables belonging to the user.
 ///
 /// # Errors
@@ -139,6 +221,7 @@ async fn get_table_children(
 /// Get all the meta data, fields, and entries of a table.
 ///
 /// Used for displaying the table in the user interface.
+/// The collaborators of the table can also get it.
 ///
 /// # Errors
 /// - [ApiError::Unauthorized]: User not authenticated
@@ -154,75 +237,91 @@ async fn get_table_data(
 
     db::check_table_relation(&pool, user_id, table_id)
         .await?
-        .to_api_result()?;
+        .to_shared_api_result()?;
 
     let data_table = db::get_table_data(&pool, table_id).await?;
 
     Ok(Json(data_table))
 }
 
-/// Takes an Excel file and attempts to convert it into an table.
+/// Subscribe to the changes of a table with Server-Sent Events.
+///
+/// Sends the entry and field events of the table, and the events of the table itself.
+/// Changes made from any server instance are received.
+/// The collaborators of the table can also subscribe.
+/// The stream ends when the table is deleted or the user loses access to it.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table
+/// - [ApiError::NotFound]: Table not found
+///
+async fn get_table_events(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, events, .. }): State<ApiState>,
+    Path(table_id): Path<Id>,
+) -> ApiResult<Sse<impl Stream<Item = Result<sse::Event, axum::Error>>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_shared_api_result()?;
+
+    let has_access = move || {
+        let pool = pool.clone();
+        async move {
+            matches!(
+                db::check_table_relation(&pool, user_id, table_id).await,
+                Ok(Relation::Owned | Relation::Shared)
+            )
+        }
+    };
+
+    Ok(event_stream(
+        events.subscribe(),
+        move |event| event.table_id() == Some(table_id),
+        has_access,
+    ))
+}
+
+/// Takes an Excel file and queues a job converting it into tables.
+///
+/// The tables are created when the job is completed, see the routes of the jobs.
+/// The job fails if the tables exceed the resource limits.
 ///
 /// # Errors
 /// - [ApiError::Unauthorized]: User not authenticated
 /// - [ApiError::BadRequest]: Multipart has zero fields
+/// - [ApiError::PayloadTooLarge]: File is larger than the maximum import size
+/// - [ApiError::UnprocessableEntity]:
+///     - [usage::TABLE_LIMIT]
 ///
 async fn import_table_from_excel(
     AuthSession { user, .. }: AuthSession,
-    State(ApiState { pool, .. }): State<ApiState>,
+    State(ApiState { pool, limits, .. }): State<ApiState>,
     mut multipart: Multipart,
-) -> ApiResult<Json<Vec<TableData>>> {
+) -> ApiResult<Json<Job>> {
     let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
 
-    let Some(field) = multipart.next_field().await.unwrap() else {
+    // Only rejects the imports early, the job checks the limit again when it reserves the tables
+    usage::check_table_limit(&pool, &limits, user_id, 1).await?;
+
+    let Some(field) = multipart.next_field().await.into_anyhow()? else {
         return Err(ApiError::BadRequest);
     };
 
-    let data = field.bytes().await.into_anyhow()?;
-    let spreadsheet = xlsx::read_reader(Cursor::new(data), true).into_anyhow()?;
-
-    let create_tables = io::import_table_from_excel(spreadsheet);
+    let data = read_import_file(field, &limits).await?;
 
-    let mut tx = pool.begin().await?;
-
-    let mut tables = Vec::new();
-
-    for CreateTableData {
-        table,
-        fields,
-        entries,
-    } in create_tables
-    {
-        let table = db::create_table(tx.as_mut(), user_id, table).await?;
-        let fields = db::create_fields(tx.as_mut(), table.table_id, fields).await?;
-        let entries = db::create_entries(
-            tx.as_mut(),
-            table.table_id,
-            None,
-            fields
-                .iter()
-                .map(|field| FieldMetadata::from_field(field.clone()))
-                .collect_vec(),
-            entries,
-        )
-        .await?;
-        tables.push(TableData {
-            table,
-            fields,
-            entries,
-            children: Vec::new(),
-        })
-    }
-
-    tx.commit().await?;
+    let job = db::create_job(&pool, user_id, &JobKind::ImportExcel, Some(data)).await?;
 
-    Ok(Json(tables))
+    Ok(Json(job))
 }
 
-/// Converts the specified table into an Excel file.
+/// Queues a job converting the specified table into an Excel file.
 ///
 /// Can optionally take an input Excel file in which to add the table to.
 /// Otherwise, provide an empty multipart field.
+/// The file is downloaded from the result of the job.
 ///
 /// # TODO
 /// Add support for child tables.
@@ -232,90 +331,69 @@ async fn import_table_from_excel(
 /// - [ApiError::Forbidden]: User does not have access to that table
 /// - [ApiError::NotFound]: Table not found
 /// - [ApiError::BadRequest]: Multipart has zero fields
+/// - [ApiError::PayloadTooLarge]: File is larger than the maximum import size
 ///
 async fn export_table_to_excel(
     AuthSession { user, .. }: AuthSession,
-    State(ApiState { pool, .. }): State<ApiState>,
+    State(ApiState { pool, limits, .. }): State<ApiState>,
     Path(table_id): Path<Id>,
     mut multipart: Multipart,
-) -> ApiResult<Vec<u8>> {
+) -> ApiResult<Json<Job>> {
     let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
 
     db::check_table_relation(&pool, user_id, table_id)
         .await?
         .to_api_result()?;
 
-    let mut spreadsheet = if let Some(field) = multipart.next_field().await.into_anyhow()? {
-        let data = field.bytes().await.into_anyhow()?;
-        if data.is_empty() {
-            umya_spreadsheet::new_file_empty_worksheet()
-        } else {
-            reader::xlsx::read_reader(Cursor::new(data), true).into_anyhow()?
-        }
-    } else {
+    let Some(field) = multipart.next_field().await.into_anyhow()? else {
         return Err(ApiError::BadRequest);
     };
 
-    let mut buffer = Vec::new();
-    let data = Cursor::new(&mut buffer);
+    let data = read_import_file(field, &limits).await?;
+    let input = if data.is_empty() { None } else { Some(data) };
 
-    io::export_table_to_excel(&mut spreadsheet, db::get_table_data(&pool, table_id).await?);
+    let job = db::create_job(&pool, user_id, &JobKind::ExportExcel { table_id }, input).await?;
 
-    writer::xlsx::write_writer(&spreadsheet, data).into_anyhow()?;
-
-    Ok(buffer)
+    Ok(Json(job))
 }
 
-/// Takes an CSV file and attempts to convert it into an table.
+/// Takes an CSV file and queues a job converting it into an table.
+///
+/// The table is created when the job is completed, see the routes of the jobs.
+/// The job fails if the table exceeds the resource limits.
 ///
 /// # Errors
 /// - [ApiError::Unauthorized]: User not authenticated
 /// - [ApiError::BadRequest]: Multipart has zero fields
+/// - [ApiError::PayloadTooLarge]: File is larger than the maximum import size
+/// - [ApiError::UnprocessableEntity]:
+///     - [usage::TABLE_LIMIT]
 ///
 async fn import_table_from_csv(
     AuthSession { user, .. }: AuthSession,
-    State(ApiState { pool, .. }): State<ApiState>,
+    State(ApiState { pool, limits, .. }): State<ApiState>,
     mut multipart: Multipart,
-) -> ApiResult<Json<TableData>> {
+) -> ApiResult<Json<Job>> {
     let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
 
-    let Some(field) = multipart.next_field().await.unwrap() else {
+    // Only rejects the imports early, the job checks the limit again when it reserves the tables
+    usage::check_table_limit(&pool, &limits, user_id, 1).await?;
+
+    let Some(field) = multipart.next_field().await.into_anyhow()? else {
         return Err(ApiError::BadRequest);
     };
 
     let name = field.file_name().unwrap_or("CSV Import").to_string();
-    let data = field.bytes().await.into_anyhow()?;
-    let csv_reader = csv::Reader::from_reader(Cursor::new(data));
-
-    let create_table = io::import_table_from_csv(csv_reader, &name).into_anyhow()?;
-
-    let mut tx = pool.begin().await?;
+    let data = read_import_file(field, &limits).await?;
 
-    let table = db::create_table(tx.as_mut(), user_id, create_table.table).await?;
-    let fields = db::create_fields(tx.as_mut(), table.table_id, create_table.fields).await?;
-    let entries = db::create_entries(
-        tx.as_mut(),
-        table.table_id,
-        None,
-        fields
-            .iter()
-            .map(|field| FieldMetadata::from_field(field.clone()))
-            .collect_vec(),
-        create_table.entries,
-    )
-    .await?;
-
-    tx.commit().await?;
+    let job = db::create_job(&pool, user_id, &JobKind::ImportCsv { name }, Some(data)).await?;
 
-    Ok(Json(TableData {
-        table,
-        fields,
-        entries,
-        children: Vec::new(),
-    }))
+    Ok(Json(job))
 }
 
-/// Converts the specified table into an CSV file.
+/// Queues a job converting the specified table into an CSV file.
+///
+/// The file is downloaded from the result of the job.
 ///
 /// # TODO
 /// Add support for child tables.
@@ -329,17 +407,32 @@ async fn export_table_to_csv(
     AuthSession { user, .. }: AuthSession,
     State(ApiState { pool, .. }): State<ApiState>,
     Path(table_id): Path<Id>,
-) -> ApiResult<Vec<u8>> {
+) -> ApiResult<Json<Job>> {
     let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
     db::check_table_relation(&pool, user_id, table_id)
         .await?
         .to_api_result()?;
 
-    let mut buffer = Vec::new();
-    let csv_writer = csv::Writer::from_writer(Cursor::new(&mut buffer));
+    let job = db::create_job(&pool, user_id, &JobKind::ExportCsv { table_id }, None).await?;
 
-    io::export_table_to_csv(csv_writer, db::get_table_data(&pool, table_id).await?)
-        .into_anyhow()?;
+    Ok(Json(job))
+}
 
-    Ok(buffer)
+/// Read an uploaded file, which can not be larger than the maximum import size.
+///
+/// The import routes have no request body limit, the file is read in chunks
+/// to stop reading as soon as it is too large.
+///
+/// # Errors
+/// - [ApiError::PayloadTooLarge]: File is larger than the maximum import size
+///
+async fn read_import_file(mut field: Field<'_>, limits: &Limits) -> ApiResult<Vec<u8>> {
+    let mut data = Vec::new();
+    while let Some(chunk) = field.chunk().await.into_anyhow()? {
+        if data.len() + chunk.len() > limits.max_import_bytes {
+            return Err(ApiError::PayloadTooLarge);
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
 }
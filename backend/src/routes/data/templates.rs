@@ -0,0 +1,211 @@
+use super::ApiState;
+use crate::{
+    db::{self, AuthSession, Relation},
+    error::{ApiError, ApiResult},
+    model::{
+        data::{
+            CreateTableTemplate, InstantiateTableTemplate, PublishTableTemplate, TableTemplate,
+            TemplateInstance, UpdateTableTemplate,
+        },
+        users::{User, UserRole},
+    },
+    routes::usage,
+    Id,
+};
+use axum::{
+    extract::{Path, State},
+    routing::{get, patch, post},
+    Json, Router,
+};
+
+pub fn router() -> Router<ApiState> {
+    Router::new()
+        .route("/tables/{table-id}/template", post(create_table_template))
+        .nest(
+            "/templates",
+            Router::new()
+                .route("/", get(get_table_templates))
+                .route(
+                    "/{template-id}",
+                    patch(update_table_template).delete(delete_table_template),
+                )
+                .route("/{template-id}/publish", patch(publish_table_template))
+                .route("/{template-id}/tables", post(instantiate_table_template)),
+        )
+}
+
+/// Save a table with its fields and child tables as a template.
+/// The charts on the tables are saved if requested.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table
+/// - [ApiError::NotFound]: Table not found
+///
+async fn create_table_template(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(table_id): Path<Id>,
+    Json(CreateTableTemplate {
+        name,
+        description,
+        include_charts,
+    }): Json<CreateTableTemplate>,
+) -> ApiResult<Json<TableTemplate>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+
+    let content = db::get_template_table(&pool, table_id, include_charts).await?;
+    let template = db::create_table_template(&pool, user_id, name, description, content).await?;
+
+    Ok(Json(template))
+}
+
+/// Update a template's meta data.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that template
+/// - [ApiError::NotFound]: Template not found
+///
+async fn update_table_template(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(template_id): Path<Id>,
+    Json(UpdateTableTemplate { name, description }): Json<UpdateTableTemplate>,
+) -> ApiResult<Json<TableTemplate>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_template_relation(&pool, user_id, template_id)
+        .await?
+        .to_api_result()?;
+
+    let template = db::update_table_template(&pool, template_id, name, description).await?;
+
+    Ok(Json(template))
+}
+
+/// Delete a template. Tables created from the template are not affected.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that template
+/// - [ApiError::NotFound]: Template not found
+///
+async fn delete_table_template(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(template_id): Path<Id>,
+) -> ApiResult<()> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_template_relation(&pool, user_id, template_id)
+        .await?
+        .to_api_result()?;
+
+    db::delete_table_template(&pool, template_id).await?;
+
+    Ok(())
+}
+
+/// Publish a template to the gallery of every user, or unpublish it.
+/// Request user must have the role [UserRole::Admin].
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User is not [UserRole::Admin]
+/// - [ApiError::NotFound]: Template not found
+///
+async fn publish_table_template(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(template_id): Path<Id>,
+    Json(PublishTableTemplate { is_published }): Json<PublishTableTemplate>,
+) -> ApiResult<Json<TableTemplate>> {
+    let user_id = match user {
+        Some(User {
+            user_id,
+            role: UserRole::Admin,
+            ..
+        }) => Ok(user_id),
+        Some(_) => Err(ApiError::Forbidden),
+        None => Err(ApiError::Unauthorized),
+    }?;
+
+    // Admins can publish the templates of any user
+    if let Relation::Absent =
+        db::check_table_template_relation(&pool, user_id, template_id).await?
+    {
+        return Err(ApiError::NotFound);
+    }
+
+    let template = db::publish_table_template(&pool, template_id, is_published).await?;
+
+    Ok(Json(template))
+}
+
+/// Get the templates of the user and the published templates of the gallery.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+///
+async fn get_table_templates(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+) -> ApiResult<Json<Vec<TableTemplate>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    let templates = db::get_table_templates(&pool, user_id).await?;
+
+    Ok(Json(templates))
+}
+
+/// Create the tables of a template for the user.
+/// The charts of the template are created in a new dashboard.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: Template is not owned by the user and not published
+/// - [ApiError::NotFound]: Template not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [usage::TABLE_LIMIT]
+///     - [usage::FIELD_LIMIT]
+///
+async fn instantiate_table_template(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, limits, .. }): State<ApiState>,
+    Path(template_id): Path<Id>,
+    Json(InstantiateTableTemplate { name }): Json<InstantiateTableTemplate>,
+) -> ApiResult<Json<TemplateInstance>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    let relation = db::check_table_template_relation(&pool, user_id, template_id).await?;
+    if let Relation::Absent = relation {
+        return Err(ApiError::NotFound);
+    }
+
+    let template = db::get_table_template(&pool, template_id).await?;
+    if let Relation::NotOwned = relation {
+        if !template.is_published {
+            return Err(ApiError::Forbidden);
+        }
+    }
+
+    if template.content.max_field_count() > limits.max_fields_per_table {
+        return Err(ApiError::unprocessable_entity([usage::FIELD_LIMIT]));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let tables = template.content.table_count();
+    usage::check_table_limit(tx.as_mut(), &limits, user_id, tables).await?;
+    let instance =
+        db::instantiate_table_template(tx.as_mut(), user_id, template.content.0, name).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(instance))
+}
@@ -0,0 +1,249 @@
+use super::ApiState;
+use crate::{
+    db::{self, AuthSession},
+    error::{ApiError, ApiResult, ErrorMessage},
+    model::data::{
+        CreateWebhook, UpdateWebhook, Webhook, WebhookDelivery, WebhookEvent, WebhookSecret,
+    },
+    tasks::{is_public_address, parse_ip},
+    Id,
+};
+use axum::{
+    extract::{Path, State},
+    routing::{get, patch, post},
+    Json, Router,
+};
+use rand::{distr::Alphanumeric, Rng};
+
+const INVALID_URL: ErrorMessage = ("url", "URL must be an absolute HTTP or HTTPS URL");
+const URL_NOT_PUBLIC: ErrorMessage = ("url", "URL must not be a local or private address");
+const EVENTS_MISSING: ErrorMessage = ("events", "At least one event is required");
+
+/// Length of the generated webhook secrets.
+const SECRET_LENGTH: usize = 32;
+/// Number of deliveries returned in the delivery log.
+const DELIVERY_LOG_LIMIT: i64 = 100;
+
+pub fn router() -> Router<ApiState> {
+    Router::new().nest(
+        "/tables/{table-id}/webhooks",
+        Router::new()
+            .route("/", post(create_webhook).get(get_webhooks))
+            .route(
+                "/{webhook-id}",
+                patch(update_webhook).delete(delete_webhook),
+            )
+            .route("/{webhook-id}/secret", post(rotate_webhook_secret))
+            .route("/{webhook-id}/deliveries", get(get_webhook_deliveries)),
+    )
+}
+
+/// Register a webhook on a table. A secret is generated to sign the payloads,
+/// it is only returned in this response.
+///
+/// Every delivery is a JSON POST request signed with HMAC-SHA256
+/// in the `X-Chronicle-Signature` header.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table
+/// - [ApiError::NotFound]: Table not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [INVALID_URL]
+///     - [URL_NOT_PUBLIC]
+///     - [EVENTS_MISSING]
+///
+async fn create_webhook(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(table_id): Path<Id>,
+    Json(create_webhook): Json<CreateWebhook>,
+) -> ApiResult<Json<WebhookSecret>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+
+    validate_webhook(&create_webhook.url, &create_webhook.events)?;
+
+    let secret = generate_secret();
+
+    let webhook = db::create_webhook(&pool, table_id, secret.clone(), create_webhook).await?;
+
+    Ok(Json(WebhookSecret { webhook, secret }))
+}
+
+/// Replace the secret of a webhook with a new generated secret,
+/// which is only returned in this response.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table or webhook
+/// - [ApiError::NotFound]: Table or webhook not found
+///
+async fn rotate_webhook_secret(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((table_id, webhook_id)): Path<(Id, Id)>,
+) -> ApiResult<Json<WebhookSecret>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+    db::check_webhook_relation(&pool, table_id, webhook_id)
+        .await?
+        .to_api_result()?;
+
+    let secret = generate_secret();
+
+    let webhook = db::set_webhook_secret(&pool, webhook_id, &secret).await?;
+
+    Ok(Json(WebhookSecret { webhook, secret }))
+}
+
+/// Update a webhook's URL, events and activation.
+/// The pending deliveries of an inactive webhook are sent when it is activated again.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table or webhook
+/// - [ApiError::NotFound]: Table or webhook not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [INVALID_URL]
+///     - [URL_NOT_PUBLIC]
+///     - [EVENTS_MISSING]
+///
+async fn update_webhook(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((table_id, webhook_id)): Path<(Id, Id)>,
+    Json(update_webhook): Json<UpdateWebhook>,
+) -> ApiResult<Json<Webhook>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+    db::check_webhook_relation(&pool, table_id, webhook_id)
+        .await?
+        .to_api_result()?;
+
+    validate_webhook(&update_webhook.url, &update_webhook.events)?;
+
+    let webhook = db::update_webhook(&pool, webhook_id, update_webhook).await?;
+
+    Ok(Json(webhook))
+}
+
+/// Delete a webhook and its delivery log.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table or webhook
+/// - [ApiError::NotFound]: Table or webhook not found
+///
+async fn delete_webhook(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((table_id, webhook_id)): Path<(Id, Id)>,
+) -> ApiResult<()> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+    db::check_webhook_relation(&pool, table_id, webhook_id)
+        .await?
+        .to_api_result()?;
+
+    db::delete_webhook(&pool, webhook_id).await?;
+
+    Ok(())
+}
+
+/// Get all webhooks of a table.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table
+/// - [ApiError::NotFound]: Table not found
+///
+async fn get_webhooks(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(table_id): Path<Id>,
+) -> ApiResult<Json<Vec<Webhook>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+
+    let webhooks = db::get_webhooks(&pool, table_id).await?;
+
+    Ok(Json(webhooks))
+}
+
+/// Get the most recent deliveries of a webhook, including pending and failed deliveries.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that table or webhook
+/// - [ApiError::NotFound]: Table or webhook not found
+///
+async fn get_webhook_deliveries(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((table_id, webhook_id)): Path<(Id, Id)>,
+) -> ApiResult<Json<Vec<WebhookDelivery>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_table_relation(&pool, user_id, table_id)
+        .await?
+        .to_api_result()?;
+    db::check_webhook_relation(&pool, table_id, webhook_id)
+        .await?
+        .to_api_result()?;
+
+    let deliveries = db::get_webhook_deliveries(&pool, webhook_id, DELIVERY_LOG_LIMIT).await?;
+
+    Ok(Json(deliveries))
+}
+
+/// Generate a random secret to sign the payloads of a webhook.
+fn generate_secret() -> String {
+    rand::rng()
+        .sample_iter(Alphanumeric)
+        .take(SECRET_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Validates the URL and events of a webhook request.
+///
+/// The hosts resolving to local or private addresses are also rejected when delivering.
+fn validate_webhook(url: &str, events: &[WebhookEvent]) -> ApiResult<()> {
+    let mut error_messages = Vec::new();
+
+    match reqwest::Url::parse(url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {
+            let host = url.host_str().unwrap_or_default();
+            let is_local = host == "localhost" || host.ends_with(".localhost");
+            if is_local || parse_ip(host).is_some_and(|ip| !is_public_address(ip)) {
+                error_messages.push(URL_NOT_PUBLIC);
+            }
+        }
+        _ => error_messages.push(INVALID_URL),
+    }
+    if events.is_empty() {
+        error_messages.push(EVENTS_MISSING);
+    }
+
+    if error_messages.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::unprocessable_entity(error_messages))
+    }
+}
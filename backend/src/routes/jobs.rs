@@ -0,0 +1,156 @@
+//! Route handlers for the background jobs of the user.
+//!
+//! Users must be authenticated for all requests.
+
+use super::ApiState;
+use crate::{
+    db::{self, AuthSession},
+    error::{ApiError, ApiResult, ErrorMessage},
+    model::jobs::{Job, JobOutput, JobStatus},
+    Id,
+};
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+
+const JOB_FINISHED: ErrorMessage = ("job_id", "Job is already finished");
+const NO_RESULT: ErrorMessage = ("job_id", "Job has no result to download");
+
+/// Number of jobs returned in the job log.
+const JOB_LOG_LIMIT: i64 = 100;
+
+pub fn router() -> Router<ApiState> {
+    Router::new().nest(
+        "/jobs",
+        Router::new()
+            .route("/", get(get_jobs))
+            .route("/{job-id}", get(get_job))
+            .route("/{job-id}/cancel", post(cancel_job))
+            .route("/{job-id}/result", get(get_job_result)),
+    )
+}
+
+/// Get the most recent jobs of the user.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+///
+async fn get_jobs(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+) -> ApiResult<Json<Vec<Job>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    let jobs = db::get_jobs(&pool, user_id, JOB_LOG_LIMIT).await?;
+
+    Ok(Json(jobs))
+}
+
+/// Get the status and progress of a job.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this job
+/// - [ApiError::NotFound]: Job not found
+///
+async fn get_job(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(job_id): Path<Id>,
+) -> ApiResult<Json<Job>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_job_relation(&pool, user_id, job_id)
+        .await?
+        .to_api_result()?;
+
+    let job = db::get_job(&pool, job_id).await?;
+
+    Ok(Json(job))
+}
+
+/// Cancel a pending or running job.
+/// The changes of a running import are rolled back.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this job
+/// - [ApiError::NotFound]: Job not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [JOB_FINISHED]
+///
+async fn cancel_job(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(job_id): Path<Id>,
+) -> ApiResult<Json<Job>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_job_relation(&pool, user_id, job_id)
+        .await?
+        .to_api_result()?;
+
+    if !db::get_job(&pool, job_id).await?.is_active() {
+        return Err(ApiError::unprocessable_entity([JOB_FINISHED]));
+    }
+
+    let job = db::cancel_job(&pool, job_id).await?;
+
+    Ok(Json(job))
+}
+
+/// Download the file exported by a completed job.
+/// The file is dropped a week after the job is finished.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this job
+/// - [ApiError::NotFound]: Job not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [NO_RESULT]
+///
+async fn get_job_result(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(job_id): Path<Id>,
+) -> ApiResult<impl IntoResponse> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_job_relation(&pool, user_id, job_id)
+        .await?
+        .to_api_result()?;
+
+    let job = db::get_job(&pool, job_id).await?;
+    let (
+        JobStatus::Completed,
+        Some(JobOutput::Exported {
+            file_name,
+            content_type,
+        }),
+    ) = (job.status, job.output.map(|output| output.0))
+    else {
+        return Err(ApiError::unprocessable_entity([NO_RESULT]));
+    };
+
+    let result = db::get_job_result(&pool, job_id)
+        .await?
+        .ok_or(ApiError::unprocessable_entity([NO_RESULT]))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}\"",
+                    file_name.replace(['"', '\\'], "")
+                ),
+            ),
+        ],
+        result,
+    ))
+}
@@ -24,27 +24,44 @@
 
 mod users;
 mod data;
+mod jobs;
+mod notifications;
+mod trash;
+mod usage;
 mod viz;
 
 // #[cfg(test)]
 // mod tests;
 
 use crate::{
-    config::Config, db::Backend, model::users::{Credentials, UserRole},
+    cache::ChartCache,
+    config::{Config, Limits},
+    db::{self, Backend},
+    model::{
+        events::Event,
+        users::{Credentials, UserRole},
+    },
+    tasks,
 };
 use anyhow::Result;
 use axum::{
+    extract::DefaultBodyLimit,
     http::{
         header::{self, SET_COOKIE},
         HeaderValue, Method,
     },
-    response::Response,
+    response::{
+        sse::{self, KeepAlive, Sse},
+        Response,
+    },
     Router,
 };
 use axum_login::{tower_sessions::ExpiredDeletion, AuthManagerLayerBuilder};
+use futures::Stream;
 use shuttle_runtime::SecretStore;
 use sqlx::PgPool;
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
+use tokio::sync::broadcast::{self, error::RecvError};
 use tower::ServiceBuilder;
 use tower_http::{
     catch_panic::CatchPanicLayer, compression::CompressionLayer, cors::CorsLayer,
@@ -55,15 +72,27 @@ use tower_sessions::{
     Expiry, SessionManagerLayer,
 };
 use tower_sessions_sqlx_store::PostgresStore;
+use tracing::warn;
+
+/// Days before the deleted items are purged from the trash.
+const DEFAULT_TRASH_RETENTION_DAYS: i32 = 30;
+/// Number of workers running the background jobs.
+const DEFAULT_JOB_WORKERS: usize = 2;
 
 /// Global state for the API.
 ///
-/// Contains the configuration ([Config]) and the
-/// shared database connection ([PgPool]).
+/// Contains the configuration ([Config]), the
+/// shared database connection ([PgPool]), the
+/// sender of the database change events ([Event]),
+/// the cache of the chart data ([ChartCache])
+/// and the resource limits of the users ([Limits]).
 #[derive(Clone)]
 pub struct ApiState {
     pub config: Arc<Config>,
     pub pool: PgPool,
+    pub events: broadcast::Sender<Event>,
+    pub chart_cache: ChartCache,
+    pub limits: Limits,
 }
 
 /// Create the application [Router].
@@ -76,6 +105,24 @@ pub struct ApiState {
 /// ALLOWED_ORIGIN=<url>
 /// ```
 /// 
+/// Dashboard reports are sent by email only if these optional keys are set:
+/// ```toml
+/// SMTP_URL=<smtp-url>
+/// SMTP_FROM=<sender-address>
+/// ```
+/// 
+/// Deleted items are purged from the trash after 30 days, unless this optional key is set:
+/// ```toml
+/// TRASH_RETENTION_DAYS=<days>
+/// ```
+/// 
+/// Background jobs are run by 2 workers, unless this optional key is set:
+/// ```toml
+/// JOB_WORKERS=<workers>
+/// ```
+/// 
+/// The resource limits of the users are set with optional keys, see [Limits::from_secrets].
+/// 
 /// An amount of admin accounts can be defined by repeating this pair of variables:
 /// ```toml
 /// <identifier>_USERNAME=<username>
@@ -115,16 +162,59 @@ pub async fn create_app(
         .get("ALLOWED_ORIGIN")
         .expect("ALLOWED_ORIGIN secret must be set");
 
+    match (secrets.get("SMTP_URL"), secrets.get("SMTP_FROM")) {
+        (Some(smtp_url), Some(from)) => {
+            let mailer = tasks::Mailer::new(&smtp_url, &from)?;
+            tokio::spawn(tasks::send_reports(api_state.pool.clone(), mailer));
+        }
+        _ => warn!("SMTP_URL and SMTP_FROM secrets are not set, reports will not be sent"),
+    }
+
+    let retention_days = match secrets.get("TRASH_RETENTION_DAYS") {
+        Some(days) => days.parse()?,
+        None => DEFAULT_TRASH_RETENTION_DAYS,
+    };
+    tokio::spawn(tasks::purge_trash(api_state.pool.clone(), retention_days));
+
+    let job_workers = match secrets.get("JOB_WORKERS") {
+        Some(workers) => workers.parse()?,
+        None => DEFAULT_JOB_WORKERS,
+    };
+    tokio::spawn(tasks::run_jobs(
+        api_state.pool.clone(),
+        job_workers,
+        api_state.limits,
+    ));
+
     tokio::spawn(async move { create_admin_users(backend, secrets).await.unwrap() });
 
+    tokio::spawn(db::listen_events(
+        api_state.pool.clone(),
+        api_state.events.clone(),
+    ));
+
+    tokio::spawn(tasks::deliver_webhooks(api_state.pool.clone()));
+
+    tokio::spawn(tasks::invalidate_chart_cache(
+        api_state.chart_cache.clone(),
+        api_state.events.subscribe(),
+    ));
+
+    tokio::spawn(tasks::refresh_charts(api_state.pool.clone()));
+
     Ok(Router::new()
         .nest(
             "/api",
             Router::new()
                 .merge(users::router())
                 .merge(data::router())
+                .merge(jobs::router())
+                .merge(notifications::router())
+                .merge(trash::router())
+                .merge(usage::router())
                 .merge(viz::router()),
         )
+        .layer(DefaultBodyLimit::max(api_state.limits.max_body_bytes))
         .layer(auth_layer)
         .layer(ServiceBuilder::new().map_response(set_partitioned_cookie))
         .layer(CompressionLayer::new())
@@ -181,6 +271,52 @@ async fn create_admin_users(mut backend: Backend, secrets: SecretStore) -> sqlx:
     Ok(())
 }
 
+/// Create a Server-Sent Events response from the change events accepted by the filter.
+///
+/// Each event is sent as a `change` event with the JSON [Event] as data.
+/// A `lagged` event is sent when the client is too slow or the server missed events,
+/// in which case the client should reload the ressource.
+///
+/// The access of the user is checked again after the events which can remove it
+/// and after dropped events. The stream ends when the access is lost.
+fn event_stream<F>(
+    receiver: broadcast::Receiver<Event>,
+    filter: impl FnMut(&Event) -> bool + Send + 'static,
+    has_access: impl Fn() -> F + Send + 'static,
+) -> Sse<impl Stream<Item = Result<sse::Event, axum::Error>>>
+where
+    F: Future<Output = bool> + Send,
+{
+    let stream =
+        futures::stream::unfold(Some((receiver, filter, has_access)), |state| async move {
+            let (mut receiver, mut filter, has_access) = state?;
+            let (sse_event, check_access) = loop {
+                match receiver.recv().await {
+                    Ok(Event::Resync) | Err(RecvError::Lagged(_)) => {
+                        break (Ok(sse::Event::default().event("lagged").data("")), true)
+                    }
+                    Ok(event) if filter(&event) => {
+                        break (
+                            sse::Event::default().event("change").json_data(&event),
+                            event.may_revoke_access(),
+                        )
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            };
+            // The last event is still sent, so the client knows why the stream ends
+            let state = if !check_access || has_access().await {
+                Some((receiver, filter, has_access))
+            } else {
+                None
+            };
+            Some((sse_event, state))
+        });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
 /// Sets the "Partiioned" attribute of the "set-cookie" header.
 fn set_partitioned_cookie(mut res: Response) -> Response {
     if let Some(set_cookie) = res.headers().get(SET_COOKIE) {
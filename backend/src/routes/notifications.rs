@@ -0,0 +1,135 @@
+//! Route handlers for the notification inbox of the user.
+//!
+//! Users must be authenticated for all requests.
+
+use super::{event_stream, ApiState};
+use crate::{
+    db::{self, AuthSession},
+    error::{ApiError, ApiResult},
+    model::{
+        events::Event,
+        notifications::{
+            Notification, NotificationsQuery, UnreadNotifications, UpdateNotification,
+        },
+    },
+    Id,
+};
+use axum::{
+    extract::{Path, Query, State},
+    response::sse::{self, Sse},
+    routing::{get, patch, post},
+    Json, Router,
+};
+use futures::{future, Stream};
+
+/// Number of notifications returned in a page.
+const PAGE_LIMIT: i64 = 50;
+
+pub fn router() -> Router<ApiState> {
+    Router::new().nest(
+        "/notifications",
+        Router::new()
+            .route("/", get(get_notifications))
+            .route("/unread", get(get_unread_notifications))
+            .route("/read", post(read_notifications))
+            .route("/events", get(get_notification_events))
+            .route("/{notification-id}", patch(update_notification)),
+    )
+}
+
+/// Get the notifications of the user, most recent first.
+///
+/// Can optionally only get the unread notifications, and the notifications
+/// before a notification ID to get the next page.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+///
+async fn get_notifications(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Query(query): Query<NotificationsQuery>,
+) -> ApiResult<Json<Vec<Notification>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    let notifications = db::get_notifications(&pool, user_id, query, PAGE_LIMIT).await?;
+
+    Ok(Json(notifications))
+}
+
+/// Get the number of unread notifications of the user.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+///
+async fn get_unread_notifications(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+) -> ApiResult<Json<UnreadNotifications>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    let unread = db::get_unread_notifications(&pool, user_id).await?;
+
+    Ok(Json(unread))
+}
+
+/// Mark a notification as read or unread.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this notification
+/// - [ApiError::NotFound]: Notification not found
+///
+async fn update_notification(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(notification_id): Path<Id>,
+    Json(update_notification): Json<UpdateNotification>,
+) -> ApiResult<Json<Notification>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_notification_relation(&pool, user_id, notification_id)
+        .await?
+        .to_api_result()?;
+
+    let notification = db::update_notification(&pool, notification_id, update_notification).await?;
+
+    Ok(Json(notification))
+}
+
+/// Mark all the notifications of the user as read.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+///
+async fn read_notifications(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+) -> ApiResult<()> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::read_notifications(&pool, user_id).await?;
+
+    Ok(())
+}
+
+/// Subscribe to the new notifications of the user with Server-Sent Events.
+///
+/// Sends the [Event::NotificationCreated] events with the notification details.
+/// Notifications created from any server instance are received.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+///
+async fn get_notification_events(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { events, .. }): State<ApiState>,
+) -> ApiResult<Sse<impl Stream<Item = Result<sse::Event, axum::Error>>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    Ok(event_stream(
+        events.subscribe(),
+        move |event| matches!(event, Event::NotificationCreated { user_id: id, .. } if *id == user_id),
+        || future::ready(true),
+    ))
+}
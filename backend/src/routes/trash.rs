@@ -0,0 +1,77 @@
+//! Route handlers for the trash of deleted tables, fields, dashboards and charts.
+//!
+//! Users must be authenticated for all requests.
+
+use super::ApiState;
+use crate::{
+    db::{self, AuthSession},
+    error::{ApiError, ApiResult, ErrorMessage},
+    model::trash::{RestoreTrashItem, TrashItem, TrashItemKind},
+};
+use axum::{
+    extract::State,
+    routing::{get, post},
+    Json, Router,
+};
+
+const PARENT_DELETED: ErrorMessage = ("item_id", "Parent is in the trash, it must be restored first");
+
+pub fn router() -> Router<ApiState> {
+    Router::new().nest(
+        "/trash",
+        Router::new()
+            .route("/", get(get_trash))
+            .route("/restore", post(restore_trash_item)),
+    )
+}
+
+/// Get the items in the trash of the user.
+/// Items are purged after the retention period.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+///
+async fn get_trash(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+) -> ApiResult<Json<Vec<TrashItem>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    let trash = db::get_trash(&pool, user_id).await?;
+
+    Ok(Json(trash))
+}
+
+/// Restore an item from the trash. A table is restored with the descendants deleted with it.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to that item
+/// - [ApiError::NotFound]: Item not found in the trash
+/// - [ApiError::UnprocessableEntity]:
+///     - [PARENT_DELETED]
+///
+async fn restore_trash_item(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Json(RestoreTrashItem { item_kind, item_id }): Json<RestoreTrashItem>,
+) -> ApiResult<()> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_trash_relation(&pool, user_id, item_kind, item_id)
+        .await?
+        .to_api_result()?;
+
+    if db::is_trash_parent_deleted(&pool, item_kind, item_id).await? {
+        return Err(ApiError::unprocessable_entity([PARENT_DELETED]));
+    }
+
+    match item_kind {
+        TrashItemKind::Table => db::restore_table(&pool, item_id, user_id).await?,
+        TrashItemKind::Field => db::restore_field(&pool, item_id, user_id).await?,
+        TrashItemKind::Dashboard => db::restore_dashboard(&pool, item_id).await?,
+        TrashItemKind::Chart => db::restore_chart(&pool, item_id).await?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//! Route handlers for the resource usage of the user, and the checks of the resource limits.
+//!
+//! Users must be authenticated for all requests.
+
+use super::ApiState;
+use crate::{
+    config::Limits,
+    db::{self, AuthSession},
+    error::{ApiError, ApiResult, ErrorMessage},
+    model::usage::{ResourceUsage, TableUsage, Usage},
+    Id,
+};
+use axum::{extract::State, routing::get, Json, Router};
+use sqlx::{Acquire, Postgres};
+
+pub(super) const TABLE_LIMIT: ErrorMessage = ("limit", "The maximum number of tables is reached");
+pub(super) const FIELD_LIMIT: ErrorMessage = (
+    "limit",
+    "The maximum number of fields of the table is reached",
+);
+pub(super) const ROW_LIMIT: ErrorMessage = (
+    "limit",
+    "The maximum number of rows of the table is reached",
+);
+
+pub fn router() -> Router<ApiState> {
+    Router::new().route("/usage", get(get_usage))
+}
+
+/// Get the usage of the tables, fields and rows of the user against their limits,
+/// and the maximum request sizes.
+///
+/// The tables and fields in the trash are counted until they are purged.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+///
+async fn get_usage(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, limits, .. }): State<ApiState>,
+) -> ApiResult<Json<Usage>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    let mut table_usage = Vec::new();
+    for table in db::get_table_counts(&pool, user_id).await? {
+        table_usage.push(TableUsage {
+            fields: ResourceUsage {
+                used: table.field_count,
+                limit: limits.max_fields_per_table,
+            },
+            rows: ResourceUsage {
+                used: table.row_count,
+                limit: limits.max_rows_per_table,
+            },
+            table_id: table.table_id,
+            name: table.name,
+        });
+    }
+
+    Ok(Json(Usage {
+        tables: ResourceUsage {
+            used: table_usage.len() as i64,
+            limit: limits.max_tables_per_user,
+        },
+        table_usage,
+        max_import_bytes: limits.max_import_bytes,
+        max_body_bytes: limits.max_body_bytes,
+    }))
+}
+
+/// Check that the user can create `tables` more tables.
+/// The creation of tables of the user is locked until the end of the transaction of `conn`,
+/// so the tables must be created in that transaction.
+///
+/// # Errors
+/// - [ApiError::UnprocessableEntity]:
+///     - [TABLE_LIMIT]
+///
+pub(super) async fn check_table_limit(
+    conn: impl Acquire<'_, Database = Postgres>,
+    limits: &Limits,
+    user_id: Id,
+    tables: i64,
+) -> ApiResult<()> {
+    let mut tx = conn.begin().await?;
+
+    db::lock_user_tables(tx.as_mut(), user_id).await?;
+    if db::get_table_count(tx.as_mut(), user_id).await? + tables > limits.max_tables_per_user {
+        return Err(ApiError::unprocessable_entity([TABLE_LIMIT]));
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Check that `fields` more fields can be created in a table.
+/// The creation of fields of the table is locked until the end of the transaction of `conn`,
+/// so the fields must be created in that transaction.
+///
+/// # Errors
+/// - [ApiError::UnprocessableEntity]:
+///     - [FIELD_LIMIT]
+///
+pub(super) async fn check_field_limit(
+    conn: impl Acquire<'_, Database = Postgres>,
+    limits: &Limits,
+    table_id: Id,
+    fields: i64,
+) -> ApiResult<()> {
+    let mut tx = conn.begin().await?;
+
+    db::lock_table_fields(tx.as_mut(), table_id).await?;
+    if db::get_field_count(tx.as_mut(), table_id).await? + fields > limits.max_fields_per_table {
+        return Err(ApiError::unprocessable_entity([FIELD_LIMIT]));
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Check that `rows` more entries can be created in a table.
+/// The creation of entries of the table is locked until the end of the transaction of `conn`,
+/// so the entries must be created in that transaction.
+///
+/// # Errors
+/// - [ApiError::UnprocessableEntity]:
+///     - [ROW_LIMIT]
+///
+pub(super) async fn check_row_limit(
+    conn: impl Acquire<'_, Database = Postgres>,
+    limits: &Limits,
+    table_id: Id,
+    rows: i64,
+) -> ApiResult<()> {
+    let mut tx = conn.begin().await?;
+
+    db::lock_table_rows(tx.as_mut(), table_id).await?;
+    if db::get_row_count(tx.as_mut(), table_id).await? + rows > limits.max_rows_per_table {
+        return Err(ApiError::unprocessable_entity([ROW_LIMIT]));
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
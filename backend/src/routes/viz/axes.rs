@@ -1,7 +1,9 @@
 use crate::{
     db::{self, AuthSession}, error::{ApiError, ApiResult}, model::{
         data::FieldKind,
-        viz::{Aggregate, Axis, SetAxes},
+        viz::{
+            Aggregate, AggregateRule, Axis, AxisField, AxisKind, ChartKind, CreateAxis, SetAxes,
+        },
     }, routes::ApiState, Id
 };
 use axum::{
@@ -9,11 +11,26 @@ use axum::{
     routing::put,
     Json, Router,
 };
-use itertools::Itertools;
 use std::collections::HashMap;
 
 const FIELD_NOT_FOUND: &str = "Field not found";
 const INVALID_AXIS_AGGREGATE: &str = "Axis aggregate is invalid for this field";
+const INVALID_PERCENTILE: &str = "Percentile must be between 0 and 1";
+const SORT_FIELD_NOT_FOUND: &str = "Sort field of the aggregate not found";
+const SIBLING_TABLES: &str = "Fields of child tables on different branches can not be combined";
+const INVALID_TIME_BUCKET: &str = "Time bucket requires a DateTime field without aggregate";
+const INVALID_TIME_ZONE: &str = "Time zone is unknown";
+const TIME_BUCKET_MISSING: &str = "Time zone and gap filling require a time bucket";
+const MANY_FILL_GAPS: &str = "Only one axis can fill gaps";
+const FILL_GAPS_TOP_N: &str = "Gap filling can not be used with top N";
+const FILL_GAPS_PIVOT: &str = "Gap filling can not be used in pivot charts";
+const AXIS_KIND_NOT_ALLOWED: &str = "Axis kind is not allowed for this chart kind";
+const AXIS_KIND_MISSING: &str = "Axis kind is required for this chart kind";
+const TOO_MANY_AXES: &str = "Too many axes of this kind for this chart kind";
+const AGGREGATE_REQUIRED: &str = "Axis must be aggregated for this chart kind";
+const AGGREGATE_FORBIDDEN: &str = "Axis can not be aggregated for this chart kind";
+const COUNT_REQUIRED: &str = "Axis must be a count for this chart kind";
+const NUMERIC_REQUIRED: &str = "Axis must be numeric for this chart kind";
 
 pub fn router() -> Router<ApiState> {
     Router::new().nest(
@@ -24,6 +41,9 @@ pub fn router() -> Router<ApiState> {
 
 /// Set all the axes of the specified chart.
 /// 
+/// Axes can use the fields of the table of the chart, of its ancestors and of its descendants,
+/// which are joined through the `parent_id` column.
+/// 
 /// This is the only way to modify chart axes because the dynamic view needs to
 /// be rebuilt and it is much more convienient when receiving all the axes at once.
 /// 
@@ -34,10 +54,28 @@ pub fn router() -> Router<ApiState> {
 /// - [ApiError::UnprocessableEntity]:
 ///     - <field_id>: [FIELD_NOT_FOUND]
 ///     - <field_id>: [INVALID_AXIS_AGGREGATE]
+///     - <field_id>: [INVALID_PERCENTILE]
+///     - <field_id>: [SORT_FIELD_NOT_FOUND]
+///     - <field_id>: [SIBLING_TABLES]
+///     - <field_id>: [INVALID_TIME_BUCKET]
+///     - <field_id>: [INVALID_TIME_ZONE]
+///     - <field_id>: [TIME_BUCKET_MISSING]
+///     - <field_id>: [MANY_FILL_GAPS]
+///     - <field_id>: [FILL_GAPS_TOP_N]
+///     - <field_id>: [FILL_GAPS_PIVOT]
+///     - <axis_kind>: [AXIS_KIND_NOT_ALLOWED]
+///     - <axis_kind>: [AXIS_KIND_MISSING]
+///     - <axis_kind>: [TOO_MANY_AXES]
+///     - <axis_kind>: [AGGREGATE_REQUIRED]
+///     - <axis_kind>: [AGGREGATE_FORBIDDEN]
+///     - <axis_kind>: [COUNT_REQUIRED]
+///     - <axis_kind>: [NUMERIC_REQUIRED]
 /// 
 async fn set_axes(
     AuthSession { user, .. }: AuthSession,
-    State(ApiState { pool, .. }): State<ApiState>,
+    State(ApiState {
+        pool, chart_cache, ..
+    }): State<ApiState>,
     Path((dashboard_id, chart_id)): Path<(Id, Id)>,
     Json(SetAxes(axes)): Json<SetAxes>,
 ) -> ApiResult<Json<Vec<Axis>>> {
@@ -50,50 +88,127 @@ async fn set_axes(
         .await?
         .to_api_result()?;
 
-    let table_id = db::get_chart_table_id(&pool, chart_id).await?;
+    let chart = db::get_chart(&pool, chart_id).await?;
 
+    let related_tables = db::get_related_tables(&pool, chart.table_id).await?;
+    let mut fields = HashMap::new();
+    for &table_id in related_tables.keys() {
+        for field in db::get_fields_metadata(&pool, table_id).await? {
+            fields.insert(field.field_id, (table_id, field.field_kind.0));
+        }
+    }
 
-    let field_kinds: HashMap<_, _> = db::get_fields_metadata(&pool, table_id)
-        .await?
-        .into_iter()
-        .map(|field| (field.field_id, field.field_kind.0))
-        .collect();
-
-    let axes = axes
-        .into_iter()
-        .map(|axis| {
-            let field_kind =
-                &field_kinds
-                    .get(&axis.field_id)
-                    .ok_or(ApiError::unprocessable_entity([(
+    let mut has_fill_gaps = false;
+    for (i, axis) in axes.iter().enumerate() {
+        let (table_id, field_kind) = &fields
+            .get(&axis.field_id)
+            .ok_or(ApiError::unprocessable_entity([(
+                axis.field_id.to_string(),
+                FIELD_NOT_FOUND,
+            )]))?;
+
+        let table_ids = axes[..=i].iter().map(|axis| fields[&axis.field_id].0);
+        if db::get_table_joins(chart.table_id, &related_tables, table_ids).is_none() {
+            return Err(ApiError::unprocessable_entity([(
+                axis.field_id.to_string(),
+                SIBLING_TABLES,
+            )]));
+        }
+
+        if let Some(aggregate) = &axis.aggregate {
+            validate_axis(aggregate, field_kind).map_err(|message| {
+                ApiError::unprocessable_entity([(axis.field_id.to_string(), message)])
+            })?;
+
+            if let Aggregate::First { field_id } | Aggregate::Last { field_id } = aggregate {
+                // The sort field is aggregated with the axis field, so it must be in the same table
+                if fields
+                    .get(field_id)
+                    .is_none_or(|(sort_table_id, _)| sort_table_id != table_id)
+                {
+                    return Err(ApiError::unprocessable_entity([(
                         axis.field_id.to_string(),
-                        FIELD_NOT_FOUND,
-                    )]))?;
+                        SORT_FIELD_NOT_FOUND,
+                    )]));
+                }
+            }
+        }
+
+        validate_time_bucket(axis, field_kind).map_err(|message| {
+            ApiError::unprocessable_entity([(axis.field_id.to_string(), message)])
+        })?;
 
-            if let Some(aggregate) = &axis.aggregate {
-                validate_axis(&aggregate, field_kind).map_err(|message| {
-                    ApiError::unprocessable_entity([(axis.field_id.to_string(), message)])
-                })?;
+        if let Some(time_zone) = &axis.time_zone {
+            if !db::is_valid_time_zone(&pool, time_zone).await? {
+                return Err(ApiError::unprocessable_entity([(
+                    axis.field_id.to_string(),
+                    INVALID_TIME_ZONE,
+                )]));
             }
+        }
 
-            ApiResult::Ok(axis)
-        })
-        .try_collect()?;
+        if axis.fill_gaps {
+            if chart.top_n.is_some() {
+                return Err(ApiError::unprocessable_entity([(
+                    axis.field_id.to_string(),
+                    FILL_GAPS_TOP_N,
+                )]));
+            }
+            if chart.chart_kind == ChartKind::Pivot {
+                return Err(ApiError::unprocessable_entity([(
+                    axis.field_id.to_string(),
+                    FILL_GAPS_PIVOT,
+                )]));
+            }
+            if has_fill_gaps {
+                return Err(ApiError::unprocessable_entity([(
+                    axis.field_id.to_string(),
+                    MANY_FILL_GAPS,
+                )]));
+            }
+            has_fill_gaps = true;
+        }
+    }
+
+    validate_chart_axes(
+        chart.chart_kind,
+        axes.iter().map(|axis| {
+            (
+                axis.axis_kind,
+                axis.aggregate.as_ref(),
+                &fields[&axis.field_id].1,
+            )
+        }),
+    )?;
 
-    let axes = db::set_axes(&pool, chart_id, table_id, &field_kinds, axes).await?;
+    let axes = db::set_axes(&pool, chart_id, axes).await?;
+    chart_cache.invalidate_chart(chart_id);
 
     Ok(Json(axes))
 }
 
 fn validate_axis(aggregate: &Aggregate, field_kind: &FieldKind) -> Result<(), &'static str> {
     match (aggregate, field_kind) {
-        (Aggregate::Count, _)
+        (Aggregate::Percentile(fraction), _) if !(0.0..=1.0).contains(fraction) => {
+            Err(INVALID_PERCENTILE)
+        }
+        (
+            Aggregate::Count
+            | Aggregate::CountDistinct
+            | Aggregate::First { .. }
+            | Aggregate::Last { .. },
+            _,
+        )
         | (
             Aggregate::Sum,
             FieldKind::Integer { .. } | FieldKind::Float { .. } | FieldKind::Money { .. },
         )
         | (
-            Aggregate::Average,
+            Aggregate::Average
+            | Aggregate::Median
+            | Aggregate::Percentile(_)
+            | Aggregate::StdDev
+            | Aggregate::Variance,
             FieldKind::Integer { .. }
             | FieldKind::Float { .. }
             | FieldKind::Money { .. }
@@ -106,8 +221,135 @@ fn validate_axis(aggregate: &Aggregate, field_kind: &FieldKind) -> Result<(), &'
             | FieldKind::Float { .. }
             | FieldKind::Money { .. }
             | FieldKind::Progress { .. }
-            | FieldKind::DateTime { .. },
-        ) => Ok(()),
+            | FieldKind::DateTime { .. }
+            | FieldKind::RowNumber
+            | FieldKind::CreatedAt
+            | FieldKind::UpdatedAt,
+        )
+        | (Aggregate::BoolAnd | Aggregate::BoolOr, FieldKind::Checkbox) => Ok(()),
         _ => Err(INVALID_AXIS_AGGREGATE),
     }
 }
+
+fn validate_time_bucket(axis: &CreateAxis, field_kind: &FieldKind) -> Result<(), &'static str> {
+    match (axis.time_bucket, field_kind) {
+        (None, _) if axis.time_zone.is_some() || axis.fill_gaps => Err(TIME_BUCKET_MISSING),
+        (None, _) => Ok(()),
+        (Some(_), field_kind) if field_kind.is_date_time() && axis.aggregate.is_none() => Ok(()),
+        (Some(_), _) => Err(INVALID_TIME_BUCKET),
+    }
+}
+
+/// Check that the axes are allowed in the chart kind.
+///
+/// Each axis is described by its kind, aggregate and field kind.
+pub fn validate_chart_axes<'a>(
+    chart_kind: ChartKind,
+    axes: impl IntoIterator<Item = (AxisKind, Option<&'a Aggregate>, &'a FieldKind)>,
+) -> ApiResult<()> {
+    let Some(rules) = chart_kind.get_axis_rules() else {
+        return Ok(());
+    };
+
+    let mut errors = Vec::new();
+    let mut counts: HashMap<AxisKind, usize> = HashMap::new();
+
+    for (axis_kind, aggregate, field_kind) in axes {
+        *counts.entry(axis_kind).or_default() += 1;
+
+        let key = format!("{axis_kind:?}");
+        let Some(rule) = rules.iter().find(|rule| rule.axis_kind == axis_kind) else {
+            errors.push((key, AXIS_KIND_NOT_ALLOWED));
+            continue;
+        };
+
+        match (rule.aggregate, aggregate) {
+            (AggregateRule::Required, None) => errors.push((key.clone(), AGGREGATE_REQUIRED)),
+            (AggregateRule::Forbidden, Some(_)) => errors.push((key.clone(), AGGREGATE_FORBIDDEN)),
+            (AggregateRule::Count, aggregate)
+                if !matches!(aggregate, Some(Aggregate::Count | Aggregate::CountDistinct)) =>
+            {
+                errors.push((key.clone(), COUNT_REQUIRED))
+            }
+            _ => (),
+        }
+
+        let is_numeric = aggregate.map_or_else(
+            || field_kind.is_numeric(),
+            |aggregate| aggregate.is_numeric(field_kind),
+        );
+        if rule.is_numeric && !is_numeric {
+            errors.push((key, NUMERIC_REQUIRED));
+        }
+    }
+
+    for rule in &rules {
+        let count = counts.get(&rule.axis_kind).copied().unwrap_or(0);
+        let key = format!("{:?}", rule.axis_kind);
+        if count < rule.min_count {
+            errors.push((key, AXIS_KIND_MISSING));
+        } else if rule.max_count.is_some_and(|max_count| count > max_count) {
+            errors.push((key, TOO_MANY_AXES));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::unprocessable_entity(errors))
+    }
+}
+
+/// Check that the axes of a chart stay valid once a field is converted to another field kind.
+/// A chart already breaking the rules of its chart kind is only checked per axis.
+pub fn are_converted_axes_valid(
+    chart_kind: ChartKind,
+    axes: &[AxisField],
+    field_id: Id,
+    field_kind: &FieldKind,
+) -> bool {
+    let converted_axes = axes.iter().map(|AxisField { axis, field_kind: old_field_kind, .. }| {
+        let field_kind = if axis.field_id == field_id {
+            field_kind
+        } else {
+            &old_field_kind.0
+        };
+        (axis, axis.aggregate.as_ref().map(|aggregate| &aggregate.0), field_kind)
+    });
+
+    let are_axes_valid = converted_axes.clone().all(|(axis, aggregate, field_kind)| {
+        let create_axis = CreateAxis {
+            field_id: axis.field_id,
+            axis_kind: axis.axis_kind,
+            aggregate: aggregate.cloned(),
+            time_bucket: axis.time_bucket,
+            time_zone: axis.time_zone.clone(),
+            fill_gaps: axis.fill_gaps,
+            sort: axis.sort,
+        };
+        aggregate.map_or(Ok(()), |aggregate| validate_axis(aggregate, field_kind)).is_ok()
+            && validate_time_bucket(&create_axis, field_kind).is_ok()
+    });
+
+    let were_chart_axes_valid = validate_chart_axes(
+        chart_kind,
+        axes.iter().map(|AxisField { axis, field_kind, .. }| {
+            (
+                axis.axis_kind,
+                axis.aggregate.as_ref().map(|aggregate| &aggregate.0),
+                &field_kind.0,
+            )
+        }),
+    )
+    .is_ok();
+
+    are_axes_valid
+        && (!were_chart_axes_valid
+            || validate_chart_axes(
+                chart_kind,
+                converted_axes.map(|(axis, aggregate, field_kind)| {
+                    (axis.axis_kind, aggregate, field_kind)
+                }),
+            )
+            .is_ok())
+}
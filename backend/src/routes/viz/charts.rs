@@ -1,11 +1,62 @@
+use super::{axes::validate_chart_axes, filters::validate_filter_kind};
 use crate::{
-    db::{self, AuthSession}, error::{ApiError, ApiResult}, model::viz::{Chart, ChartData, CreateChart, UpdateChart}, routes::ApiState, Id
+    cache::ChartCache,
+    db::{self, AuthSession},
+    error::{ApiError, ApiResult, ErrorMessage},
+    io,
+    model::{
+        jobs::{Job, JobKind},
+        viz::{
+            Aggregate, CacheMode, Chart, ChartData, ChartDataQuery, ChartKind, CopyChart,
+            CreateChart, FilterKind, UpdateChart,
+        },
+    },
+    routes::ApiState,
+    Id,
 };
+use anyhow::Context;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::header,
+    response::IntoResponse,
     routing::{get, patch, post},
     Json, Router,
 };
+use itertools::Itertools;
+use sqlx::PgPool;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    mem::discriminant,
+};
+
+const FILTER_NOT_FOUND: ErrorMessage = ("filters", "Filter not found in this dashboard");
+const INVALID_ROW_LIMIT: ErrorMessage = ("row_limit", "Row limit must be positive");
+const INVALID_TOP_N: ErrorMessage = ("top_n", "Top N must be positive");
+const LIMIT_CONFLICT: ErrorMessage = ("top_n", "Top N and row limit can not both be set");
+const TOP_N_FILL_GAPS: ErrorMessage = ("top_n", "Top N can not be used with gap filling");
+const PIVOT_TOP_N: ErrorMessage = ("top_n", "Top N can not be used in pivot charts");
+const PIVOT_FILL_GAPS: ErrorMessage = ("chart_kind", "Gap filling can not be used in pivot charts");
+const INVALID_REFRESH_INTERVAL: ErrorMessage =
+    ("refresh_interval", "Refresh interval must be positive");
+const REFRESH_INTERVAL_REQUIRED: ErrorMessage = (
+    "refresh_interval",
+    "Refresh interval is required for materialized charts",
+);
+const LIVE_REFRESH_INTERVAL: ErrorMessage = (
+    "refresh_interval",
+    "Refresh interval can not be used without cache",
+);
+const CHART_NOT_MATERIALIZED: ErrorMessage =
+    ("cache_mode", "Only materialized charts can be refreshed");
+const CHART_KIND_NOT_RENDERED: ErrorMessage = (
+    "chart_kind",
+    "Only bar, line and table charts can be rendered",
+);
+const FIELD_NOT_COMPATIBLE: &str = "No field of the same name and kind in the target table";
+const SIBLING_TABLES: ErrorMessage = (
+    "table_id",
+    "Fields of child tables on different branches can not be combined",
+);
 
 pub fn router() -> Router<ApiState> {
     Router::new().nest(
@@ -13,17 +64,29 @@ pub fn router() -> Router<ApiState> {
         Router::new()
             .route("/", post(create_chart).get(get_charts))
             .route("/{chart-id}", patch(update_chart).delete(delete_chart))
+            .route("/{chart-id}/copy", post(copy_chart))
+            .route("/{chart-id}/refresh", post(refresh_chart))
             .route("/{chart-id}/data", get(get_chart_data))
+            .route("/{chart-id}/render.svg", get(render_chart_svg))
+            .route("/{chart-id}/render.png", get(render_chart_png)),
     )
 }
 
 /// Create a blank chart.
-/// 
+///
 /// # Errors
 /// - [ApiError::Unauthorized]: User not authenticated
 /// - [ApiError::Forbidden]: User does not have access to this dashboard or table
 /// - [ApiError::NotFound]: Dashboard or table not found
-/// 
+/// - [ApiError::UnprocessableEntity]:
+///     - [INVALID_ROW_LIMIT]
+///     - [INVALID_TOP_N]
+///     - [LIMIT_CONFLICT]
+///     - [PIVOT_TOP_N]
+///     - [INVALID_REFRESH_INTERVAL]
+///     - [REFRESH_INTERVAL_REQUIRED]
+///     - [LIVE_REFRESH_INTERVAL]
+///
 async fn create_chart(
     AuthSession { user, .. }: AuthSession,
     State(ApiState { pool, .. }): State<ApiState>,
@@ -39,21 +102,43 @@ async fn create_chart(
         .await?
         .to_api_result()?;
 
+    validate_limits(
+        create_chart.chart_kind,
+        create_chart.row_limit,
+        create_chart.top_n,
+    )?;
+    validate_cache(create_chart.cache_mode, create_chart.refresh_interval)?;
+
     let chart = db::create_chart(&pool, dashboard_id, create_chart).await?;
 
     Ok(Json(chart))
 }
 
 /// Update a chart's metadata.
-/// 
+///
+/// The current axes, if any, must be allowed in the new chart kind.
+///
 /// # Errors
 /// - [ApiError::Unauthorized]: User not authenticated
 /// - [ApiError::Forbidden]: User does not have access to this dashboard or chart
 /// - [ApiError::NotFound]: Dashboard or chart not found
-/// 
+/// - [ApiError::UnprocessableEntity]:
+///     - [INVALID_ROW_LIMIT]
+///     - [INVALID_TOP_N]
+///     - [LIMIT_CONFLICT]
+///     - [TOP_N_FILL_GAPS]
+///     - [PIVOT_TOP_N]
+///     - [PIVOT_FILL_GAPS]
+///     - [INVALID_REFRESH_INTERVAL]
+///     - [REFRESH_INTERVAL_REQUIRED]
+///     - [LIVE_REFRESH_INTERVAL]
+///     - Any error of axes not allowed in the chart kind
+///
 async fn update_chart(
     AuthSession { user, .. }: AuthSession,
-    State(ApiState { pool, .. }): State<ApiState>,
+    State(ApiState {
+        pool, chart_cache, ..
+    }): State<ApiState>,
     Path((dashboard_id, chart_id)): Path<(Id, Id)>,
     Json(update_chart): Json<UpdateChart>,
 ) -> ApiResult<Json<Chart>> {
@@ -66,18 +151,144 @@ async fn update_chart(
         .await?
         .to_api_result()?;
 
+    validate_limits(
+        update_chart.chart_kind,
+        update_chart.row_limit,
+        update_chart.top_n,
+    )?;
+    validate_cache(update_chart.cache_mode, update_chart.refresh_interval)?;
+
+    let axes = db::get_axis_fields(&pool, chart_id).await?;
+    if axes.iter().any(|axis_field| axis_field.axis.fill_gaps) {
+        if update_chart.top_n.is_some() {
+            return Err(ApiError::unprocessable_entity([TOP_N_FILL_GAPS]));
+        }
+        if update_chart.chart_kind == ChartKind::Pivot {
+            return Err(ApiError::unprocessable_entity([PIVOT_FILL_GAPS]));
+        }
+    }
+    // A blank chart can change to any kind
+    if !axes.is_empty() {
+        validate_chart_axes(
+            update_chart.chart_kind,
+            axes.iter().map(|axis_field| {
+                (
+                    axis_field.axis.axis_kind,
+                    axis_field.axis.aggregate.as_deref(),
+                    &axis_field.field_kind.0,
+                )
+            }),
+        )?;
+    }
+
     let chart = db::update_chart(&pool, chart_id, update_chart).await?;
+    chart_cache.invalidate_chart(chart_id);
+
+    Ok(Json(chart))
+}
+
+/// Copy a chart with its axes and its own filters, to another dashboard or table if specified.
+///
+/// When the table is different, the fields of the axes and filters are replaced
+/// by the fields of the table and its related tables with the same name and kind.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to a dashboard, the chart or the table
+/// - [ApiError::NotFound]: Dashboard, chart or table not found
+/// - [ApiError::UnprocessableEntity]:
+///     - <field_id>: [FIELD_NOT_COMPATIBLE]
+///     - [SIBLING_TABLES]
+///
+async fn copy_chart(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((dashboard_id, chart_id)): Path<(Id, Id)>,
+    Json(CopyChart {
+        dashboard_id: target_dashboard_id,
+        table_id,
+    }): Json<CopyChart>,
+) -> ApiResult<Json<Chart>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+    db::check_chart_relation(&pool, dashboard_id, chart_id)
+        .await?
+        .to_api_result()?;
+
+    let chart = db::get_chart(&pool, chart_id).await?;
+
+    let target_dashboard_id = target_dashboard_id.unwrap_or(dashboard_id);
+    db::check_dashboard_relation(&pool, user_id, target_dashboard_id)
+        .await?
+        .to_api_result()?;
+
+    let table_id = table_id.unwrap_or(chart.table_id);
+    let field_ids = if table_id == chart.table_id {
+        HashMap::new()
+    } else {
+        db::check_table_relation(&pool, user_id, table_id)
+            .await?
+            .to_api_result()?;
+        map_chart_fields(&pool, &chart, table_id).await?
+    };
+
+    let chart = db::copy_chart(&pool, chart_id, target_dashboard_id, table_id, &field_ids).await?;
 
     Ok(Json(chart))
 }
 
-/// Delete a chart and its axes.
-/// 
+/// Queue a job refreshing the materialized view of a chart now.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard or chart
+/// - [ApiError::NotFound]: Dashboard or chart not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [CHART_NOT_MATERIALIZED]
+///
+async fn refresh_chart(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((dashboard_id, chart_id)): Path<(Id, Id)>,
+) -> ApiResult<Json<Job>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+    db::check_chart_relation(&pool, dashboard_id, chart_id)
+        .await?
+        .to_api_result()?;
+
+    let chart = db::get_chart(&pool, chart_id).await?;
+    if chart.cache_mode != CacheMode::Materialized {
+        return Err(ApiError::unprocessable_entity([CHART_NOT_MATERIALIZED]));
+    }
+
+    let job = db::create_job(
+        &pool,
+        user_id,
+        &JobKind::RefreshChart {
+            dashboard_id,
+            chart_id,
+        },
+        None,
+    )
+    .await?;
+
+    Ok(Json(job))
+}
+
+/// Move a chart and its axes to the trash.
+///
 /// # Errors
 /// - [ApiError::Unauthorized]: User not authenticated
 /// - [ApiError::Forbidden]: User does not have access to this dashboard or chart
 /// - [ApiError::NotFound]: Dashboard or chart not found
-/// 
+///
 async fn delete_chart(
     AuthSession { user, .. }: AuthSession,
     State(ApiState { pool, .. }): State<ApiState>,
@@ -98,12 +309,12 @@ async fn delete_chart(
 }
 
 /// Get all charts for this dashboard.
-/// 
+///
 /// # Errors
 /// - [ApiError::Unauthorized]: User not authenticated
 /// - [ApiError::Forbidden]: User does not have access to this dashboard
 /// - [ApiError::NotFound]: Dashboard not found
-/// 
+///
 async fn get_charts(
     AuthSession { user, .. }: AuthSession,
     State(ApiState { pool, .. }): State<ApiState>,
@@ -120,20 +331,29 @@ async fn get_charts(
     Ok(Json(charts))
 }
 
-
-/// Get the chart's metadata, axes metadata, and cell data.
-/// 
+/// Get the chart's metadata, axes metadata, filters, and cell data.
+///
 /// Used for building and displaying the chart.
-/// 
+/// The `filters` query parameter is a JSON object of filter values
+/// replacing the saved ones for this request, keyed by filter ID.
+///
+/// The data of charts cached in memory is computed once for each filter values.
+///
 /// # Errors
 /// - [ApiError::Unauthorized]: User not authenticated
 /// - [ApiError::Forbidden]: User does not have access to this dashboard or chart
 /// - [ApiError::NotFound]: Dashboard or chart not found
-/// 
+/// - [ApiError::UnprocessableEntity]:
+///     - [FILTER_NOT_FOUND]
+///     - Any error of an invalid filter
+///
 async fn get_chart_data(
     AuthSession { user, .. }: AuthSession,
-    State(ApiState { pool, .. }): State<ApiState>,
+    State(ApiState {
+        pool, chart_cache, ..
+    }): State<ApiState>,
     Path((dashboard_id, chart_id)): Path<(Id, Id)>,
+    Query(ChartDataQuery { filters: overrides }): Query<ChartDataQuery>,
 ) -> ApiResult<Json<ChartData>> {
     let user_id = user.ok_or(ApiError::Forbidden)?.user_id;
 
@@ -144,7 +364,255 @@ async fn get_chart_data(
         .await?
         .to_api_result()?;
 
-    let chart_data = db::get_chart_data(&pool, chart_id).await?;
+    let chart_data = load_chart_data(
+        &pool,
+        &chart_cache,
+        dashboard_id,
+        chart_id,
+        overrides.unwrap_or_default(),
+    )
+    .await?;
 
     Ok(Json(chart_data))
 }
+
+/// Render the chart data to an SVG image.
+///
+/// Takes the same `filters` query parameter as the chart data.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard or chart
+/// - [ApiError::NotFound]: Dashboard or chart not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [FILTER_NOT_FOUND]
+///     - [CHART_KIND_NOT_RENDERED]
+///     - Any error of an invalid filter
+///
+async fn render_chart_svg(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState {
+        pool, chart_cache, ..
+    }): State<ApiState>,
+    Path((dashboard_id, chart_id)): Path<(Id, Id)>,
+    Query(ChartDataQuery { filters: overrides }): Query<ChartDataQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let user_id = user.ok_or(ApiError::Forbidden)?.user_id;
+
+    let chart_data = load_rendered_chart_data(
+        &pool,
+        &chart_cache,
+        user_id,
+        dashboard_id,
+        chart_id,
+        overrides.unwrap_or_default(),
+    )
+    .await?;
+
+    let svg = tokio::task::spawn_blocking(move || io::render_chart_svg(&chart_data))
+        .await
+        .context("Chart rendering failed")??;
+
+    Ok(([(header::CONTENT_TYPE, "image/svg+xml")], svg))
+}
+
+/// Render the chart data to a PNG image.
+///
+/// Takes the same `filters` query parameter as the chart data.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard or chart
+/// - [ApiError::NotFound]: Dashboard or chart not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [FILTER_NOT_FOUND]
+///     - [CHART_KIND_NOT_RENDERED]
+///     - Any error of an invalid filter
+///
+async fn render_chart_png(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState {
+        pool, chart_cache, ..
+    }): State<ApiState>,
+    Path((dashboard_id, chart_id)): Path<(Id, Id)>,
+    Query(ChartDataQuery { filters: overrides }): Query<ChartDataQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let user_id = user.ok_or(ApiError::Forbidden)?.user_id;
+
+    let chart_data = load_rendered_chart_data(
+        &pool,
+        &chart_cache,
+        user_id,
+        dashboard_id,
+        chart_id,
+        overrides.unwrap_or_default(),
+    )
+    .await?;
+
+    let png = tokio::task::spawn_blocking(move || io::render_chart_png(&chart_data))
+        .await
+        .context("Chart rendering failed")??;
+
+    Ok(([(header::CONTENT_TYPE, "image/png")], png))
+}
+
+/// Get the data of a chart to render, with the filter values replacing the saved ones.
+async fn load_rendered_chart_data(
+    pool: &PgPool,
+    chart_cache: &ChartCache,
+    user_id: Id,
+    dashboard_id: Id,
+    chart_id: Id,
+    overrides: HashMap<Id, FilterKind>,
+) -> ApiResult<ChartData> {
+    db::check_dashboard_relation(pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+    db::check_chart_relation(pool, dashboard_id, chart_id)
+        .await?
+        .to_api_result()?;
+
+    let chart_data = load_chart_data(pool, chart_cache, dashboard_id, chart_id, overrides).await?;
+    if !io::RENDERED_CHART_KINDS.contains(&chart_data.chart.chart_kind) {
+        return Err(ApiError::unprocessable_entity([CHART_KIND_NOT_RENDERED]));
+    }
+
+    Ok(chart_data)
+}
+
+/// Get the chart data with the filter values replacing the saved ones,
+/// from the cache if the chart is cached in memory.
+async fn load_chart_data(
+    pool: &PgPool,
+    chart_cache: &ChartCache,
+    dashboard_id: Id,
+    chart_id: Id,
+    overrides: HashMap<Id, FilterKind>,
+) -> ApiResult<ChartData> {
+    if !overrides.is_empty() {
+        let filters: HashMap<_, _> = db::get_filters(pool, dashboard_id)
+            .await?
+            .into_iter()
+            .map(|filter| (filter.filter_id, filter.field_id))
+            .collect();
+        for (filter_id, filter_kind) in &overrides {
+            let field_id = filters
+                .get(filter_id)
+                .ok_or(ApiError::unprocessable_entity([FILTER_NOT_FOUND]))?;
+            let field = db::get_field(pool, *field_id).await?;
+            validate_filter_kind(pool, filter_kind, &field.field_kind).await?;
+        }
+    }
+
+    let cache_key = serde_json::to_string(&overrides.iter().collect::<BTreeMap<_, _>>())
+        .expect("filter values should serialize");
+    if let Some(chart_data) = chart_cache.get(chart_id, &cache_key) {
+        return Ok(chart_data);
+    }
+
+    // The data is not cached if a change invalidates it while it is computed
+    let generation = chart_cache.generation();
+    let chart_data = db::get_chart_data(pool, chart_id, &overrides).await?;
+    if chart_data.chart.cache_mode == CacheMode::Memory {
+        chart_cache.insert(cache_key, chart_data.clone(), generation);
+    }
+
+    Ok(chart_data)
+}
+
+/// Map the fields of the axes and filters of a chart to the fields with the same name and kind
+/// in another table. Fields of the table of the chart are mapped to the fields of that table,
+/// and fields of its related tables to the fields of the tables related to that table.
+async fn map_chart_fields(
+    pool: &PgPool,
+    chart: &Chart,
+    table_id: Id,
+) -> ApiResult<HashMap<Id, Id>> {
+    let related_tables = db::get_related_tables(pool, table_id).await?;
+    let mut target_fields = Vec::new();
+    for &related_table_id in related_tables.keys() {
+        target_fields.extend(db::get_fields(pool, related_table_id).await?);
+    }
+
+    let axes = db::get_axis_fields(pool, chart.chart_id).await?;
+    let filters = db::get_chart_filters(pool, chart.chart_id).await?;
+    let field_ids: BTreeSet<Id> = axes
+        .iter()
+        .flat_map(|axis_field| {
+            let sort_field_id = axis_field
+                .axis
+                .aggregate
+                .as_deref()
+                .and_then(Aggregate::get_sort_field_id);
+            [Some(axis_field.axis.field_id), sort_field_id]
+        })
+        .flatten()
+        .chain(
+            filters
+                .iter()
+                .filter(|filter| filter.chart_id == Some(chart.chart_id))
+                .map(|filter| filter.field_id),
+        )
+        .collect();
+
+    let mut mapped_fields = HashMap::new();
+    for field_id in field_ids {
+        let field = db::get_field(pool, field_id).await?;
+        let candidates = target_fields
+            .iter()
+            .filter(|target_field| {
+                (target_field.table_id == table_id) == (field.table_id == chart.table_id)
+                    && target_field.name == field.name
+                    && discriminant(&target_field.field_kind.0) == discriminant(&field.field_kind.0)
+            })
+            .collect_vec();
+        let [target_field] = candidates[..] else {
+            return Err(ApiError::unprocessable_entity([(
+                field_id.to_string(),
+                FIELD_NOT_COMPATIBLE,
+            )]));
+        };
+        mapped_fields.insert(field_id, (target_field.field_id, target_field.table_id));
+    }
+
+    let table_ids = mapped_fields.values().map(|(_, table_id)| *table_id);
+    if db::get_table_joins(table_id, &related_tables, table_ids).is_none() {
+        return Err(ApiError::unprocessable_entity([SIBLING_TABLES]));
+    }
+
+    Ok(mapped_fields
+        .into_iter()
+        .map(|(field_id, (target_field_id, _))| (field_id, target_field_id))
+        .collect())
+}
+
+fn validate_limits(
+    chart_kind: ChartKind,
+    row_limit: Option<i32>,
+    top_n: Option<i32>,
+) -> ApiResult<()> {
+    match (row_limit, top_n) {
+        (_, Some(_)) if chart_kind == ChartKind::Pivot => {
+            Err(ApiError::unprocessable_entity([PIVOT_TOP_N]))
+        }
+        (Some(_), Some(_)) => Err(ApiError::unprocessable_entity([LIMIT_CONFLICT])),
+        (Some(row_limit), None) if row_limit <= 0 => {
+            Err(ApiError::unprocessable_entity([INVALID_ROW_LIMIT]))
+        }
+        (None, Some(top_n)) if top_n <= 0 => Err(ApiError::unprocessable_entity([INVALID_TOP_N])),
+        _ => Ok(()),
+    }
+}
+
+fn validate_cache(cache_mode: CacheMode, refresh_interval: Option<i32>) -> ApiResult<()> {
+    match (cache_mode, refresh_interval) {
+        (_, Some(refresh_interval)) if refresh_interval <= 0 => {
+            Err(ApiError::unprocessable_entity([INVALID_REFRESH_INTERVAL]))
+        }
+        (CacheMode::Materialized, None) => {
+            Err(ApiError::unprocessable_entity([REFRESH_INTERVAL_REQUIRED]))
+        }
+        (CacheMode::Live, Some(_)) => Err(ApiError::unprocessable_entity([LIVE_REFRESH_INTERVAL])),
+        _ => Ok(()),
+    }
+}
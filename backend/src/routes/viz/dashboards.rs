@@ -1,15 +1,21 @@
 use crate::{
-    db::{self, AuthSession},
+    db::{self, AuthSession, Relation},
     error::{ApiError, ApiResult},
-    model::viz::{CreateDashboard, Dashboard, UpdateDashboard},
-    routes::ApiState,
+    model::{
+        events::Event,
+        viz::{CopyDashboard, CreateDashboard, Dashboard, UpdateDashboard},
+    },
+    routes::{event_stream, ApiState},
     Id,
 };
 use axum::{
     extract::{Path, State},
-    routing::{patch, post},
+    response::sse::{self, Sse},
+    routing::{get, patch, post},
     Json, Router,
 };
+use futures::Stream;
+use std::collections::HashMap;
 
 pub fn router() -> Router<ApiState> {
     Router::new().nest(
@@ -19,7 +25,9 @@ pub fn router() -> Router<ApiState> {
             .route(
                 "/{dashboard-id}",
                 patch(update_dashboard).delete(delete_dashboard),
-            ),
+            )
+            .route("/{dashboard-id}/copy", post(copy_dashboard))
+            .route("/{dashboard-id}/events", get(get_dashboard_events)),
     )
 }
 
@@ -40,6 +48,31 @@ async fn create_dashboard(
     Ok(Json(dashboard))
 }
 
+/// Copy a dashboard with its charts, filters and layout.
+/// The copy is renamed if its name is taken.
+/// 
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard
+/// - [ApiError::NotFound]: Dashboard not found
+/// 
+async fn copy_dashboard(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(dashboard_id): Path<Id>,
+    Json(CopyDashboard { name }): Json<CopyDashboard>,
+) -> ApiResult<Json<Dashboard>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+
+    let dashboard = db::copy_dashboard(&pool, dashboard_id, name).await?;
+
+    Ok(Json(dashboard))
+}
+
 /// Update a dashboard's metadata.
 /// 
 /// # Errors
@@ -64,7 +97,7 @@ async fn update_dashboard(
     Ok(Json(dashboard))
 }
 
-/// Delete a dashboard and all of it's charts and chart axes.
+/// Move a dashboard and all of it's charts to the trash.
 /// 
 /// # Errors
 /// - [ApiError::Unauthorized]: User not authenticated
@@ -102,3 +135,78 @@ async fn get_dashboards(
 
     Ok(Json(dashboards))
 }
+
+/// Subscribe to the changes of a dashboard with Server-Sent Events.
+///
+/// Sends the events of the dashboard and its charts, and the entry and field
+/// events of the tables used by its charts.
+/// Changes made from any server instance are received.
+/// The stream ends when the dashboard is deleted or the user loses access to it.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard
+/// - [ApiError::NotFound]: Dashboard not found
+///
+async fn get_dashboard_events(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, events, .. }): State<ApiState>,
+    Path(dashboard_id): Path<Id>,
+) -> ApiResult<Sse<impl Stream<Item = Result<sse::Event, axum::Error>>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+
+    // Subscribe before fetching the charts to not miss any chart event
+    let receiver = events.subscribe();
+
+    // Maps chart IDs to their table ID
+    let mut chart_tables: HashMap<Id, Id> = db::get_charts(&pool, dashboard_id)
+        .await?
+        .into_iter()
+        .map(|chart| (chart.chart_id, chart.table_id))
+        .collect();
+
+    let has_access = move || {
+        let pool = pool.clone();
+        async move {
+            matches!(
+                db::check_dashboard_relation(&pool, user_id, dashboard_id).await,
+                Ok(Relation::Owned)
+            )
+        }
+    };
+
+    let filter = move |event: &Event| match event {
+        Event::DashboardUpdated { dashboard_id: id }
+        | Event::DashboardDeleted { dashboard_id: id } => *id == dashboard_id,
+        Event::ChartCreated {
+            dashboard_id: id,
+            chart_id,
+            table_id,
+        }
+        | Event::ChartUpdated {
+            dashboard_id: id,
+            chart_id,
+            table_id,
+        } if *id == dashboard_id => {
+            chart_tables.insert(*chart_id, *table_id);
+            true
+        }
+        Event::ChartDeleted {
+            dashboard_id: id,
+            chart_id,
+            ..
+        } if *id == dashboard_id => {
+            chart_tables.remove(chart_id);
+            true
+        }
+        event => event
+            .table_id()
+            .is_some_and(|table_id| chart_tables.values().any(|id| *id == table_id)),
+    };
+
+    Ok(event_stream(receiver, filter, has_access))
+}
@@ -0,0 +1,224 @@
+use crate::{
+    db::{self, AuthSession, Relation},
+    error::{ApiError, ApiResult, ErrorMessage},
+    model::{
+        data::FieldKind,
+        viz::{CreateFilter, Filter, FilterKind, TimeUnit, UpdateFilter},
+    },
+    routes::ApiState,
+    Id,
+};
+use axum::{
+    extract::{Path, State},
+    routing::{patch, post},
+    Json, Router,
+};
+use sqlx::PgPool;
+
+const FIELD_NOT_FOUND: ErrorMessage = ("field_id", "Field not found");
+const CHART_NOT_FOUND: ErrorMessage = ("chart_id", "Chart not found");
+const FIELD_NOT_IN_CHART: ErrorMessage = ("field_id", "Field is not in the table of the chart");
+const INVALID_FILTER_KIND: ErrorMessage = ("filter_kind", "Filter is invalid for this field");
+const INVALID_AMOUNT: ErrorMessage = ("amount", "Amount must be between 1 and 10000");
+const INVALID_TIME_ZONE: ErrorMessage = ("time_zone", "Time zone is unknown");
+const INVALID_ENUMERATION_VALUE: ErrorMessage = ("values", "Value is not in the enumeration");
+
+pub fn router() -> Router<ApiState> {
+    Router::new().nest(
+        "/dashboards/{dashboard-id}/filters",
+        Router::new()
+            .route("/", post(create_filter).get(get_filters))
+            .route("/{filter-id}", patch(update_filter).delete(delete_filter)),
+    )
+}
+
+/// Create a filter on the dashboard, or on one of its charts if a chart is specified.
+///
+/// A dashboard filter applies to every chart on the table of the field.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard
+/// - [ApiError::NotFound]: Dashboard not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [FIELD_NOT_FOUND]
+///     - [CHART_NOT_FOUND]
+///     - [FIELD_NOT_IN_CHART]
+///     - [INVALID_FILTER_KIND]
+///     - [INVALID_AMOUNT]
+///     - [INVALID_TIME_ZONE]
+///     - [INVALID_ENUMERATION_VALUE]
+///
+async fn create_filter(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState {
+        pool, chart_cache, ..
+    }): State<ApiState>,
+    Path(dashboard_id): Path<Id>,
+    Json(create_filter): Json<CreateFilter>,
+) -> ApiResult<Json<Filter>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+
+    let table_id = db::get_field_table_id(&pool, create_filter.field_id)
+        .await?
+        .ok_or(ApiError::unprocessable_entity([FIELD_NOT_FOUND]))?;
+    if !matches!(
+        db::check_table_relation(&pool, user_id, table_id).await?,
+        Relation::Owned
+    ) {
+        return Err(ApiError::unprocessable_entity([FIELD_NOT_FOUND]));
+    }
+
+    if let Some(chart_id) = create_filter.chart_id {
+        if !matches!(
+            db::check_chart_relation(&pool, dashboard_id, chart_id).await?,
+            Relation::Owned
+        ) {
+            return Err(ApiError::unprocessable_entity([CHART_NOT_FOUND]));
+        }
+        if db::get_chart_table_id(&pool, chart_id).await? != table_id {
+            return Err(ApiError::unprocessable_entity([FIELD_NOT_IN_CHART]));
+        }
+    }
+
+    let field = db::get_field(&pool, create_filter.field_id).await?;
+    validate_filter_kind(&pool, &create_filter.filter_kind, &field.field_kind).await?;
+
+    let filter = db::create_filter(&pool, dashboard_id, create_filter).await?;
+    chart_cache.invalidate_dashboard(dashboard_id);
+
+    Ok(Json(filter))
+}
+
+/// Update the condition of a filter.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard or filter
+/// - [ApiError::NotFound]: Dashboard or filter not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [INVALID_FILTER_KIND]
+///     - [INVALID_AMOUNT]
+///     - [INVALID_TIME_ZONE]
+///     - [INVALID_ENUMERATION_VALUE]
+///
+async fn update_filter(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState {
+        pool, chart_cache, ..
+    }): State<ApiState>,
+    Path((dashboard_id, filter_id)): Path<(Id, Id)>,
+    Json(update_filter): Json<UpdateFilter>,
+) -> ApiResult<Json<Filter>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+    db::check_filter_relation(&pool, dashboard_id, filter_id)
+        .await?
+        .to_api_result()?;
+
+    let filter = db::get_filter(&pool, filter_id).await?;
+    let field = db::get_field(&pool, filter.field_id).await?;
+    validate_filter_kind(&pool, &update_filter.filter_kind, &field.field_kind).await?;
+
+    let filter = db::update_filter(&pool, filter_id, update_filter).await?;
+    chart_cache.invalidate_dashboard(dashboard_id);
+
+    Ok(Json(filter))
+}
+
+/// Delete a filter.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard or filter
+/// - [ApiError::NotFound]: Dashboard or filter not found
+///
+async fn delete_filter(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState {
+        pool, chart_cache, ..
+    }): State<ApiState>,
+    Path((dashboard_id, filter_id)): Path<(Id, Id)>,
+) -> ApiResult<()> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+    db::check_filter_relation(&pool, dashboard_id, filter_id)
+        .await?
+        .to_api_result()?;
+
+    db::delete_filter(&pool, filter_id).await?;
+    chart_cache.invalidate_dashboard(dashboard_id);
+
+    Ok(())
+}
+
+/// Get all filters of the dashboard and its charts.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard
+/// - [ApiError::NotFound]: Dashboard not found
+///
+async fn get_filters(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(dashboard_id): Path<Id>,
+) -> ApiResult<Json<Vec<Filter>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+
+    let filters = db::get_filters(&pool, dashboard_id).await?;
+
+    Ok(Json(filters))
+}
+
+/// Check that the filter can be applied to a field of this kind.
+pub async fn validate_filter_kind(
+    pool: &PgPool,
+    filter_kind: &FilterKind,
+    field_kind: &FieldKind,
+) -> ApiResult<()> {
+    if !filter_kind.applies_to(field_kind) {
+        return Err(ApiError::unprocessable_entity([INVALID_FILTER_KIND]));
+    }
+
+    match (filter_kind, field_kind) {
+        (FilterKind::LastPeriod { amount, .. }, _)
+            if !(1..=TimeUnit::MAX_PERIOD_AMOUNT).contains(amount) =>
+        {
+            Err(ApiError::unprocessable_entity([INVALID_AMOUNT]))
+        }
+        (
+            FilterKind::CurrentPeriod {
+                time_zone: Some(time_zone),
+                ..
+            },
+            _,
+        ) if !db::is_valid_time_zone(pool, time_zone).await? => {
+            Err(ApiError::unprocessable_entity([INVALID_TIME_ZONE]))
+        }
+        (
+            FilterKind::Enumeration { values },
+            FieldKind::Enumeration {
+                values: enumeration,
+                ..
+            },
+        ) if !values.iter().all(|value| enumeration.contains_key(value)) => {
+            Err(ApiError::unprocessable_entity([INVALID_ENUMERATION_VALUE]))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{
+    db::{self, AuthSession},
+    error::{ApiError, ApiResult},
+    model::viz::{Breakpoint, GridPosition, LayoutItem, SetLayout},
+    routes::ApiState,
+    Id,
+};
+use axum::{
+    extract::{Path, State},
+    routing::put,
+    Json, Router,
+};
+use std::collections::{HashMap, HashSet};
+
+const INVALID_CONTENT: &str = "Item must have either a chart or a block";
+const CHART_NOT_FOUND: &str = "Chart not found in this dashboard";
+const DUPLICATE_CHART: &str = "Chart is already in the layout";
+const INVALID_POSITION: &str = "Position must be inside the columns of the breakpoint";
+const OVERLAPPING_ITEMS: &str = "Item overlaps another item at the same breakpoint";
+
+pub fn router() -> Router<ApiState> {
+    Router::new().nest(
+        "/dashboards/{dashboard-id}/layout",
+        Router::new().route("/", put(set_layout).get(get_layout)),
+    )
+}
+
+/// Set all the items of the layout of the dashboard at once.
+///
+/// Items are placed on a grid with a number of columns depending on the breakpoint,
+/// see [Breakpoint::get_columns]. An item is hidden at the breakpoints without a position.
+/// Charts of the dashboard which are not in the layout are not shown.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard
+/// - [ApiError::NotFound]: Dashboard not found
+/// - [ApiError::UnprocessableEntity]:
+///     - <item_index>: [INVALID_CONTENT]
+///     - <item_index>: [CHART_NOT_FOUND]
+///     - <item_index>: [DUPLICATE_CHART]
+///     - <item_index>: [INVALID_POSITION]
+///     - <item_index>: [OVERLAPPING_ITEMS]
+///
+async fn set_layout(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(dashboard_id): Path<Id>,
+    Json(SetLayout(items)): Json<SetLayout>,
+) -> ApiResult<Json<Vec<LayoutItem>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+
+    let chart_ids: HashSet<Id> = db::get_charts(&pool, dashboard_id)
+        .await?
+        .into_iter()
+        .map(|chart| chart.chart_id)
+        .collect();
+
+    let mut error_messages = Vec::new();
+    let mut placed_chart_ids = HashSet::new();
+    let mut placed_positions: HashMap<Breakpoint, Vec<GridPosition>> = HashMap::new();
+    for (i, item) in items.iter().enumerate() {
+        match (item.chart_id, &item.block) {
+            (Some(chart_id), None) => {
+                if !chart_ids.contains(&chart_id) {
+                    error_messages.push((i.to_string(), CHART_NOT_FOUND));
+                } else if !placed_chart_ids.insert(chart_id) {
+                    error_messages.push((i.to_string(), DUPLICATE_CHART));
+                }
+            }
+            (None, Some(_)) => {}
+            _ => error_messages.push((i.to_string(), INVALID_CONTENT)),
+        }
+
+        for (breakpoint, position) in &item.positions {
+            if !position.fits(breakpoint.get_columns()) {
+                error_messages.push((i.to_string(), INVALID_POSITION));
+                continue;
+            }
+
+            let positions = placed_positions.entry(*breakpoint).or_default();
+            if positions.iter().any(|other| position.overlaps(other)) {
+                error_messages.push((i.to_string(), OVERLAPPING_ITEMS));
+            }
+            positions.push(*position);
+        }
+    }
+
+    if !error_messages.is_empty() {
+        return Err(ApiError::unprocessable_entity(error_messages));
+    }
+
+    let items = db::set_layout(&pool, dashboard_id, items).await?;
+
+    Ok(Json(items))
+}
+
+/// Get the items of the layout of the dashboard.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard
+/// - [ApiError::NotFound]: Dashboard not found
+///
+async fn get_layout(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(dashboard_id): Path<Id>,
+) -> ApiResult<Json<Vec<LayoutItem>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+
+    let items = db::get_layout(&pool, dashboard_id).await?;
+
+    Ok(Json(items))
+}
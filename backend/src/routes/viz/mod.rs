@@ -2,9 +2,12 @@
 //!
 //! Users must be authenticated for all requests.
 
-mod axes;
+pub(super) mod axes;
 mod charts;
 mod dashboards;
+mod filters;
+mod layout;
+mod reports;
 
 use super::ApiState;
 use axum::Router;
@@ -14,4 +17,7 @@ pub fn router() -> Router<ApiState> {
         .merge(dashboards::router())
         .merge(charts::router())
         .merge(axes::router())
+        .merge(filters::router())
+        .merge(layout::router())
+        .merge(reports::router())
 }
@@ -0,0 +1,243 @@
+use crate::{
+    db::{self, AuthSession},
+    error::{ApiError, ApiResult, ErrorMessage},
+    model::viz::{CreateReport, Report, ReportRun, UpdateReport},
+    routes::ApiState,
+    Id,
+};
+use axum::{
+    extract::{Path, State},
+    routing::{patch, post},
+    Json, Router,
+};
+use sqlx::PgPool;
+
+const RECIPIENTS_MISSING: ErrorMessage = ("recipients", "At least one recipient is required");
+const INVALID_RECIPIENT: ErrorMessage = ("recipients", "Recipient is not a valid email address");
+const INVALID_TIME_ZONE: ErrorMessage = ("time_zone", "Time zone is unknown");
+
+/// Number of runs returned in the run log.
+const RUN_LOG_LIMIT: i64 = 100;
+
+pub fn router() -> Router<ApiState> {
+    Router::new().nest(
+        "/dashboards/{dashboard-id}/reports",
+        Router::new()
+            .route("/", post(create_report).get(get_reports))
+            .route("/{report-id}", patch(update_report).delete(delete_report))
+            .route(
+                "/{report-id}/runs",
+                post(create_report_run).get(get_report_runs),
+            ),
+    )
+}
+
+/// Schedule a report of the dashboard sent by email to the recipients.
+///
+/// The first run is at `next_run_at` and the next runs are repeated at the frequency,
+/// at the same local time in the time zone of the report.
+/// Every run sends a PNG image of the bar, line and table charts
+/// and a CSV export of the data of every chart.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard
+/// - [ApiError::NotFound]: Dashboard not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [RECIPIENTS_MISSING]
+///     - [INVALID_RECIPIENT]
+///     - [INVALID_TIME_ZONE]
+///
+async fn create_report(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(dashboard_id): Path<Id>,
+    Json(create_report): Json<CreateReport>,
+) -> ApiResult<Json<Report>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+
+    validate_report(
+        &pool,
+        &create_report.recipients,
+        create_report.time_zone.as_deref(),
+    )
+    .await?;
+
+    let report = db::create_report(&pool, dashboard_id, create_report).await?;
+
+    Ok(Json(report))
+}
+
+/// Update the recipients, schedule and activation of a report.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard or report
+/// - [ApiError::NotFound]: Dashboard or report not found
+/// - [ApiError::UnprocessableEntity]:
+///     - [RECIPIENTS_MISSING]
+///     - [INVALID_RECIPIENT]
+///     - [INVALID_TIME_ZONE]
+///
+async fn update_report(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((dashboard_id, report_id)): Path<(Id, Id)>,
+    Json(update_report): Json<UpdateReport>,
+) -> ApiResult<Json<Report>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+    db::check_report_relation(&pool, dashboard_id, report_id)
+        .await?
+        .to_api_result()?;
+
+    validate_report(
+        &pool,
+        &update_report.recipients,
+        update_report.time_zone.as_deref(),
+    )
+    .await?;
+
+    let report = db::update_report(&pool, report_id, update_report).await?;
+
+    Ok(Json(report))
+}
+
+/// Delete a report and its run log.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard or report
+/// - [ApiError::NotFound]: Dashboard or report not found
+///
+async fn delete_report(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((dashboard_id, report_id)): Path<(Id, Id)>,
+) -> ApiResult<()> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+    db::check_report_relation(&pool, dashboard_id, report_id)
+        .await?
+        .to_api_result()?;
+
+    db::delete_report(&pool, report_id).await?;
+
+    Ok(())
+}
+
+/// Get all reports of a dashboard.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard
+/// - [ApiError::NotFound]: Dashboard not found
+///
+async fn get_reports(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path(dashboard_id): Path<Id>,
+) -> ApiResult<Json<Vec<Report>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+
+    let reports = db::get_reports(&pool, dashboard_id).await?;
+
+    Ok(Json(reports))
+}
+
+/// Send a report now, outside of its schedule.
+/// The run is sent in the background like the scheduled runs.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard or report
+/// - [ApiError::NotFound]: Dashboard or report not found
+///
+async fn create_report_run(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((dashboard_id, report_id)): Path<(Id, Id)>,
+) -> ApiResult<Json<ReportRun>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+    db::check_report_relation(&pool, dashboard_id, report_id)
+        .await?
+        .to_api_result()?;
+
+    let run = db::create_report_run(&pool, report_id).await?;
+
+    Ok(Json(run))
+}
+
+/// Get the most recent runs of a report, including pending and failed runs.
+///
+/// # Errors
+/// - [ApiError::Unauthorized]: User not authenticated
+/// - [ApiError::Forbidden]: User does not have access to this dashboard or report
+/// - [ApiError::NotFound]: Dashboard or report not found
+///
+async fn get_report_runs(
+    AuthSession { user, .. }: AuthSession,
+    State(ApiState { pool, .. }): State<ApiState>,
+    Path((dashboard_id, report_id)): Path<(Id, Id)>,
+) -> ApiResult<Json<Vec<ReportRun>>> {
+    let user_id = user.ok_or(ApiError::Unauthorized)?.user_id;
+
+    db::check_dashboard_relation(&pool, user_id, dashboard_id)
+        .await?
+        .to_api_result()?;
+    db::check_report_relation(&pool, dashboard_id, report_id)
+        .await?
+        .to_api_result()?;
+
+    let runs = db::get_report_runs(&pool, report_id, RUN_LOG_LIMIT).await?;
+
+    Ok(Json(runs))
+}
+
+/// Validates the recipients and time zone of a report request.
+async fn validate_report(
+    pool: &PgPool,
+    recipients: &[String],
+    time_zone: Option<&str>,
+) -> ApiResult<()> {
+    let mut error_messages = Vec::new();
+
+    if recipients.is_empty() {
+        error_messages.push(RECIPIENTS_MISSING);
+    }
+    if recipients
+        .iter()
+        .any(|recipient| recipient.parse::<lettre::Address>().is_err())
+    {
+        error_messages.push(INVALID_RECIPIENT);
+    }
+    if let Some(time_zone) = time_zone {
+        if !db::is_valid_time_zone(pool, time_zone).await? {
+            error_messages.push(INVALID_TIME_ZONE);
+        }
+    }
+
+    if error_messages.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::unprocessable_entity(error_messages))
+    }
+}
@@ -0,0 +1,36 @@
+use crate::{cache::ChartCache, db, model::events::Event};
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::error;
+
+const REFRESH_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Invalidate the cached chart data on the change events of its tables and charts.
+pub async fn invalidate_chart_cache(cache: ChartCache, mut receiver: broadcast::Receiver<Event>) {
+    loop {
+        match receiver.recv().await {
+            Ok(event) => cache.invalidate(&event),
+            // The missed events could have invalidated any chart
+            Err(RecvError::Lagged(_)) => cache.clear(),
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+/// Refresh the materialized views of the charts when their refresh interval is elapsed.
+pub async fn refresh_charts(pool: PgPool) {
+    loop {
+        match db::get_stale_chart_ids(&pool).await {
+            Ok(chart_ids) => {
+                for chart_id in chart_ids {
+                    if let Err(e) = db::refresh_chart_view(&pool, chart_id).await {
+                        error!("Chart {chart_id} refresh error: {e:?}");
+                    }
+                }
+            }
+            Err(e) => error!("Chart refresh error: {e:?}"),
+        }
+        tokio::time::sleep(REFRESH_POLL_INTERVAL).await;
+    }
+}
@@ -0,0 +1,338 @@
+use crate::{
+    config::Limits,
+    db, io,
+    model::{
+        data::{CreateTableData, FieldMetadata},
+        jobs::{ClaimedJob, JobKind, JobOutput},
+        notifications::NotificationKind,
+    },
+    Id,
+};
+use anyhow::{bail, Context};
+use itertools::Itertools;
+use sqlx::PgPool;
+use std::{fmt, io::Cursor, time::Duration};
+use tokio::sync::oneshot;
+use tracing::error;
+use umya_spreadsheet::{reader::xlsx, writer};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+/// A running job without heartbeat for this long was interrupted by a restart.
+const LEASE: Duration = Duration::from_secs(60);
+/// Number of attempts after which an interrupted job is marked as failed.
+const MAX_ATTEMPTS: i32 = 3;
+/// Number of entries imported per insert, the progress is updated after each chunk.
+const IMPORT_CHUNK_SIZE: usize = 1000;
+
+/// The input and result files of the finished jobs are dropped after this long.
+const FILE_RETENTION: Duration = Duration::from_secs(7 * 24 * 3600);
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+const TABLE_LIMIT: JobError = JobError("The maximum number of tables is reached");
+const FIELD_LIMIT: JobError = JobError("The maximum number of fields of the table is reached");
+const ROW_LIMIT: JobError = JobError("The maximum number of rows of the table is reached");
+const INVALID_EXCEL: JobError = JobError("The file is not a valid Excel spreadsheet");
+const INVALID_CSV: JobError = JobError("The file is not a valid CSV file");
+const SERVER_ERROR: &str = "The job failed because of a server error";
+
+const EXCEL_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+const CSV_CONTENT_TYPE: &str = "text/csv";
+
+/// Error of a job shown to its user.
+/// The other errors are logged and shown as [SERVER_ERROR].
+#[derive(Debug)]
+struct JobError(&'static str);
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for JobError {}
+
+/// Run the jobs of the queue on a pool of workers, and drop the files of the old jobs.
+/// Imports fail if the created tables exceed the resource limits.
+pub async fn run_jobs(pool: PgPool, workers: usize, limits: Limits) {
+    for _ in 0..workers {
+        tokio::spawn(run_worker(pool.clone(), limits));
+    }
+    tokio::spawn(drop_expired_files(pool));
+}
+
+async fn drop_expired_files(pool: PgPool) {
+    loop {
+        if let Err(e) = db::drop_expired_job_files(&pool, FILE_RETENTION.as_secs_f64()).await {
+            error!("Job file cleanup error: {e:?}");
+        }
+        tokio::time::sleep(CLEANUP_INTERVAL).await;
+    }
+}
+
+async fn run_worker(pool: PgPool, limits: Limits) {
+    loop {
+        match run_next(&pool, &limits).await {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(e) => error!("Job error: {e:?}"),
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Claim and run the next job, and return whether a job was claimed.
+///
+/// A heartbeat is sent while the job runs, and the job is stopped when it is cancelled,
+/// which rolls back the changes of an import.
+async fn run_next(pool: &PgPool, limits: &Limits) -> sqlx::Result<bool> {
+    for job in db::fail_abandoned_jobs(pool, LEASE.as_secs_f64(), MAX_ATTEMPTS).await? {
+        notify(pool, &job.job.0, job.job_id, job.user_id, false).await?;
+    }
+
+    let Some(job) = db::claim_job(pool, LEASE.as_secs_f64(), MAX_ATTEMPTS).await? else {
+        return Ok(false);
+    };
+
+    let (cancel_sender, cancel_receiver) = oneshot::channel();
+    let heartbeat = tokio::spawn(send_heartbeats(
+        pool.clone(),
+        job.job_id,
+        job.attempts,
+        cancel_sender,
+    ));
+
+    let result = tokio::select! {
+        result = run_job(pool, limits, &job) => Some(result),
+        _ = cancel_receiver => None,
+    };
+    heartbeat.abort();
+
+    match result {
+        Some(Ok(true)) => notify(pool, &job.job.0, job.job_id, job.user_id, true).await?,
+        Some(Err(e)) => {
+            let message = match e.downcast_ref::<JobError>() {
+                Some(job_error) => job_error.to_string(),
+                None => {
+                    error!("Job {} error: {e:?}", job.job_id);
+                    SERVER_ERROR.to_string()
+                }
+            };
+            if db::fail_job(pool, job.job_id, job.attempts, message).await? {
+                notify(pool, &job.job.0, job.job_id, job.user_id, false).await?;
+            }
+        }
+        // Cancelled or claimed again
+        Some(Ok(false)) | None => {}
+    }
+
+    Ok(true)
+}
+
+/// Send the heartbeats of a running job until it is cancelled or claimed again.
+/// The heartbeats are sent from their own task, so they are never blocked
+/// by the transaction of the job.
+async fn send_heartbeats(pool: PgPool, job_id: Id, attempts: i32, cancel: oneshot::Sender<()>) {
+    loop {
+        tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+        match db::beat_job(&pool, job_id, attempts).await {
+            Ok(true) => {}
+            Ok(false) => {
+                _ = cancel.send(());
+                return;
+            }
+            Err(e) => error!("Job {job_id} heartbeat error: {e:?}"),
+        }
+    }
+}
+
+/// Notify the user that a job completed or failed.
+async fn notify(
+    pool: &PgPool,
+    job: &JobKind,
+    job_id: Id,
+    user_id: Id,
+    completed: bool,
+) -> sqlx::Result<()> {
+    let job = job.clone();
+    let notification = if completed {
+        NotificationKind::JobCompleted { job_id, job }
+    } else {
+        NotificationKind::JobFailed { job_id, job }
+    };
+    db::create_notifications(pool, &[user_id], &notification).await
+}
+
+/// Run a job and return whether it was completed.
+/// The spreadsheets and CSV files are parsed and written on the blocking thread pool.
+async fn run_job(pool: &PgPool, limits: &Limits, job: &ClaimedJob) -> anyhow::Result<bool> {
+    let input = job.input.clone().unwrap_or_default();
+
+    match &job.job.0 {
+        JobKind::ImportExcel => {
+            let create_tables = tokio::task::spawn_blocking(move || {
+                let spreadsheet =
+                    xlsx::read_reader(Cursor::new(input), true).context(INVALID_EXCEL)?;
+                anyhow::Ok(io::import_table_from_excel(spreadsheet))
+            })
+            .await??;
+
+            import_tables(pool, limits, job, create_tables).await
+        }
+        JobKind::ImportCsv { name } => {
+            let name = name.clone();
+            let create_table = tokio::task::spawn_blocking(move || {
+                io::import_table_from_csv(csv::Reader::from_reader(Cursor::new(input)), &name)
+                    .context(INVALID_CSV)
+            })
+            .await??;
+
+            import_tables(pool, limits, job, vec![create_table]).await
+        }
+        JobKind::ExportExcel { table_id } => {
+            let table_data = db::get_table_data(pool, *table_id).await?;
+            let file_name = format!("{}.xlsx", table_data.table.name);
+
+            let buffer = tokio::task::spawn_blocking(move || {
+                let mut spreadsheet = if input.is_empty() {
+                    umya_spreadsheet::new_file_empty_worksheet()
+                } else {
+                    xlsx::read_reader(Cursor::new(input), true).context(INVALID_EXCEL)?
+                };
+                io::export_table_to_excel(&mut spreadsheet, table_data);
+
+                let mut buffer = Vec::new();
+                writer::xlsx::write_writer(&spreadsheet, Cursor::new(&mut buffer))?;
+                anyhow::Ok(buffer)
+            })
+            .await??;
+
+            let output = JobOutput::Exported {
+                file_name,
+                content_type: EXCEL_CONTENT_TYPE.to_string(),
+            };
+            Ok(
+                db::complete_job(pool, job.job_id, job.attempts, Some(output), Some(buffer))
+                    .await?,
+            )
+        }
+        JobKind::ExportCsv { table_id } => {
+            let table_data = db::get_table_data(pool, *table_id).await?;
+            let file_name = format!("{}.csv", table_data.table.name);
+
+            let buffer = tokio::task::spawn_blocking(move || {
+                let mut buffer = Vec::new();
+                io::export_table_to_csv(
+                    csv::Writer::from_writer(Cursor::new(&mut buffer)),
+                    table_data,
+                )?;
+                anyhow::Ok(buffer)
+            })
+            .await??;
+
+            let output = JobOutput::Exported {
+                file_name,
+                content_type: CSV_CONTENT_TYPE.to_string(),
+            };
+            Ok(
+                db::complete_job(pool, job.job_id, job.attempts, Some(output), Some(buffer))
+                    .await?,
+            )
+        }
+        JobKind::RefreshChart { chart_id, .. } => {
+            db::refresh_chart_view(pool, *chart_id).await?;
+
+            Ok(db::complete_job(pool, job.job_id, job.attempts, None, None).await?)
+        }
+    }
+}
+
+/// Create the imported tables in one transaction, which is committed
+/// only if the job is completed.
+///
+/// The tables are reserved by the job before the transaction, under the lock of the table limit,
+/// so the creation of other tables of the user only waits for the reservation.
+async fn import_tables(
+    pool: &PgPool,
+    limits: &Limits,
+    job: &ClaimedJob,
+    create_tables: Vec<CreateTableData>,
+) -> anyhow::Result<bool> {
+    for create_table in &create_tables {
+        if create_table.fields.len() as i64 > limits.max_fields_per_table {
+            bail!(FIELD_LIMIT);
+        }
+        if create_table.entries.len() as i64 > limits.max_rows_per_table {
+            bail!(ROW_LIMIT);
+        }
+    }
+
+    let total = create_tables
+        .iter()
+        .map(|create_table| create_table.entries.len())
+        .sum::<usize>()
+        .max(1);
+    let mut imported = 0;
+
+    let mut tx = pool.begin().await?;
+
+    db::lock_user_tables(tx.as_mut(), job.user_id).await?;
+    let tables = db::get_table_count(tx.as_mut(), job.user_id).await?;
+    if tables + create_tables.len() as i64 > limits.max_tables_per_user {
+        bail!(TABLE_LIMIT);
+    }
+    let reserved = create_tables.len() as i32;
+    if !db::reserve_job_tables(tx.as_mut(), job.job_id, job.attempts, reserved).await? {
+        return Ok(false);
+    }
+
+    tx.commit().await?;
+
+    let mut tx = pool.begin().await?;
+
+    let mut table_ids = Vec::new();
+    for CreateTableData {
+        table,
+        fields,
+        entries,
+    } in create_tables
+    {
+        let table = db::create_table(tx.as_mut(), job.user_id, table).await?;
+        let fields = db::create_fields(tx.as_mut(), table.table_id, job.user_id, fields).await?;
+
+        for chunk in entries.chunks(IMPORT_CHUNK_SIZE) {
+            db::create_entries(
+                tx.as_mut(),
+                table.table_id,
+                None,
+                fields
+                    .iter()
+                    .map(|field| FieldMetadata::from_field(field.clone()))
+                    .collect_vec(),
+                chunk
+                    .iter()
+                    .map(|cells| cells.iter().cloned().map(Some).collect())
+                    .collect(),
+            )
+            .await?;
+
+            imported += chunk.len();
+            let progress = imported as f64 / total as f64;
+            if !db::set_job_progress(pool, job.job_id, job.attempts, progress).await? {
+                return Ok(false);
+            }
+        }
+
+        table_ids.push(table.table_id);
+    }
+
+    let output = JobOutput::Imported { table_ids };
+    let completed =
+        db::complete_job(tx.as_mut(), job.job_id, job.attempts, Some(output), None).await?;
+    if completed {
+        tx.commit().await?;
+    }
+
+    Ok(completed)
+}
@@ -0,0 +1,16 @@
+//! This module contains the background tasks running inside the server process.
+//!
+//! Tasks are spawned once by [crate::routes::create_app] and run forever.
+//! They must handle their own errors since nothing awaits them.
+
+mod charts;
+mod jobs;
+mod reports;
+mod trash;
+mod webhooks;
+
+pub use charts::*;
+pub use jobs::*;
+pub use reports::*;
+pub use trash::*;
+pub use webhooks::*;
@@ -0,0 +1,182 @@
+use crate::{
+    db,
+    io::{export_chart_to_csv, render_chart_png, RENDERED_CHART_KINDS},
+    model::{
+        notifications::NotificationKind,
+        viz::{PendingReportRun, ReportRunStatus},
+    },
+};
+use lettre::{
+    message::{header::ContentType, Attachment, Mailbox, MultiPart, SinglePart},
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use sqlx::PgPool;
+use std::{collections::HashMap, fmt::Write, time::Duration};
+use tracing::error;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// Reports are rendered and sent one after the other, so batches are kept small.
+const BATCH_SIZE: i64 = 4;
+/// Time after which a claimed run without result is claimed again,
+/// longer than rendering and sending a whole batch.
+const CLAIM_LEASE: Duration = Duration::from_secs(600);
+
+/// Number of attempts after which a run is marked as failed.
+const MAX_ATTEMPTS: i32 = 6;
+/// Delay before the first retry, doubled after every failed attempt.
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// SMTP client sending the report emails.
+#[derive(Clone)]
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    /// Create a mailer from an SMTP URL and the sender address of the emails.
+    ///
+    /// The URL follows the format of [AsyncSmtpTransport::from_url],
+    /// `smtp://localhost:1025` connects without TLS to a local SMTP sink.
+    pub fn new(smtp_url: &str, from: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            transport: AsyncSmtpTransport::<Tokio1Executor>::from_url(smtp_url)?.build(),
+            from: from.parse()?,
+        })
+    }
+}
+
+/// Add the runs of the reports when they are due and send the pending runs.
+pub async fn send_reports(pool: PgPool, mailer: Mailer) {
+    loop {
+        if let Err(e) = db::schedule_report_runs(&pool).await {
+            error!("Report scheduling error: {e:?}");
+        }
+        match send_pending(&pool, &mailer).await {
+            Ok(count) if count > 0 => continue,
+            Ok(_) => {}
+            Err(e) => error!("Report sending error: {e:?}"),
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Send one batch of pending runs and return the number of runs attempted.
+///
+/// The runs are claimed before they are sent, so no transaction
+/// is open while the reports are rendered and sent.
+async fn send_pending(pool: &PgPool, mailer: &Mailer) -> sqlx::Result<usize> {
+    let runs = db::claim_pending_report_runs(pool, BATCH_SIZE, CLAIM_LEASE.as_secs_f64()).await?;
+
+    for run in &runs {
+        let result = match build_message(pool, mailer, run).await {
+            Ok(message) => mailer
+                .transport
+                .send(message)
+                .await
+                .map_err(anyhow::Error::from),
+            Err(e) => Err(e),
+        };
+
+        let (status, last_error) = match result {
+            Ok(_) => (ReportRunStatus::Sent, None),
+            Err(e) if run.attempts + 1 >= MAX_ATTEMPTS => {
+                (ReportRunStatus::Failed, Some(e.to_string()))
+            }
+            Err(e) => (ReportRunStatus::Pending, Some(e.to_string())),
+        };
+
+        let mut tx = pool.begin().await?;
+
+        let is_recorded = db::set_report_run_result(
+            tx.as_mut(),
+            run.run_id,
+            run.attempts,
+            status,
+            last_error,
+            retry_delay(run.attempts).as_secs_f64(),
+        )
+        .await?;
+
+        if is_recorded && status == ReportRunStatus::Failed {
+            db::create_notifications(
+                tx.as_mut(),
+                &[run.user_id],
+                &NotificationKind::ReportFailed {
+                    dashboard_id: run.dashboard_id,
+                    report_id: run.report_id,
+                    run_id: run.run_id,
+                },
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+    }
+
+    Ok(runs.len())
+}
+
+/// Build the email of a report run, with a CSV export of every chart
+/// and a PNG image of the charts which can be rendered on the blocking thread pool.
+async fn build_message(
+    pool: &PgPool,
+    mailer: &Mailer,
+    run: &PendingReportRun,
+) -> anyhow::Result<Message> {
+    let dashboard = db::get_dashboard(pool, run.dashboard_id).await?;
+    let charts = db::get_charts(pool, run.dashboard_id).await?;
+
+    let mut text = format!("{}\n", dashboard.name);
+    if !dashboard.description.is_empty() {
+        writeln!(text, "{}", dashboard.description)?;
+    }
+    writeln!(text)?;
+
+    let mut attachments = Vec::new();
+    for chart in charts {
+        let chart_data = db::get_chart_data(pool, chart.chart_id, &HashMap::new()).await?;
+
+        writeln!(
+            text,
+            "- {}: {} rows, computed at {}",
+            chart.name,
+            chart_data.cells.len(),
+            chart_data.computed_at.to_rfc3339()
+        )?;
+
+        let mut csv = Vec::new();
+        export_chart_to_csv(csv::Writer::from_writer(&mut csv), &chart_data)?;
+
+        if RENDERED_CHART_KINDS.contains(&chart.chart_kind) {
+            let png = tokio::task::spawn_blocking(move || render_chart_png(&chart_data)).await??;
+            attachments.push(
+                Attachment::new(format!("{}.png", chart.name))
+                    .body(png, ContentType::parse("image/png")?),
+            );
+        }
+        attachments.push(
+            Attachment::new(format!("{}.csv", chart.name))
+                .body(csv, ContentType::parse("text/csv")?),
+        );
+    }
+
+    let mut builder = Message::builder()
+        .from(mailer.from.clone())
+        .subject(format!("{} report", dashboard.name));
+    for recipient in &run.recipients {
+        builder = builder.to(recipient.parse()?);
+    }
+
+    let body = attachments.into_iter().fold(
+        MultiPart::mixed().singlepart(SinglePart::plain(text)),
+        |body, attachment| body.singlepart(attachment),
+    );
+
+    Ok(builder.multipart(body)?)
+}
+
+/// Exponential backoff delay after a failed attempt.
+fn retry_delay(attempts: i32) -> Duration {
+    BASE_RETRY_DELAY * 2u32.pow(attempts.clamp(0, MAX_ATTEMPTS) as u32)
+}
@@ -0,0 +1,29 @@
+use crate::{db, model::trash::TrashItemKind};
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::error;
+
+const PURGE_POLL_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Permanently delete the items which have been in the trash for longer than the retention period.
+pub async fn purge_trash(pool: PgPool, retention_days: i32) {
+    loop {
+        match db::get_expired_trash(&pool, retention_days).await {
+            Ok(items) => {
+                for (item_kind, item_id) in items {
+                    let result = match item_kind {
+                        TrashItemKind::Table => db::purge_table(&pool, item_id).await,
+                        TrashItemKind::Field => db::purge_field(&pool, item_id).await,
+                        TrashItemKind::Dashboard => db::purge_dashboard(&pool, item_id).await,
+                        TrashItemKind::Chart => db::purge_chart(&pool, item_id).await,
+                    };
+                    if let Err(e) = result {
+                        error!("{item_kind:?} {item_id} purge error: {e:?}");
+                    }
+                }
+            }
+            Err(e) => error!("Trash purge error: {e:?}"),
+        }
+        tokio::time::sleep(PURGE_POLL_INTERVAL).await;
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{
+    db,
+    model::data::{DeliveryStatus, PendingDelivery},
+};
+use anyhow::bail;
+use hmac::{Hmac, Mac};
+use itertools::Itertools;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect,
+};
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::{net::IpAddr, sync::Arc, time::Duration};
+use tracing::error;
+
+/// Header containing the hex encoded HMAC-SHA256 of the body, prefixed by `sha256=`.
+const SIGNATURE_HEADER: &str = "X-Chronicle-Signature";
+const EVENT_HEADER: &str = "X-Chronicle-Event";
+const DELIVERY_HEADER: &str = "X-Chronicle-Delivery";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const BATCH_SIZE: i64 = 16;
+/// Time after which a claimed delivery without result is claimed again,
+/// longer than sending a whole batch.
+const CLAIM_LEASE: Duration = Duration::from_secs(300);
+
+/// Number of attempts after which a delivery is marked as failed.
+const MAX_ATTEMPTS: i32 = 8;
+/// Delay before the first retry, doubled after every failed attempt.
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Send the pending deliveries of the webhook outbox.
+pub async fn deliver_webhooks(pool: PgPool) {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .dns_resolver(Arc::new(PublicResolver))
+        // A redirect could lead to a private address
+        .redirect(redirect::Policy::none())
+        .build()
+        .expect("webhook HTTP client should build");
+
+    loop {
+        match deliver_pending(&pool, &client).await {
+            Ok(count) if count > 0 => continue,
+            Ok(_) => {}
+            Err(e) => error!("Webhook delivery error: {e:?}"),
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Send one batch of pending deliveries and return the number of deliveries attempted.
+///
+/// The deliveries are claimed before they are sent, so no transaction
+/// is open during the requests.
+async fn deliver_pending(pool: &PgPool, client: &reqwest::Client) -> sqlx::Result<usize> {
+    let deliveries =
+        db::claim_pending_deliveries(pool, BATCH_SIZE, CLAIM_LEASE.as_secs_f64()).await?;
+
+    for delivery in &deliveries {
+        let (status, response_status, last_error) = match send(client, delivery).await {
+            Ok(response) if response.status().is_success() => {
+                (DeliveryStatus::Delivered, Some(response.status()), None)
+            }
+            Ok(response) => (
+                DeliveryStatus::Pending,
+                Some(response.status()),
+                Some(format!("Unexpected response status {}", response.status())),
+            ),
+            Err(e) => (DeliveryStatus::Pending, None, Some(e.to_string())),
+        };
+
+        let status = if status == DeliveryStatus::Pending && delivery.attempts + 1 >= MAX_ATTEMPTS {
+            DeliveryStatus::Failed
+        } else {
+            status
+        };
+
+        db::set_delivery_result(
+            pool,
+            delivery.delivery_id,
+            delivery.attempts,
+            status,
+            response_status.map(|status| status.as_u16().into()),
+            last_error,
+            retry_delay(delivery.attempts).as_secs_f64(),
+        )
+        .await?;
+    }
+
+    Ok(deliveries.len())
+}
+
+async fn send(
+    client: &reqwest::Client,
+    delivery: &PendingDelivery,
+) -> anyhow::Result<reqwest::Response> {
+    // IP addresses in the URL are not resolved
+    let url = reqwest::Url::parse(&delivery.url)?;
+    if let Some(ip) = url.host_str().and_then(parse_ip) {
+        if !is_public_address(ip) {
+            bail!("{ip} is not a public address");
+        }
+    }
+
+    let body = delivery.payload.0.to_string();
+
+    Ok(client
+        .post(&delivery.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, sign(&delivery.secret, &body))
+        .header(EVENT_HEADER, format!("{:?}", delivery.event))
+        .header(DELIVERY_HEADER, delivery.delivery_id)
+        .body(body)
+        .send()
+        .await?)
+}
+
+/// Resolves the hosts of the webhooks to their public addresses only,
+/// so deliveries can not reach the network of the server.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_address(addr.ip()))
+                .collect_vec();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Parse the host of a URL as an IP address, IPv6 addresses being in brackets.
+pub fn parse_ip(host: &str) -> Option<IpAddr> {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
+
+/// Whether an address is reachable on the internet, and not a loopback,
+/// private, link-local, shared or otherwise reserved address.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || a == 0
+                // Shared address space of carrier-grade NAT
+                || (a == 100 && b & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_address(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local addresses
+                    || first & 0xfe00 == 0xfc00
+                    // Link-local addresses
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Sign the body with the webhook secret.
+fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC should accept keys of any size");
+    mac.update(body.as_bytes());
+
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    format!("sha256={hex}")
+}
+
+/// Exponential backoff delay after a failed attempt.
+fn retry_delay(attempts: i32) -> Duration {
+    BASE_RETRY_DELAY * 2u32.pow(attempts.clamp(0, MAX_ATTEMPTS) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_addresses() {
+        for ip in ["93.184.215.14", "8.8.8.8", "2606:4700::1111"] {
+            assert!(is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn private_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn parse_url_hosts() {
+        assert_eq!(parse_ip("[::1]"), Some("::1".parse().unwrap()));
+        assert_eq!(parse_ip("10.0.0.1"), Some("10.0.0.1".parse().unwrap()));
+        assert_eq!(parse_ip("example.com"), None);
+    }
+}
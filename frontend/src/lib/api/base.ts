@@ -29,6 +29,16 @@ export const GET = async <T,>(endpoint: string): Promise<T> => fetch(API_URL + e
   credentials: "include"
 }).then(handleResponse<T>);
 
+/**
+ * Send a GET request for a file
+ * @param {string} endpoint - The API endpoint to which the GET request will be sent
+ * @returns {Blob} - The file, with the content type of the response
+ */
+export const GET_FILE = async (endpoint: string): Promise<Blob> => fetch(API_URL + endpoint, {
+  method: "GET",
+  credentials: "include"
+}).then(response => response.ok ? response.blob() : handleResponse<Blob>(response));
+
 /**
  * Send a POST request
  * @param {string} endpoint - The API endpoint to which the POST request will be sent
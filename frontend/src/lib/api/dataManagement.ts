@@ -1,5 +1,6 @@
 import { GET, POST, PATCH, DELETE, hydrateJSONTableData, POST_FORM } from "./base.js";
-import { type Table, type TableData, type Field, type Entry, } from "../types";
+import { getJobResult, waitForJob } from "./jobs.js";
+import { type Table, type TableData, type Field, type Entry, type Job, } from "../types";
 
 //
 // Data Management
@@ -16,24 +17,31 @@ export const postCreateTable = async (table: Table): Promise<Table> => POST<Tabl
   description: table.description,
 });
 
-export const postImportTable = async (table: File): Promise<Table> => {
+export const postImportTable = async (table: File): Promise<number[]> => {
   let form = new FormData();
 
   form.append("file", table)
 
+  let job: Job;
   if (table.type === "text/csv") {
-    return POST_FORM<Table>("/tables/csv", form);
+    job = await POST_FORM<Job>("/tables/csv", form);
   } else if (table.type === "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet") {
-    return POST_FORM<Table>("/tables/excel", form);
+    job = await POST_FORM<Job>("/tables/excel", form);
   } else {
     throw { body: "Unsupported format" };
   }
+
+  const { output } = await waitForJob(job);
+  return output?.type === "Imported" ? output.table_ids : [];
 }
 
-export const postExportTable = async (table: Table, type: "csv" | "excel"): Promise<Blob> => {
+export const postExportTable = async (table: Table, type: "csv" | "excel"): Promise<File> => {
   let form = new FormData();
   form.append("file", new Blob())
-  return POST_FORM<Blob>(`/tables/${table.table_id}/${type}`, form);
+  const job = await waitForJob(await POST_FORM<Job>(`/tables/${table.table_id}/${type}`, form));
+  const blob = await getJobResult(job.job_id);
+  const fileName = job.output?.type === "Exported" ? job.output.file_name : table.name;
+  return new File([blob], fileName, { type: blob.type });
 }
 
 
@@ -50,12 +58,14 @@ export const getFields = async (table: Table): Promise<Field[]> => GET<Field[]>(
 
 export const postField = async (field: Field): Promise<Field> => POST<Field>(`/tables/${field.table_id}/fields`, {
   name: field.name,
-  field_kind: field.field_kind
+  field_kind: field.field_kind,
+  default_value: field.default_value ?? null
 });
 
 export const patchField = async (field: Field): Promise<Field> => PATCH<Field>(`/tables/${field.table_id}/fields/${field.field_id}`, {
   name: field.name,
-  field_kind: field.field_kind
+  field_kind: field.field_kind,
+  default_value: field.default_value ?? null
 });
 
 export const deleteField = async (field: Field): Promise<void> => DELETE(`/tables/${field.table_id}/fields/${field.field_id}`);
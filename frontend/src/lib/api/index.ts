@@ -1,3 +1,4 @@
 export * from "./dataManagement.js";
 export * from "./dashboard.js";
+export * from "./jobs.js";
 export { type APIError } from "./base.js";
@@ -0,0 +1,31 @@
+import { GET, GET_FILE, type APIError } from "./base.js";
+import { type Job, JobStatus } from "../types";
+
+// Delay between two polls of a running job, in milliseconds
+const JOB_POLL_INTERVAL = 1000;
+
+// Job methods
+export const getJob = async (jobId: number): Promise<Job> => GET<Job>(`/jobs/${jobId}`);
+
+export const getJobResult = async (jobId: number): Promise<Blob> => GET_FILE(`/jobs/${jobId}/result`);
+
+/**
+ * Poll a job until it is finished
+ * @param {Job} job - The queued job
+ * @returns {Job} - The completed job, or throws an APIError with the error of the failed job
+ */
+export const waitForJob = async (job: Job): Promise<Job> => {
+  while (job.status === JobStatus.Pending || job.status === JobStatus.Running) {
+    await new Promise((resolve) => setTimeout(resolve, JOB_POLL_INTERVAL));
+    job = await getJob(job.job_id);
+  }
+
+  if (job.status !== JobStatus.Completed) {
+    throw {
+      status: 422,
+      body: job.error ?? `Job ${job.status.toLowerCase()}`,
+    } as APIError;
+  }
+
+  return job;
+}
@@ -17,6 +17,7 @@ export type Field = {
   name: string;
   ordering: number;
   field_kind: FieldKind;
+  default_value?: Cell | null;
   updated_at?: Date;
 };
 
@@ -74,7 +75,6 @@ export type EnumerationKind = {
   type: FieldType.Enumeration;
   is_required: boolean;
   values: { [key: number]: string };
-  default_value: number;
 };
 
 export type FieldKind =
@@ -2,3 +2,4 @@ export * from "./base.js";
 export * from "./dataManagement.js";
 export * from "./dashboard.js";
 export * from "./user.js"
+export * from "./jobs.js";
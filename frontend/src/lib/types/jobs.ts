@@ -0,0 +1,34 @@
+// Job
+export enum JobStatus {
+  Pending = "Pending",
+  Running = "Running",
+  Completed = "Completed",
+  Failed = "Failed",
+  Cancelled = "Cancelled",
+}
+
+export type JobKind =
+  | { type: "ImportExcel" }
+  | { type: "ImportCsv"; name: string }
+  | { type: "ExportExcel"; table_id: number }
+  | { type: "ExportCsv"; table_id: number }
+  | { type: "RefreshChart"; dashboard_id: number; chart_id: number };
+
+export type JobOutput =
+  | { type: "Imported"; table_ids: number[] }
+  | { type: "Exported"; file_name: string; content_type: string };
+
+export type Job = {
+  job_id: number;
+  user_id: number;
+  job: JobKind;
+  status: JobStatus;
+  progress: number;
+  attempts: number;
+  output: JobOutput | null;
+  error: string | null;
+  started_at: string | null;
+  finished_at: string | null;
+  created_at: string;
+  updated_at: string | null;
+};